@@ -4,6 +4,11 @@ use std::io::Write;
 
 #[cfg(not(tarpaulin_include))]
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("fmt") {
+        fmt_main();
+        return;
+    }
+
     let mut input = "-".to_owned();
     let mut output = "-".to_owned();
     let mut no_html = false;
@@ -12,6 +17,16 @@ fn main() {
     let mut typographer = false;
     let mut sourcepos = false;
     let mut show_tree = false;
+    let mut format = "html".to_owned();
+    let mut width = 0;
+    #[cfg(feature = "syntect")]
+    let mut highlight_classes = false;
+    #[cfg(feature = "syntect")]
+    let mut theme_css = String::new();
+    #[cfg(feature = "syntect")]
+    let mut theme_css_dark = String::new();
+    #[cfg(feature = "tree-sitter")]
+    let mut tree_sitter = false;
 
     {
         let mut cli = argparse::ArgumentParser::new();
@@ -39,10 +54,38 @@ fn main() {
             .refer(&mut typographer)
             .add_option(&["-t", "--typographer"], argparse::StoreTrue, "Enable smartquotes and other typographic replacements");
 
+        cli
+            .refer(&mut format)
+            .add_option(&["-f", "--format"], argparse::Store, "Output format: html, ansi, plain, latex, man or gemtext");
+
+        cli
+            .refer(&mut width)
+            .add_option(&["-w", "--width"], argparse::Store, "Wrap text at this width in ansi output (default: $COLUMNS or 80)");
+
         cli
             .refer(&mut show_tree)
             .add_option(&["--tree"], argparse::StoreTrue, "Print syntax tree for debugging");
 
+        #[cfg(feature = "syntect")]
+        cli
+            .refer(&mut highlight_classes)
+            .add_option(&["--highlight-classes"], argparse::StoreTrue, "Use css classes instead of inline styles for syntax highlighting");
+
+        #[cfg(feature = "syntect")]
+        cli
+            .refer(&mut theme_css)
+            .add_option(&["--theme-css"], argparse::Store, "Print css for syntax highlighting theme and exit");
+
+        #[cfg(feature = "syntect")]
+        cli
+            .refer(&mut theme_css_dark)
+            .add_option(&["--theme-css-dark"], argparse::Store, "Add dark theme to --theme-css output");
+
+        #[cfg(feature = "tree-sitter")]
+        cli
+            .refer(&mut tree_sitter)
+            .add_option(&["--tree-sitter"], argparse::StoreTrue, "Use tree-sitter for syntax highlighting");
+
         cli
             .refer(&mut input)
             .add_argument("file", argparse::Store, "File to read");
@@ -50,6 +93,22 @@ fn main() {
         cli.parse_args_or_exit();
     }
 
+    #[cfg(feature = "syntect")]
+    if !theme_css.is_empty() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::extra::syntect::add(md);
+        let dark = if theme_css_dark.is_empty() { None } else { Some(theme_css_dark.as_str()) };
+        if let Some(css) = markdown_it::plugins::extra::syntect::stylesheet(md, &theme_css, dark) {
+            std::io::stdout().write_all(css.as_bytes()).unwrap();
+        } else {
+            eprintln!("Unknown theme, available themes are: {}",
+                markdown_it::plugins::extra::syntect::default_theme_set().themes.keys()
+                    .map(|s| s.as_str()).collect::<Vec<_>>().join(", "));
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let vec = if input == "-" {
         let mut vec = Vec::new();
         std::io::stdin().read_to_end(&mut vec).unwrap();
@@ -63,6 +122,14 @@ fn main() {
     markdown_it::plugins::cmark::add(md);
     #[cfg(feature = "syntect")]
     markdown_it::plugins::extra::syntect::add(md);
+    #[cfg(feature = "syntect")]
+    if highlight_classes {
+        markdown_it::plugins::extra::syntect::set_class_style(md, syntect::html::ClassStyle::Spaced);
+    }
+    #[cfg(feature = "tree-sitter")]
+    if tree_sitter {
+        markdown_it::plugins::extra::tree_sitter::add(md);
+    }
     markdown_it::plugins::extra::tables::add(md);
     markdown_it::plugins::extra::strikethrough::add(md);
     markdown_it::plugins::extra::beautify_links::add(md);
@@ -98,11 +165,149 @@ fn main() {
         return;
     }
 
-    let result = ast.render();
+    let text = match format.as_str() {
+        "html" => None,
+        "ansi" => {
+            if width == 0 {
+                width = std::env::var("COLUMNS").ok().and_then(|s| s.parse().ok()).unwrap_or(80);
+            }
+            let options = markdown_it::renderers::ansi::AnsiOptions {
+                width,
+                #[cfg(feature = "syntect")]
+                syntax_set: Some(markdown_it::plugins::extra::syntect::syntax_set(md)),
+                #[cfg(feature = "syntect")]
+                theme_set: Some(markdown_it::plugins::extra::syntect::theme_set(md)),
+                ..Default::default()
+            };
+            Some(markdown_it::renderers::ansi::AnsiRenderer::new(options).render(&ast))
+        }
+        "plain" => Some(markdown_it::renderers::plain::render(&ast)),
+        "gemtext" => Some(markdown_it::renderers::gemtext::render(&ast)),
+        "man" => Some(markdown_it::renderers::roff::render(&ast)),
+        "latex" => match markdown_it::renderers::latex::render(&ast) {
+            Ok(text) => Some(text),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Unknown format {:?}, available formats are: html, ansi, plain, latex, man, gemtext", format);
+            std::process::exit(1);
+        }
+    };
 
     if output == "-" {
-        std::io::stdout().write_all(result.as_bytes()).unwrap();
+        let mut stdout = std::io::stdout().lock();
+        match text {
+            Some(text) => stdout.write_all(text.as_bytes()).unwrap(),
+            None => ast.render_to(&mut stdout).unwrap(),
+        }
+    } else {
+        let mut file = std::io::BufWriter::new(std::fs::File::create(output).unwrap());
+        match text {
+            Some(text) => file.write_all(text.as_bytes()).unwrap(),
+            None => ast.render_to(&mut file).unwrap(),
+        }
+        file.flush().unwrap();
+    }
+}
+
+// `markdown-it fmt [files]`, rewrites markdown in canonical style
+#[cfg(not(tarpaulin_include))]
+fn fmt_main() {
+    use markdown_it::renderers::markdown::{format, MarkdownOptions, Wrap};
+
+    let mut files : Vec<String> = Vec::new();
+    let mut width = 0;
+    let mut unwrap = false;
+    let mut check = false;
+    let mut write = false;
+
+    {
+        let mut cli = argparse::ArgumentParser::new();
+        cli.set_description("Rewrite markdown in canonical style.");
+
+        cli
+            .refer(&mut width)
+            .add_option(&["-w", "--width"], argparse::Store, "Wrap paragraphs at this width (default: keep line breaks)");
+
+        cli
+            .refer(&mut unwrap)
+            .add_option(&["--unwrap"], argparse::StoreTrue, "Join each paragraph into a single line");
+
+        cli
+            .refer(&mut check)
+            .add_option(&["--check"], argparse::StoreTrue, "Don't write anything, exit with error if files aren't formatted");
+
+        cli
+            .refer(&mut write)
+            .add_option(&["--write"], argparse::StoreTrue, "Write formatted files in place");
+
+        cli
+            .refer(&mut files)
+            .add_argument("files", argparse::List, "Files to format (stdin if none)");
+
+        let args = std::iter::once("markdown-it fmt".to_owned()).chain(std::env::args().skip(2)).collect();
+        if let Err(code) = cli.parse(args, &mut std::io::stdout(), &mut std::io::stderr()) {
+            std::process::exit(code);
+        }
+    }
+
+    let md = &mut markdown_it::MarkdownIt::new();
+    markdown_it::plugins::cmark::add(md);
+    markdown_it::plugins::extra::tables::add(md);
+    markdown_it::plugins::extra::strikethrough::add(md);
+    markdown_it::plugins::html::add(md);
+
+    let wrap = if unwrap {
+        Wrap::Never
+    } else if width != 0 {
+        Wrap::Width(width)
     } else {
-        std::fs::write(output, &result).unwrap();
+        Wrap::Preserve
+    };
+    let options = MarkdownOptions { wrap, ..Default::default() };
+
+    if files.is_empty() {
+        files.push("-".to_owned());
+    }
+
+    let mut failed = false;
+    for file in files {
+        let vec = if file == "-" {
+            let mut vec = Vec::new();
+            std::io::stdin().read_to_end(&mut vec).unwrap();
+            vec
+        } else {
+            std::fs::read(&file).unwrap()
+        };
+        let source = String::from_utf8_lossy(&vec);
+
+        let result = match format(md, &source, options.clone()) {
+            Ok(result) => result,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                failed = true;
+                continue;
+            }
+        };
+
+        if check {
+            if result != source {
+                eprintln!("{}: not formatted", file);
+                failed = true;
+            }
+        } else if write && file != "-" {
+            if result != source {
+                std::fs::write(&file, result).unwrap();
+            }
+        } else {
+            std::io::stdout().write_all(result.as_bytes()).unwrap();
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
     }
 }
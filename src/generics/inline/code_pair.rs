@@ -34,11 +34,11 @@
 //! This generic structure follows exact rules of code span in CommonMark:
 //!
 //! 1. Literal marker character sequence can be used inside of structure if its length
-//! doesn't match length of the opening/closing sequence (e.g. with `%` defined
-//! as a marker, `%%foo%bar%%` gets parsed as `Node("foo%bar")`).
+//!    doesn't match length of the opening/closing sequence (e.g. with `%` defined
+//!    as a marker, `%%foo%bar%%` gets parsed as `Node("foo%bar")`).
 //!
 //! 2. Single space inside is trimmed to allow you to write `% %%foo %` to be parsed as
-//! `Node("%%foo")`.
+//!    `Node("%%foo")`.
 //!
 //! If you define two structures with the same marker, only the first one will work.
 //!
@@ -134,7 +134,7 @@ impl<const MARKER: char> InlineRule for CodePairScanner<MARKER> {
         }
 
         // Scanned through the end, didn't find anything
-        let mut backticks = state.inline_ext.get_mut::<CodePairCache<MARKER>>().unwrap();
+        let backticks = state.inline_ext.get_mut::<CodePairCache<MARKER>>().unwrap();
         backticks.scanned = true;
 
         None
@@ -127,9 +127,9 @@ impl<const MARKER: char, const CAN_SPLIT_WORD: bool> InlineRule for EmphPairScan
         node = scan_and_match_delimiters::<MARKER>(state, node);
         let map = node.srcmap.unwrap().get_byte_offsets();
         // backtrack to keep correct source maps
-        state.pos += scanned.length;
-        let token_len = map.1 - map.0;
-        state.pos -= token_len;
+        let start = state.get_content_pos_for(map.0);
+        let token_len = state.pos + scanned.length - start;
+        state.pos = start;
         Some((node, token_len))
     }
 }
@@ -200,7 +200,7 @@ fn scan_and_match_delimiters<const MARKER: char>(state: &mut InlineState, mut cl
                         start_map_pos = end - marker_len;
                     }
 
-                    new_token.srcmap = state.get_map(start_map_pos, end_map_pos);
+                    new_token.srcmap = Some(SourcePos::new(start_map_pos, end_map_pos));
 
                     // remove empty node as a small optimization so we can do less work later
                     if opener.remaining == 0 { state.node.children.pop(); }
@@ -249,8 +249,8 @@ fn is_odd_match(opener: &EmphMarker, closer: &EmphMarker) -> bool {
     //
     #[allow(clippy::collapsible_if)]
     if opener.close || closer.open {
-        if (opener.length + closer.length) % 3 == 0 {
-            if opener.length % 3 != 0 || closer.length % 3 != 0 {
+        if (opener.length + closer.length).is_multiple_of(3) {
+            if !opener.length.is_multiple_of(3) || !closer.length.is_multiple_of(3) {
                 return true;
             }
         }
@@ -263,6 +263,8 @@ fn is_odd_match(opener: &EmphMarker, closer: &EmphMarker) -> bool {
 #[doc(hidden)]
 pub struct FragmentsJoin;
 impl CoreRule for FragmentsJoin {
+    const INCREMENTAL: bool = true;
+
     fn run(node: &mut Node, _: &MarkdownIt) {
         node.walk_mut(|node, _| fragments_join(node));
     }
@@ -0,0 +1,721 @@
+//! Convert HTML fragment into the AST.
+//!
+//! Html elements are converted to the nodes from [cmark](crate::plugins::cmark)
+//! and [extra](crate::plugins::extra) plugins: `<p>` becomes [Paragraph], `<em>` and `<i>`
+//! become [Em], `<table>` becomes [Table] and so on. Wrappers that don't mean
+//! anything in markdown (`<div>`, `<span>`, `<section>`, ...) are skipped, but their
+//! contents is kept. Other elements are kept as raw html: block ones become [HtmlBlock]
+//! with their whole source, inline ones become [HtmlInline] for opening and closing
+//! tags with converted contents in between (same as markdown parser does).
+//!
+//! Link and image urls are normalized and validated the same way as in markdown,
+//! links with disallowed urls (e.g. `javascript:`) are replaced with their text,
+//! and images with their alt text.
+//!
+//! Parser is forgiving and meant for html produced by WYSIWYG editors: unclosed
+//! `<p>`, `<li>` and table cells are closed automatically, unknown closing tags are
+//! ignored. Whitespace is collapsed the same way browsers do it, except in `<pre>`.
+//! Nodes don't have source maps, since they don't correspond to any markdown source.
+//!
+//! Text (with entities decoded) is stored in [Text] nodes, use
+//! [markdown renderer](crate::renderers::markdown) to get markdown source, it escapes
+//! text so that e.g. `&lt;script&gt;` stays literal:
+//!
+//! ```rust
+//! let ast = markdown_it::importers::html::parse("<p>&lt;b&gt; *not bold*</p>");
+//! assert_eq!(markdown_it::renderers::markdown::render(&ast), "\\<b\\> \\*not bold\\*\n");
+//! ```
+//!
+//! ```rust
+//! let ast = markdown_it::importers::html::parse("<p>Hello <b>world</b></p><ul><li>one<li>two</ul>");
+//! assert_eq!(ast.render(), "<p>Hello <strong>world</strong></p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n");
+//! ```
+use std::borrow::Cow;
+use std::ops::Range;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::Node;
+use crate::common::utils::{get_entity_from_str, is_valid_entity_code};
+use crate::parser::core::Root;
+use crate::parser::inline::Text;
+use crate::parser::linkfmt::{LinkFormatter, MDLinkFormatter};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::fence::{CodeFence, FenceInfo};
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::list::{BulletList, ListItem, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::Hardbreak;
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableBody, TableCell, TableHead, TableRow};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use crate::plugins::html::utils::blocks::HTML_BLOCKS;
+
+/// Parse html fragment (or a whole document), return [Root] node with the converted contents.
+pub fn parse(html: &str) -> Node {
+    let dom = DomBuilder::new(html).build();
+    let mut converter = Converter { html, space: true };
+    let mut root = Node::new(Root::new(String::new()));
+    root.children = converter.blocks(&dom.children, true);
+    root
+}
+
+// elements that have no closing tag
+const VOID_ELEMENTS : [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input",
+    "link", "meta", "param", "source", "track", "wbr",
+];
+
+// elements with contents that isn't parsed as html
+const RAW_TEXT_ELEMENTS : [&str; 4] = [ "script", "style", "textarea", "title" ];
+
+// block elements that are replaced with their contents
+const TRANSPARENT_BLOCKS : [&str; 10] = [
+    "article", "body", "center", "div", "footer", "header", "html", "main", "nav", "section",
+];
+
+// inline elements that are replaced with their contents
+const TRANSPARENT_INLINES : [&str; 3] = [ "font", "span", "a" ];
+
+// elements that close an open `<p>`
+const CLOSES_PARAGRAPH : [&str; 20] = [
+    "address", "article", "aside", "blockquote", "div", "dl", "fieldset", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "ol", "pre", "table",
+];
+
+static ENTITY_RE : Lazy<Regex> = Lazy::new(||
+    Regex::new(r#"&(?:#[xX]([0-9a-fA-F]{1,8});|#([0-9]{1,8});|[A-Za-z][A-Za-z0-9]{1,31};)"#).unwrap()
+);
+
+// decode html entities, invalid numeric ones become U+FFFD like in browsers
+fn decode_entities(str: &str) -> Cow<'_, str> {
+    if !str.contains('&') { return Cow::Borrowed(str); }
+
+    ENTITY_RE.replace_all(str, |captures: &regex::Captures| {
+        let code = if let Some(hex) = captures.get(1) {
+            u32::from_str_radix(hex.as_str(), 16).ok()
+        } else if let Some(dec) = captures.get(2) {
+            dec.as_str().parse().ok()
+        } else {
+            let s = captures.get(0).unwrap().as_str();
+            return get_entity_from_str(s).unwrap_or(s).to_owned();
+        };
+
+        match code {
+            Some(code) if is_valid_entity_code(code) => char::from_u32(code).unwrap().to_string(),
+            _ => '\u{FFFD}'.to_string(),
+        }
+    })
+}
+
+#[derive(Debug)]
+enum Dom {
+    Element(Element),
+    Text(String),
+    // source range of the whole comment
+    Comment(Range<usize>),
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Dom>,
+    // source range of the opening tag
+    open_tag: Range<usize>,
+    // source range of the closing tag, if it was present
+    close_tag: Option<Range<usize>>,
+    // source range of the whole element
+    outer: Range<usize>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str())
+    }
+
+    fn child_elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Dom::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+}
+
+enum Tag {
+    Open { name: String, attrs: Vec<(String, String)>, self_closing: bool },
+    Close { name: String },
+    Comment,
+    // doctype, processing instructions
+    Other,
+}
+
+struct DomBuilder<'a> {
+    html: &'a str,
+    pos: usize,
+    stack: Vec<Element>,
+}
+
+impl<'a> DomBuilder<'a> {
+    fn new(html: &'a str) -> Self {
+        let root = Element {
+            name: String::new(),
+            attrs: Vec::new(),
+            children: Vec::new(),
+            open_tag: 0..0,
+            close_tag: None,
+            outer: 0..html.len(),
+        };
+        Self { html, pos: 0, stack: vec![root] }
+    }
+
+    fn build(mut self) -> Element {
+        let mut text_start = 0;
+
+        while let Some(offset) = self.html[self.pos..].find('<') {
+            let start = self.pos + offset;
+            let Some((tag, end)) = self.scan_tag(start) else {
+                self.pos = start + 1;
+                continue;
+            };
+
+            self.text(text_start..start);
+            self.pos = end;
+            match tag {
+                Tag::Open { name, attrs, self_closing } => self.open(name, attrs, start..end, self_closing),
+                Tag::Close { name } => self.close(&name, start..end),
+                Tag::Comment => self.top().children.push(Dom::Comment(start..end)),
+                Tag::Other => {}
+            }
+            text_start = self.pos;
+        }
+
+        self.text(text_start..self.html.len());
+        let len = self.html.len();
+        while self.stack.len() > 1 {
+            self.pop(len, None);
+        }
+        self.stack.pop().unwrap()
+    }
+
+    fn top(&mut self) -> &mut Element {
+        self.stack.last_mut().unwrap()
+    }
+
+    fn text(&mut self, range: Range<usize>) {
+        if range.is_empty() { return; }
+        let text = decode_entities(&self.html[range]).into_owned();
+        self.top().children.push(Dom::Text(text));
+    }
+
+    fn open(&mut self, name: String, attrs: Vec<(String, String)>, range: Range<usize>, self_closing: bool) {
+        let at = range.start;
+        match name.as_str() {
+            "li" => self.close_implied(&["li"], &["ul", "ol"], at),
+            "dt" | "dd" => self.close_implied(&["dt", "dd"], &["dl"], at),
+            "td" | "th" => self.close_implied(&["td", "th"], &["tr", "table"], at),
+            "tr" => self.close_implied(&["tr"], &["thead", "tbody", "tfoot", "table"], at),
+            "thead" | "tbody" | "tfoot" => self.close_implied(&["thead", "tbody", "tfoot"], &["table"], at),
+            _ => {}
+        }
+        if CLOSES_PARAGRAPH.contains(&name.as_str()) || name == "p" || name == "li" {
+            self.close_implied(&["p"], &["td", "th", "table", "button"], at);
+        }
+
+        let mut element = Element {
+            children: Vec::new(),
+            open_tag: range.clone(),
+            close_tag: None,
+            outer: range.start..range.end,
+            name,
+            attrs,
+        };
+
+        if self_closing || VOID_ELEMENTS.contains(&element.name.as_str()) {
+            self.top().children.push(Dom::Element(element));
+            return;
+        }
+
+        if RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) {
+            let rest = &self.html[self.pos..];
+            let end = rest.to_ascii_lowercase().find(&format!("</{}", element.name)).unwrap_or(rest.len());
+            if end > 0 {
+                let text = &rest[..end];
+                let text = if element.name == "script" || element.name == "style" {
+                    text.to_owned()
+                } else {
+                    decode_entities(text).into_owned()
+                };
+                element.children.push(Dom::Text(text));
+            }
+            self.pos += end;
+        }
+
+        self.stack.push(element);
+    }
+
+    fn close(&mut self, name: &str, range: Range<usize>) {
+        let Some(idx) = self.stack.iter().rposition(|el| el.name == name) else { return; };
+        if idx == 0 { return; }
+        while self.stack.len() > idx + 1 {
+            self.pop(range.start, None);
+        }
+        self.pop(range.end, Some(range));
+    }
+
+    // close element named one of `names` (and everything inside it) at position `at`
+    // if it is open, unless one of `boundaries` is found first
+    fn close_implied(&mut self, names: &[&str], boundaries: &[&str], at: usize) {
+        for idx in (1..self.stack.len()).rev() {
+            let name = self.stack[idx].name.as_str();
+            if boundaries.contains(&name) { return; }
+            if names.contains(&name) {
+                while self.stack.len() > idx {
+                    self.pop(at, None);
+                }
+                return;
+            }
+        }
+    }
+
+    fn pop(&mut self, end: usize, close_tag: Option<Range<usize>>) {
+        let mut element = self.stack.pop().unwrap();
+        element.outer.end = end.max(element.outer.start);
+        element.close_tag = close_tag;
+        self.top().children.push(Dom::Element(element));
+    }
+
+    // try to read a tag at `start` (pointing at `<`), return it and position after it
+    fn scan_tag(&self, start: usize) -> Option<(Tag, usize)> {
+        let rest = &self.html[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map(|pos| start + 4 + pos + 3).unwrap_or(self.html.len());
+            return Some((Tag::Comment, end));
+        }
+
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>')?;
+            return Some((Tag::Other, start + end + 1));
+        }
+
+        let (closing, name_start) = if rest.starts_with("</") { (true, 2) } else { (false, 1) };
+        let name_len = rest[name_start..].find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(rest.len() - name_start);
+        if name_len == 0 || !rest[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+        let mut pos = name_start + name_len;
+
+        if closing {
+            let end = rest[pos..].find('>')?;
+            return Some((Tag::Close { name }, start + pos + end + 1));
+        }
+
+        let bytes = rest.as_bytes();
+        let mut attrs = Vec::new();
+        loop {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() { pos += 1; }
+            match bytes.get(pos)? {
+                b'>' => {
+                    return Some((Tag::Open { name, attrs, self_closing: false }, start + pos + 1));
+                }
+                b'/' if bytes.get(pos + 1) == Some(&b'>') => {
+                    return Some((Tag::Open { name, attrs, self_closing: true }, start + pos + 2));
+                }
+                b'/' => {
+                    pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let attr_len = rest[pos..].find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+                .unwrap_or(rest.len() - pos);
+            let attr_name = rest[pos..pos + attr_len].to_ascii_lowercase();
+            pos += attr_len.max(1);
+
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() { pos += 1; }
+            let mut value = String::new();
+            if bytes.get(pos) == Some(&b'=') {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos].is_ascii_whitespace() { pos += 1; }
+                let raw = match bytes.get(pos)? {
+                    quote @ (b'"' | b'\'') => {
+                        let len = rest[pos + 1..].find(*quote as char)?;
+                        let raw = &rest[pos + 1..pos + 1 + len];
+                        pos += len + 2;
+                        raw
+                    }
+                    _ => {
+                        let len = rest[pos..].find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(rest.len() - pos);
+                        let raw = &rest[pos..pos + len];
+                        pos += len;
+                        raw
+                    }
+                };
+                value = decode_entities(raw).into_owned();
+            }
+            attrs.push((attr_name, value));
+        }
+    }
+}
+
+struct Converter<'a> {
+    html: &'a str,
+    // last emitted inline character was whitespace (or nothing was emitted yet)
+    space: bool,
+}
+
+impl Converter<'_> {
+    // convert contents of a block container, inline contents between blocks
+    // is wrapped in paragraphs if `wrap` is set (it isn't for tight list items)
+    fn blocks(&mut self, children: &[Dom], wrap: bool) -> Vec<Node> {
+        let mut result = Vec::new();
+        let mut inlines = Vec::new();
+        self.space = true;
+
+        for child in children.iter() {
+            match child {
+                Dom::Element(element) if is_block_element(&element.name) => {
+                    self.flush(&mut result, &mut inlines, wrap);
+                    self.block(element, &mut result);
+                    self.space = true;
+                }
+                Dom::Comment(range) if inlines.is_empty() => {
+                    result.push(Node::new(HtmlBlock { content: format!("{}\n", &self.html[range.clone()]) }));
+                }
+                _ => self.inline(child, &mut inlines),
+            }
+        }
+
+        self.flush(&mut result, &mut inlines, wrap);
+        result
+    }
+
+    fn flush(&mut self, result: &mut Vec<Node>, inlines: &mut Vec<Node>, wrap: bool) {
+        trim_end(inlines);
+        if inlines.is_empty() { return; }
+
+        if wrap {
+            let mut paragraph = Node::new(Paragraph);
+            paragraph.children = std::mem::take(inlines);
+            result.push(paragraph);
+        } else {
+            result.append(inlines);
+        }
+    }
+
+    fn block(&mut self, element: &Element, result: &mut Vec<Node>) {
+        let name = element.name.as_str();
+        match name {
+            "p" => {
+                let mut node = Node::new(Paragraph);
+                node.children = self.inlines(&element.children);
+                if !node.children.is_empty() { result.push(node); }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let mut node = Node::new(ATXHeading { level: name.as_bytes()[1] - b'0' });
+                node.children = self.inlines(&element.children);
+                result.push(node);
+            }
+            "blockquote" => {
+                let mut node = Node::new(Blockquote);
+                node.children = self.blocks(&element.children, true);
+                result.push(node);
+            }
+            "ul" | "ol" => {
+                let mut node = if name == "ul" {
+                    Node::new(BulletList { marker: '-' })
+                } else {
+                    let start = element.attr("start").and_then(|s| s.trim().parse().ok()).unwrap_or(1);
+                    Node::new(OrderedList { start, marker: '.' })
+                };
+                // list is loose if any of its items contain paragraphs
+                let loose = element.child_elements()
+                    .any(|item| item.child_elements().any(|child| child.name == "p"));
+                for child in element.children.iter() {
+                    let item_children = match child {
+                        Dom::Element(item) if item.name == "li" => self.blocks(&item.children, loose),
+                        Dom::Text(text) if text.trim().is_empty() => continue,
+                        _ => self.blocks(std::slice::from_ref(child), loose),
+                    };
+                    let mut item = Node::new(ListItem);
+                    item.children = item_children;
+                    node.children.push(item);
+                }
+                result.push(node);
+            }
+            "pre" => {
+                let code = element.child_elements().find(|child| child.name == "code");
+                let lang = code.into_iter().chain([element])
+                    .filter_map(|el| el.attr("class"))
+                    .flat_map(|class| class.split_whitespace())
+                    .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+                    .unwrap_or_default();
+                let mut content = String::new();
+                raw_text(&element.children, &mut content);
+                if content.starts_with('\n') { content.remove(0); }
+                if !content.is_empty() && !content.ends_with('\n') { content.push('\n'); }
+                result.push(Node::new(CodeFence {
+                    info: lang.to_owned(),
+                    parsed_info: FenceInfo::parse(lang),
+                    marker: '`',
+                    marker_len: 3,
+                    content,
+                    lang_prefix: "language-",
+                    line_wrappers: false,
+                }));
+            }
+            "hr" => {
+                result.push(Node::new(ThematicBreak { marker: '-', marker_len: 3 }));
+            }
+            "table" => {
+                if let Some(node) = self.table(element) { result.push(node); }
+            }
+            "head" => {}
+            _ if TRANSPARENT_BLOCKS.contains(&name) => {
+                result.extend(self.blocks(&element.children, true));
+            }
+            _ => {
+                let content = format!("{}\n", &self.html[element.outer.clone()]);
+                result.push(Node::new(HtmlBlock { content }));
+            }
+        }
+    }
+
+    // convert inline contents of a block, trimming whitespace around it
+    fn inlines(&mut self, children: &[Dom]) -> Vec<Node> {
+        let mut result = Vec::new();
+        self.space = true;
+        for child in children.iter() {
+            self.inline(child, &mut result);
+        }
+        trim_end(&mut result);
+        result
+    }
+
+    fn inline(&mut self, dom: &Dom, result: &mut Vec<Node>) {
+        let element = match dom {
+            Dom::Text(text) => {
+                let mut content = String::with_capacity(text.len());
+                for ch in text.chars() {
+                    if ch.is_ascii_whitespace() {
+                        if !self.space { content.push(' '); }
+                        self.space = true;
+                    } else {
+                        content.push(ch);
+                        self.space = false;
+                    }
+                }
+                if !content.is_empty() {
+                    result.push(Node::new(Text { content }));
+                }
+                return;
+            }
+            Dom::Comment(range) => {
+                result.push(Node::new(HtmlInline { content: self.html[range.clone()].to_owned() }));
+                return;
+            }
+            Dom::Element(element) => element,
+        };
+
+        let name = element.name.as_str();
+        let href = if name == "a" { element.attr("href").and_then(link_url) } else { None };
+        let mut node = match name {
+            "em" | "i" => Node::new(Em { marker: '*' }),
+            "strong" | "b" => Node::new(Strong { marker: '*' }),
+            "del" | "s" | "strike" => Node::new(Strikethrough { marker: '~' }),
+            // links with urls that aren't allowed are replaced with their contents
+            "a" if href.is_some() => Node::new(Link {
+                url: href.unwrap_or_default(),
+                title: element.attr("title").map(|s| s.to_owned()),
+            }),
+            "code" | "kbd" | "samp" | "tt" => {
+                let mut content = String::new();
+                raw_text(&element.children, &mut content);
+                let content = content.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+                let mut node = Node::new(CodeInline { marker: '`', marker_len: 1 });
+                node.children.push(Node::new(Text { content }));
+                result.push(node);
+                self.space = false;
+                return;
+            }
+            "img" => {
+                let alt = element.attr("alt").filter(|alt| !alt.is_empty());
+                let Some(url) = link_url(element.attr("src").unwrap_or_default()) else {
+                    // images with urls that aren't allowed are replaced with their alt text
+                    if let Some(alt) = alt {
+                        result.push(Node::new(Text { content: alt.to_owned() }));
+                        self.space = false;
+                    }
+                    return;
+                };
+                let mut node = Node::new(Image {
+                    url,
+                    title: element.attr("title").map(|s| s.to_owned()),
+                });
+                if let Some(alt) = alt {
+                    node.children.push(Node::new(Text { content: alt.to_owned() }));
+                }
+                result.push(node);
+                self.space = false;
+                return;
+            }
+            "br" => {
+                result.push(Node::new(Hardbreak));
+                self.space = true;
+                return;
+            }
+            _ if TRANSPARENT_INLINES.contains(&name) => {
+                for child in element.children.iter() {
+                    self.inline(child, result);
+                }
+                return;
+            }
+            _ if is_block_element(name) => {
+                // block inside of inline context (e.g. paragraphs in table cells)
+                if !self.space { result.push(Node::new(Text { content: " ".to_owned() })); }
+                self.space = true;
+                for child in element.children.iter() {
+                    self.inline(child, result);
+                }
+                if !self.space { result.push(Node::new(Text { content: " ".to_owned() })); }
+                self.space = true;
+                return;
+            }
+            _ if RAW_TEXT_ELEMENTS.contains(&name) => {
+                result.push(Node::new(HtmlInline { content: self.html[element.outer.clone()].to_owned() }));
+                return;
+            }
+            _ => {
+                result.push(Node::new(HtmlInline { content: self.html[element.open_tag.clone()].to_owned() }));
+                for child in element.children.iter() {
+                    self.inline(child, result);
+                }
+                if let Some(close_tag) = &element.close_tag {
+                    result.push(Node::new(HtmlInline { content: self.html[close_tag.clone()].to_owned() }));
+                }
+                return;
+            }
+        };
+
+        for child in element.children.iter() {
+            self.inline(child, &mut node.children);
+        }
+        if !node.children.is_empty() {
+            result.push(node);
+        }
+    }
+
+    // first row becomes table head, since markdown tables can't exist without one
+    fn table(&mut self, element: &Element) -> Option<Node> {
+        let mut rows = Vec::new();
+        for child in element.child_elements() {
+            match child.name.as_str() {
+                "tr" => rows.push(child),
+                "thead" | "tbody" | "tfoot" => rows.extend(child.child_elements().filter(|el| el.name == "tr")),
+                _ => {}
+            }
+        }
+
+        let (head, body) = rows.split_first()?;
+        let head = cells(head);
+        if head.is_empty() { return None; }
+
+        let alignments = head.iter().map(|cell| {
+            let style = cell.attr("style").unwrap_or_default().to_ascii_lowercase().replace(' ', "");
+            let align = cell.attr("align").map(|s| s.to_ascii_lowercase());
+            let align = align.as_deref().or_else(|| {
+                style.split(';').find_map(|decl| decl.strip_prefix("text-align:"))
+            });
+            match align {
+                Some("left") => ColumnAlignment::Left,
+                Some("right") => ColumnAlignment::Right,
+                Some("center") => ColumnAlignment::Center,
+                _ => ColumnAlignment::None,
+            }
+        }).collect::<Vec<_>>();
+
+        let mut row_node = |cells: Vec<&Element>| {
+            let mut row = Node::new(TableRow);
+            for idx in 0..head.len() {
+                let mut cell = Node::new(TableCell);
+                if let Some(el) = cells.get(idx) {
+                    cell.children = self.inlines(&el.children);
+                }
+                row.children.push(cell);
+            }
+            row
+        };
+
+        let mut thead = Node::new(TableHead);
+        thead.children.push(row_node(head.clone()));
+        let mut tbody = Node::new(TableBody);
+        for row in body.iter() {
+            tbody.children.push(row_node(cells(row)));
+        }
+
+        let mut table = Node::new(Table { alignments });
+        table.children.push(thead);
+        if !tbody.children.is_empty() {
+            table.children.push(tbody);
+        }
+        Some(table)
+    }
+}
+
+fn cells(row: &Element) -> Vec<&Element> {
+    row.child_elements().filter(|el| el.name == "td" || el.name == "th").collect()
+}
+
+fn is_block_element(name: &str) -> bool {
+    name == "pre" || HTML_BLOCKS.contains(&name)
+}
+
+// normalize and validate url the same way markdown parser does,
+// `None` if it isn't allowed (e.g. `javascript:` urls)
+fn link_url(url: &str) -> Option<String> {
+    let formatter = MDLinkFormatter::new();
+    let url = formatter.normalize_link(url);
+    formatter.validate_link(&url)?;
+    Some(url)
+}
+
+// text contents without whitespace collapsing (for code)
+fn raw_text(children: &[Dom], result: &mut String) {
+    for child in children.iter() {
+        match child {
+            Dom::Text(text) => result.push_str(text),
+            Dom::Element(element) if element.name == "br" => result.push('\n'),
+            Dom::Element(element) => raw_text(&element.children, result),
+            Dom::Comment(_) => {}
+        }
+    }
+}
+
+// remove trailing whitespace of inline contents (including nested emphasis and links)
+fn trim_end(nodes: &mut Vec<Node>) {
+    while let Some(last) = nodes.last_mut() {
+        if let Some(text) = last.cast_mut::<Text>() {
+            let len = text.content.trim_end_matches(|c: char| c.is_ascii_whitespace()).len();
+            if len > 0 {
+                text.content.truncate(len);
+                return;
+            }
+        } else if last.is::<CodeInline>() || last.is::<Image>() || last.children.is_empty() {
+            return;
+        } else {
+            trim_end(&mut last.children);
+            if !last.children.is_empty() { return; }
+        }
+        nodes.pop();
+    }
+}
@@ -0,0 +1,6 @@
+//! Converters from other formats into the AST.
+//!
+//! Resulting trees are made of the same nodes markdown parser produces, so they
+//! can be rendered by any renderer, or modified by the same code that works
+//! with parsed markdown.
+pub mod html;
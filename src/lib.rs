@@ -4,8 +4,11 @@
 
 pub mod common;
 pub mod generics;
+pub mod importers;
 pub mod parser;
+pub mod patch;
 pub mod plugins;
+pub mod renderers;
 pub mod examples;
 
 pub use parser::node::{Node, NodeValue};
@@ -34,12 +34,20 @@ impl BlockParser {
     // Generate tokens for input range
     //
     pub fn tokenize(&self, state: &mut BlockState) {
+        self.tokenize_until(state, |_| false);
+    }
+
+    // Same as tokenize, but stops before a block starting at a line for which
+    // `stop` returns true (used to reparse only a part of the document)
+    //
+    pub(crate) fn tokenize_until(&self, state: &mut BlockState, mut stop: impl FnMut(&BlockState) -> bool) {
         stacker::maybe_grow(64*1024, 1024*1024, || {
             let mut has_empty_lines = false;
 
             while state.line < state.line_max {
                 state.line = state.skip_empty_lines(state.line);
                 if state.line >= state.line_max { break; }
+                if stop(state) { break; }
 
                 // Termination condition for nested calls.
                 // Nested calls currently used for blockquotes & lists
@@ -112,7 +120,7 @@ impl BlockParser {
         state.node
     }
 
-    pub fn add_rule<T: BlockRule>(&mut self) -> RuleBuilder<RuleFns> {
+    pub fn add_rule<T: BlockRule>(&mut self) -> RuleBuilder<'_, RuleFns> {
         let item = self.ruler.add(TypeKey::of::<T>(), (T::check, T::run));
         RuleBuilder::new(item)
     }
@@ -242,7 +242,7 @@ impl<'a, 'b> BlockState<'a, 'b> {
             );
 
             mapping.push(( result.len(), offsets.line_start+first ));
-            result += &" ".repeat(num_spaces as usize);
+            result += &" ".repeat(num_spaces);
             result += &self.src[offsets.line_start+first..last];
             if add_last_lf { result.push('\n'); }
             line += 1;
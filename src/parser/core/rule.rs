@@ -2,6 +2,11 @@ use crate::{MarkdownIt, Node};
 
 /// Each member of core rule chain must implement this trait
 pub trait CoreRule : 'static {
+    /// Set to `true` if this rule only looks at the nodes it's given, so it can be run
+    /// on reparsed blocks alone, see [MarkdownIt::reparse](crate::MarkdownIt::reparse).
+    /// Rules that don't set it make reparse fall back to parsing the whole document.
+    const INCREMENTAL: bool = false;
+
     fn run(root: &mut Node, md: &MarkdownIt);
 }
 
@@ -123,7 +123,7 @@ impl<'a, 'b> InlineState<'a, 'b> {
             text.content.truncate(text.content.len() - count);
             if let Some(map) = node.srcmap {
                 let (map_start, map_end) = map.get_byte_offsets();
-                let map_end = self.get_source_pos_for(map_end - count);
+                let map_end = self.get_source_pos_for(self.get_content_pos_for(map_end) - count);
                 node.srcmap = Some(SourcePos::new(map_start, map_end));
             }
             self.node.children.push(node);
@@ -232,6 +232,16 @@ impl<'a, 'b> InlineState<'a, 'b> {
         self.srcmap[line].1 + (pos - self.srcmap[line].0)
     }
 
+    // inverse of get_source_pos_for, source position must be inside of inline content
+    #[must_use]
+    pub(crate) fn get_content_pos_for(&self, pos: usize) -> usize {
+        let line = match self.srcmap.binary_search_by(|x| x.1.cmp(&pos)) {
+            Ok(x) => x,
+            Err(x) => x.saturating_sub(1),
+        };
+        self.srcmap[line].0 + pos.saturating_sub(self.srcmap[line].1)
+    }
+
     #[must_use]
     pub fn get_map(&self, start_pos: usize, end_pos: usize) -> Option<SourcePos> {
         debug_assert!(start_pos <= end_pos);
@@ -1,17 +1,23 @@
 use derivative::Derivative;
-use crate::Node;
+use crate::{Node, NodeValue, Renderer};
 use crate::common::TypeKey;
 use crate::common::ruler::Ruler;
 use crate::common::sourcemap::SourcePos;
-use crate::parser::block::{self, BlockParser};
+use crate::parser::block::{self, BlockParser, BlockState};
+use crate::parser::block::builtin::BlockParserRule;
 use crate::parser::inline::{self, InlineParser};
 use crate::parser::extset::MarkdownItExtSet;
 use crate::parser::core::Root;
 use crate::parser::core::*;
 use crate::parser::linkfmt::{LinkFormatter, MDLinkFormatter};
+use crate::parser::renderer::{RenderOptions, RenderOverrides};
+use crate::patch::{self, TextEdit};
 
 type RuleFn = fn (&mut Node, &MarkdownIt);
 
+// rule id (to find block parser when reparsing), rule function, and CoreRule::INCREMENTAL
+type RuleFns = (TypeKey, RuleFn, bool);
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 /// Main parser struct, created once and reused for parsing multiple documents.
@@ -35,7 +41,7 @@ pub struct MarkdownIt {
     #[doc(hidden)]
     pub max_nesting: u32,
 
-    ruler: Ruler<TypeKey, RuleFn>,
+    ruler: Ruler<TypeKey, RuleFns>,
 }
 
 impl MarkdownIt {
@@ -47,15 +53,136 @@ impl MarkdownIt {
         let mut node = Node::new(Root::new(src.to_owned()));
         node.srcmap = Some(SourcePos::new(0, src.len()));
 
-        for rule in self.ruler.iter() {
+        for (_, rule, _) in self.ruler.iter() {
             rule(&mut node, self);
             debug_assert!(node.is::<Root>(), "root node of the AST must always be Root");
         }
         node
     }
 
-    pub fn add_rule<T: CoreRule>(&mut self) -> RuleBuilder<RuleFn> {
-        let item = self.ruler.add(TypeKey::of::<T>(), T::run);
+    /// Parse source of `old` tree with `edit` applied, reusing top-level blocks that
+    /// the edit doesn't affect.
+    ///
+    /// `old` must be the result of [parse](Self::parse) called on `old_src`. Blocks
+    /// are parsed again starting from the one before the edit, until a block starts
+    /// at the same place in the remaining text as it did before; offsets of blocks
+    /// after that are shifted. The result is the same as parsing the new source from scratch.
+    ///
+    /// Whole document is parsed again if reference definitions might have changed
+    /// (links anywhere could depend on them), or if any core rule needs the whole
+    /// document, see [CoreRule::INCREMENTAL].
+    ///
+    /// ```rust
+    /// use markdown_it::patch::TextEdit;
+    ///
+    /// let md = &mut markdown_it::MarkdownIt::new();
+    /// markdown_it::plugins::cmark::add(md);
+    ///
+    /// let source = "# Title\n\nfirst\n\nsecond\n";
+    /// let ast = md.parse(source);
+    /// let ast = md.reparse(ast, source, &TextEdit::new(9..14, "*1st*"));
+    /// assert_eq!(ast.render(), "<h1>Title</h1>\n<p><em>1st</em></p>\n<p>second</p>\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If edit range is out of bounds of `old_src` or isn't on char boundaries.
+    pub fn reparse(&self, old: Node, old_src: &str, edit: &TextEdit) -> Node {
+        let src = patch::apply(old_src, std::slice::from_ref(edit))
+            .expect("edit range must be within the source");
+
+        match self.try_reparse(old, old_src, &src, edit) {
+            Some(node) => node,
+            None => self.parse(&src),
+        }
+    }
+
+    fn try_reparse(&self, mut old: Node, old_src: &str, src: &str, edit: &TextEdit) -> Option<Node> {
+        if !self.ruler.iter().all(|(_, _, incremental)| *incremental) { return None; }
+        if !self.ruler.iter().any(|(key, _, _)| *key == TypeKey::of::<BlockParserRule>()) { return None; }
+
+        let mut old_children = std::mem::take(&mut old.children);
+        let ext = std::mem::take(&mut old.cast_mut::<Root>()?.ext);
+        let maps = old_children.iter()
+            .map(|node| node.srcmap.map(|map| map.get_byte_offsets()))
+            .collect::<Option<Vec<_>>>()?;
+
+        // edit is `edit_start..old_end` in old source and `edit_start..new_end` in new one
+        let edit_start = edit.range.start;
+        let old_end = edit.range.end;
+        let new_end = edit_start + edit.text.len();
+
+        // block before the edit is parsed again, because it may continue into edited text
+        let first = maps.iter().position(|&(_, end)| end >= edit_start).unwrap_or(maps.len());
+        let first = first.saturating_sub(1);
+        let parse_from = if first == 0 { 0 } else { maps[first].0 };
+
+        let mut root = Root::new(src.to_owned());
+        root.ext = ext;
+        let mut node = Node::new(root);
+        node.srcmap = Some(SourcePos::new(0, src.len()));
+        let mut reuse_from = old_children.len();
+        let mut parse_to = src.len();
+
+        for (key, rule, _) in self.ruler.iter() {
+            if *key != TypeKey::of::<BlockParserRule>() {
+                rule(&mut node, self);
+                continue;
+            }
+
+            let mut ext = std::mem::take(&mut node.cast_mut::<Root>().unwrap().ext);
+
+            let mut state = BlockState::new(src, self, &mut ext, std::mem::take(&mut node));
+            state.line = state.line_offsets.partition_point(|line| line.line_start <= parse_from) - 1;
+
+            // blocks are parsed from the start of a line, and the rest of the document
+            // is the same after the edit, so same block is going to be produced from there
+            self.block.tokenize_until(&mut state, |state| {
+                let line = &state.line_offsets[state.line];
+                if line.line_start < new_end { return false; }
+                if !is_line_start(old_src, line.line_start - new_end + old_end) { return false; }
+
+                let start = line.first_nonspace - new_end + old_end;
+                match maps[first..].binary_search_by_key(&start, |&(start, _)| start) {
+                    Ok(idx) => {
+                        reuse_from = first + idx;
+                        parse_to = line.first_nonspace;
+                        true
+                    }
+                    Err(_) => false,
+                }
+            });
+
+            node = state.node;
+            node.cast_mut::<Root>().unwrap().ext = ext;
+
+            // definitions are gone from the tree, so look for them in the text: `]:` can't be
+            // omitted, and there is no other way to know if they changed without parsing it all
+            let old_to = maps.get(reuse_from).map(|&(start, _)| start).unwrap_or(old_src.len());
+            if old_src[parse_from..old_to].contains("]:") || src[parse_from..parse_to].contains("]:") {
+                return None;
+            }
+        }
+
+        let mut tail = old_children.split_off(reuse_from);
+        old_children.truncate(first);
+        for child in tail.iter_mut() {
+            child.walk_mut(|node, _| {
+                if let Some(map) = node.srcmap {
+                    let (start, end) = map.get_byte_offsets();
+                    node.srcmap = Some(SourcePos::new(start - old_end + new_end, end - old_end + new_end));
+                }
+            });
+        }
+
+        old_children.append(&mut node.children);
+        old_children.append(&mut tail);
+        node.children = old_children;
+        Some(node)
+    }
+
+    pub fn add_rule<T: CoreRule>(&mut self) -> RuleBuilder<'_, RuleFns> {
+        let item = self.ruler.add(TypeKey::of::<T>(), (TypeKey::of::<T>(), T::run, T::INCREMENTAL));
         RuleBuilder::new(item)
     }
 
@@ -66,6 +193,46 @@ impl MarkdownIt {
     pub fn remove_rule<T: CoreRule>(&mut self) {
         self.ruler.remove(TypeKey::of::<T>());
     }
+
+    /// Render nodes of type `T` with function `f` instead of their own render
+    /// function, see [RenderOverrides](crate::parser::renderer::RenderOverrides).
+    ///
+    /// Overrides are only applied when rendering through this instance ([MarkdownIt::render],
+    /// or [render_options](MarkdownIt::render_options) passed to [Node::render_with]),
+    /// [Node::render] doesn't know about them.
+    pub fn set_render_override<T: NodeValue>(&mut self, f: impl Fn(&Node, &mut dyn Renderer) + Send + Sync + 'static) {
+        self.ext.get_or_insert_default::<RenderOverrides>().set::<T>(f);
+    }
+
+    /// Remove render override for nodes of type `T`.
+    pub fn remove_render_override<T: NodeValue>(&mut self) {
+        if let Some(overrides) = self.ext.get_mut::<RenderOverrides>() {
+            overrides.remove::<T>();
+        }
+    }
+
+    /// Default [RenderOptions] with render overrides set on this instance
+    /// (use them with [Node::render_with] or [HTMLRenderer](crate::parser::renderer::HTMLRenderer)).
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            overrides: self.ext.get::<RenderOverrides>().cloned().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Render node to HTML using render overrides set on this instance.
+    pub fn render(&self, node: &Node) -> String {
+        node.render_with(&self.render_options())
+    }
+}
+
+// check that `pos` isn't in the middle of a line (`\r\n` is a single line break)
+fn is_line_start(src: &str, pos: usize) -> bool {
+    match src.as_bytes()[..pos].last() {
+        None | Some(b'\n') => true,
+        Some(b'\r') => src.as_bytes().get(pos) != Some(&b'\n'),
+        Some(_) => false,
+    }
 }
 
 impl Default for MarkdownIt {
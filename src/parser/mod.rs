@@ -39,7 +39,7 @@ pub mod core;
 pub mod inline;
 pub mod extset;
 pub mod linkfmt;
+pub mod renderer;
 
 pub(super) mod node;
 pub(super) mod main;
-pub(super) mod renderer;
@@ -1,10 +1,10 @@
 use downcast_rs::{Downcast, impl_downcast};
-use std::{fmt::Debug, any::TypeId};
+use std::{fmt::{self, Debug}, any::TypeId, io};
 use crate::Renderer;
 use crate::common::TypeKey;
 use crate::common::sourcemap::SourcePos;
 use crate::parser::extset::NodeExtSet;
-use crate::parser::renderer::HTMLRenderer;
+use crate::parser::renderer::{HTMLRenderer, RenderOptions};
 
 /// Single node in the CommonMark AST.
 #[derive(Debug)]
@@ -79,21 +79,52 @@ impl Node {
     }
 
     /// Render this node to HTML.
+    ///
+    /// Render overrides set with [MarkdownIt::set_render_override](crate::MarkdownIt::set_render_override)
+    /// aren't applied here, use [MarkdownIt::render](crate::MarkdownIt::render) for that.
     pub fn render(&self) -> String {
-        let mut fmt = HTMLRenderer::<false>::new();
-        fmt.render(self);
-        fmt.into()
+        self.render_with(&RenderOptions::default())
     }
 
     /// Render this node to XHTML, it adds slash to self-closing tags like this: `<img />`.
     ///
     /// This mode exists for compatibility with CommonMark tests.
     pub fn xrender(&self) -> String {
-        let mut fmt = HTMLRenderer::<true>::new();
+        self.render_with(&RenderOptions { xhtml: true, ..Default::default() })
+    }
+
+    /// Render this node to HTML with custom options, see [RenderOptions].
+    pub fn render_with(&self, options: &RenderOptions) -> String {
+        let mut fmt = HTMLRenderer::new(options.clone());
         fmt.render(self);
         fmt.into()
     }
 
+    /// Render this node to HTML, streaming output into a writer in small chunks
+    /// (so memory usage doesn't depend on the size of the output).
+    pub fn render_to(&self, writer: &mut impl io::Write) -> io::Result<()> {
+        self.render_to_with(writer, &RenderOptions::default())
+    }
+
+    /// Same as [render_to](Self::render_to), but with custom options, see [RenderOptions].
+    pub fn render_to_with(&self, writer: &mut impl io::Write, options: &RenderOptions) -> io::Result<()> {
+        let mut fmt = HTMLRenderer::with_writer(options.clone(), writer);
+        fmt.render(self);
+        fmt.finish()
+    }
+
+    /// Same as [render_to](Self::render_to), but for [fmt::Write].
+    pub fn render_to_fmt(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        self.render_to_fmt_with(writer, &RenderOptions::default())
+    }
+
+    /// Same as [render_to_fmt](Self::render_to_fmt), but with custom options, see [RenderOptions].
+    pub fn render_to_fmt_with(&self, writer: &mut impl fmt::Write, options: &RenderOptions) -> fmt::Result {
+        let mut fmt = HTMLRenderer::with_fmt_writer(options.clone(), writer);
+        fmt.render(self);
+        fmt.finish().map_err(|_| fmt::Error)
+    }
+
     /// Replace custom value with another value (this is roughly equivalent
     /// to replacing the entire node and copying children and sourcemaps).
     pub fn replace<T: NodeValue>(&mut self, value: T) {
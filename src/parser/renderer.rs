@@ -1,8 +1,12 @@
+//! Renderer trait and default HTML renderer.
 use std::collections::HashMap;
-use std::fmt::Debug;
-use crate::Node;
+use std::fmt::{self, Debug};
+use std::io;
+use std::sync::Arc;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
 use crate::common::utils::escape_html;
-use crate::parser::extset::RenderExtSet;
+use crate::parser::extset::{MarkdownItExt, RenderExt, RenderExtSet};
 
 /// Each node outputs its HTML using this API.
 ///
@@ -19,6 +23,11 @@ pub trait Renderer {
     fn contents(&mut self, nodes: &[Node]);
     /// Write line break (`\n`). Default renderer ignores it if last char in the buffer is `\n` already.
     fn cr(&mut self);
+    /// Write soft line break (newline in the middle of a paragraph), it is the same as
+    /// [cr](Renderer::cr) by default, but may be rendered as `<br>` instead.
+    fn softbreak(&mut self) {
+        self.cr();
+    }
     /// Write plain text with escaping, `<div>` -> `&lt;div&gt;`.
     fn text(&mut self, text: &str);
     /// Write plain text without escaping, `<div>` -> `<div>`.
@@ -27,23 +36,288 @@ pub trait Renderer {
     fn ext(&mut self) -> &mut RenderExtSet;
 }
 
+/// Function that renders a node instead of its [NodeValue::render].
+pub type RenderFn = dyn Fn(&Node, &mut dyn Renderer) + Send + Sync;
+
+/// Custom render functions for node types, similar to `md.renderer.rules` in markdown-it.js.
+///
+/// Node value stays the same, so other plugins can still find it in the AST.
+/// Override can fall back to the default rendering by calling
+/// `node.node_value.render(node, fmt)`.
+///
+/// Overrides are passed to [HTMLRenderer] in [RenderOptions::overrides]. They are
+/// usually set with [MarkdownIt::set_render_override](crate::MarkdownIt::set_render_override),
+/// and [MarkdownIt::render_options](crate::MarkdownIt::render_options) returns options
+/// with them. Custom renderers can add them to their extension set (`fmt.ext().insert(overrides)`).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::inline::link::Link;
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+///
+/// md.set_render_override::<Link>(|node, fmt| {
+///     let url = &node.cast::<Link>().unwrap().url;
+///     if url.starts_with("https://") {
+///         fmt.text_raw("&#x1F517;");
+///     }
+///     node.node_value.render(node, fmt);
+/// });
+///
+/// let html = md.render(&md.parse("[a](https://example.org) [b](/b)"));
+/// assert_eq!(html.trim(), r#"<p>&#x1F517;<a href="https://example.org">a</a> <a href="/b">b</a></p>"#);
+/// ```
+#[derive(Clone, Default)]
+pub struct RenderOverrides(HashMap<TypeKey, Arc<RenderFn>>);
+
+impl RenderOverrides {
+    /// Render nodes of type `T` with function `f`, replacing previous override if any.
+    pub fn set<T: NodeValue>(&mut self, f: impl Fn(&Node, &mut dyn Renderer) + Send + Sync + 'static) {
+        self.0.insert(TypeKey::of::<T>(), Arc::new(f));
+    }
+
+    /// Remove override for nodes of type `T`.
+    pub fn remove<T: NodeValue>(&mut self) {
+        self.0.remove(&TypeKey::of::<T>());
+    }
+
+    /// Get override for a node type.
+    pub fn get(&self, node_type: TypeKey) -> Option<&Arc<RenderFn>> {
+        self.0.get(&node_type)
+    }
+
+    /// Check if there are no overrides.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for RenderOverrides {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RenderOverrides").field(&self.0.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+impl MarkdownItExt for RenderOverrides {}
+impl RenderExt for RenderOverrides {}
+
+/// Render node with a given renderer, using override from [RenderOverrides]
+/// stored in renderer's extension set if there is one.
+///
+/// Custom renderers should use it in [Renderer::contents] to support overrides.
+pub fn render_node(node: &Node, fmt: &mut dyn Renderer) {
+    let custom = match fmt.ext().get::<RenderOverrides>() {
+        Some(overrides) => overrides.get(node.node_type).cloned(),
+        None => None,
+    };
+
+    if let Some(f) = custom {
+        f(node, fmt);
+    } else {
+        node.node_value.render(node, fmt);
+    }
+}
+
+/// Order of attributes in the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttrOrder {
+    /// Order in which they were added by plugins (repeated `class` and `style`
+    /// attributes are merged at the position of the first one).
+    #[default]
+    Source,
+    /// Sorted alphabetically by name, useful for stable snapshots.
+    Sorted,
+}
+
+/// Options for [HTMLRenderer].
+///
+/// ```rust
+/// use markdown_it::parser::renderer::RenderOptions;
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+///
+/// let options = RenderOptions { breaks: true, indent: 2, ..Default::default() };
+/// let html = md.parse("> foo\nbar").render_with(&options);
+/// assert_eq!(html, "<blockquote>\n  <p>foo<br>\n  bar</p>\n</blockquote>\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Add slash to self-closing tags like this: `<img />`.
+    pub xhtml: bool,
+    /// Render soft line breaks as `<br>` (same as `breaks` option in markdown-it.js).
+    pub breaks: bool,
+    /// Line ending used in the output, all `\n` are replaced with it (including
+    /// ones inside of code blocks).
+    pub newline: &'static str,
+    /// Indent nested block tags by this amount of spaces, 0 to disable.
+    ///
+    /// Tags are indented only if they start on a new line, so content
+    /// of code blocks and raw html is never changed.
+    pub indent: usize,
+    /// Order of attributes in the output.
+    pub attr_order: AttrOrder,
+    /// Custom render functions for node types.
+    pub overrides: RenderOverrides,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            xhtml: false,
+            breaks: false,
+            newline: "\n",
+            indent: 0,
+            attr_order: AttrOrder::Source,
+            overrides: RenderOverrides::default(),
+        }
+    }
+}
+
+// output is flushed to a stream in chunks of about this size
+const FLUSH_THRESHOLD : usize = 8192;
+
+#[derive(Default)]
+enum Sink<'a> {
+    #[default]
+    None,
+    Io(&'a mut dyn io::Write),
+    Fmt(&'a mut dyn fmt::Write),
+}
+
+impl Debug for Sink<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Io(_) => f.write_str("Io"),
+            Self::Fmt(_) => f.write_str("Fmt"),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 /// Default HTML/XHTML renderer.
-pub(crate) struct HTMLRenderer<const XHTML: bool> {
+///
+/// By default, it collects output into a string (use `String::from(renderer)`
+/// to get it), but it can also stream output into [io::Write] or [fmt::Write],
+/// see [HTMLRenderer::with_writer].
+///
+/// If you want to change how some of the nodes are rendered, you can wrap it
+/// in your own [Renderer] that forwards everything to this one, except for
+/// `contents`, which should call [render_node] with your renderer for each node
+/// (so children get rendered with your renderer as well). For simple changes,
+/// [RenderOverrides] are usually enough.
+pub struct HTMLRenderer<'a> {
     result: String,
     ext: RenderExtSet,
+    options: RenderOptions,
+    // for each open tag, whether it's a block container (followed by line break)
+    open_tags: Vec<bool>,
+    depth: usize,
+    line_start: bool,
+    after_open: bool,
+    sink: Sink<'a>,
+    // last byte written to the sink, used to deduplicate line breaks
+    flushed_last: Option<u8>,
+    error: Option<io::Error>,
 }
 
-impl<const XHTML: bool> HTMLRenderer<XHTML> {
-    pub fn new() -> Self {
+impl<'a> HTMLRenderer<'a> {
+    pub fn new(options: RenderOptions) -> Self {
+        let mut ext = RenderExtSet::new();
+        if !options.overrides.is_empty() {
+            ext.insert(options.overrides.clone());
+        }
+
         Self {
             result: String::new(),
-            ext: RenderExtSet::new(),
+            ext,
+            options,
+            open_tags: Vec::new(),
+            depth: 0,
+            line_start: false,
+            after_open: false,
+            sink: Sink::None,
+            flushed_last: None,
+            error: None,
         }
     }
 
+    /// Create renderer that streams output into a writer instead of keeping it in memory,
+    /// call [finish](Self::finish) after rendering to flush remaining output.
+    ///
+    /// ```rust
+    /// use markdown_it::parser::renderer::{HTMLRenderer, RenderOptions};
+    ///
+    /// let md = &mut markdown_it::MarkdownIt::new();
+    /// markdown_it::plugins::cmark::add(md);
+    /// let ast = md.parse("hello *world*");
+    ///
+    /// let mut output = Vec::new();
+    /// let mut fmt = HTMLRenderer::with_writer(RenderOptions::default(), &mut output);
+    /// fmt.render(&ast);
+    /// fmt.finish().unwrap();
+    /// assert_eq!(output, b"<p>hello <em>world</em></p>\n");
+    /// ```
+    pub fn with_writer(options: RenderOptions, writer: &'a mut dyn io::Write) -> Self {
+        Self { sink: Sink::Io(writer), ..Self::new(options) }
+    }
+
+    /// Same as [with_writer](Self::with_writer), but for [fmt::Write] (e.g. [fmt::Formatter]).
+    pub fn with_fmt_writer(options: RenderOptions, writer: &'a mut dyn fmt::Write) -> Self {
+        Self { sink: Sink::Fmt(writer), ..Self::new(options) }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &RenderOptions {
+        &self.options
+    }
+
     pub fn render(&mut self, node: &Node) {
-        node.node_value.render(node, self);
+        render_node(node, self);
+    }
+
+    /// Flush remaining output to the writer, returning first error encountered
+    /// while writing (if any). Does nothing if renderer outputs into a string.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush();
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn maybe_flush(&mut self) {
+        if self.result.len() >= FLUSH_THRESHOLD {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if matches!(self.sink, Sink::None) || self.result.is_empty() { return; }
+
+        let chunk = std::mem::take(&mut self.result);
+        self.flushed_last = chunk.as_bytes().last().copied();
+        let chunk = postprocess(chunk, &self.options);
+
+        // after first error, remaining output is discarded
+        if self.error.is_some() { return; }
+        let result = match &mut self.sink {
+            Sink::None => Ok(()),
+            Sink::Io(writer) => writer.write_all(chunk.as_bytes()),
+            Sink::Fmt(writer) => writer.write_str(&chunk).map_err(io::Error::other),
+        };
+        self.error = result.err();
+    }
+
+    fn indent(&mut self) {
+        self.after_open = false;
+        if self.line_start {
+            self.line_start = false;
+            for _ in 0..self.depth * self.options.indent {
+                self.result.push(' ');
+            }
+        }
     }
 
     fn make_attr(&mut self, name: &str, value: &str) {
@@ -65,6 +339,10 @@ impl<const XHTML: bool> HTMLRenderer<XHTML> {
             attr_order.push(*name);
         }
 
+        if self.options.attr_order == AttrOrder::Sorted {
+            attr_order.sort_unstable();
+        }
+
         for name in attr_order {
             if let Some(value) = attr_hash.remove(name) {
                 if name == "class" {
@@ -81,48 +359,72 @@ impl<const XHTML: bool> HTMLRenderer<XHTML> {
     }
 }
 
-impl<const XHTML: bool> From<HTMLRenderer<XHTML>> for String {
-    fn from(f: HTMLRenderer<XHTML>) -> Self {
-        #[cold]
-        fn replace_null(input: String) -> String {
-            input.replace('\0', "\u{FFFD}")
-        }
+fn postprocess(mut result: String, options: &RenderOptions) -> String {
+    #[cold]
+    fn replace_null(input: String) -> String {
+        input.replace('\0', "\u{FFFD}")
+    }
 
-        if f.result.contains('\0') {
-            // U+0000 must be replaced with U+FFFD as per commonmark spec,
-            // we do it at the very end in order to avoid messing with byte offsets
-            // for source maps (since "\0".len() != "\u{FFFD}".len())
-            replace_null(f.result)
-        } else {
-            f.result
-        }
+    #[cold]
+    fn replace_newlines(input: String, newline: &str) -> String {
+        input.replace('\n', newline)
+    }
+
+    if result.contains('\0') {
+        // U+0000 must be replaced with U+FFFD as per commonmark spec,
+        // we do it at the very end in order to avoid messing with byte offsets
+        // for source maps (since "\0".len() != "\u{FFFD}".len())
+        result = replace_null(result);
+    }
+
+    if options.newline != "\n" {
+        result = replace_newlines(result, options.newline);
+    }
+
+    result
+}
+
+impl From<HTMLRenderer<'_>> for String {
+    fn from(f: HTMLRenderer<'_>) -> Self {
+        postprocess(f.result, &f.options)
     }
 }
 
-impl<const XHTML: bool> Renderer for HTMLRenderer<XHTML> {
+impl Renderer for HTMLRenderer<'_> {
     fn open(&mut self, tag: &str, attrs: &[(&str, String)]) {
+        self.indent();
         self.result.push('<');
         self.result.push_str(tag);
         self.make_attrs(attrs);
         self.result.push('>');
+        self.open_tags.push(false);
+        self.after_open = true;
+        self.maybe_flush();
     }
 
     fn close(&mut self, tag: &str) {
+        if self.open_tags.pop() == Some(true) {
+            self.depth -= 1;
+        }
+        self.indent();
         self.result.push('<');
         self.result.push('/');
         self.result.push_str(tag);
         self.result.push('>');
+        self.maybe_flush();
     }
 
     fn self_close(&mut self, tag: &str, attrs: &[(&str, String)]) {
+        self.indent();
         self.result.push('<');
         self.result.push_str(tag);
         self.make_attrs(attrs);
-        if XHTML {
+        if self.options.xhtml {
             self.result.push(' ');
             self.result.push('/');
         }
         self.result.push('>');
+        self.maybe_flush();
     }
 
     fn contents(&mut self, nodes: &[Node]) {
@@ -132,19 +434,43 @@ impl<const XHTML: bool> Renderer for HTMLRenderer<XHTML> {
     }
 
     fn cr(&mut self) {
+        // tag immediately followed by a line break contains blocks
+        if self.after_open {
+            self.after_open = false;
+            if let Some(is_block) = self.open_tags.last_mut() {
+                *is_block = true;
+                self.depth += 1;
+            }
+        }
+
         // only push '\n' if last character isn't it
-        match self.result.as_bytes().last() {
+        match self.result.as_bytes().last().or(self.flushed_last.as_ref()) {
             Some(b'\n') | None => {}
             Some(_) => self.result.push('\n')
         }
+
+        self.line_start = true;
+    }
+
+    fn softbreak(&mut self) {
+        if self.options.breaks {
+            self.self_close("br", &[]);
+        }
+        self.cr();
     }
 
     fn text(&mut self, text: &str) {
+        if text.is_empty() { return; }
+        self.indent();
         self.result.push_str(&escape_html(text));
+        self.maybe_flush();
     }
 
     fn text_raw(&mut self, text: &str) {
+        self.line_start = false;
+        self.after_open = false;
         self.result.push_str(text);
+        self.maybe_flush();
     }
 
     fn ext(&mut self) -> &mut RenderExtSet {
@@ -0,0 +1,264 @@
+//! Edit markdown source in place, using nodes of a parsed tree to find what to change.
+//!
+//! Functions in this module return [TextEdit]s: byte ranges of the original source and
+//! text to put there. They only touch the part of the source that has to change (e.g. link
+//! url, but not its text or title), so formatting of everything else is kept exactly as it was.
+//! Edits can be passed to an editor as is, or applied to the source with [apply].
+//!
+//! Nodes are located with their source mappings, which all built-in nodes have.
+//! Offsets of the tree are not updated, so the source has to be parsed again
+//! before making more edits.
+//!
+//! ```rust
+//! use markdown_it::patch;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let source = "# Title\n\n- [ ] read [docs](/old 'Docs')\n";
+//! let ast = md.parse(source);
+//! let item = &ast.children[1].children[0];
+//!
+//! let edits = [
+//!     patch::set_heading_text(source, &ast.children[0], "New *title*").unwrap(),
+//!     patch::toggle_task(source, item).unwrap(),
+//!     patch::set_link_url(source, &item.children[1], "/new docs").unwrap(),
+//! ];
+//! assert_eq!(
+//!     patch::apply(source, &edits).unwrap(),
+//!     "# New *title*\n\n- [x] read [docs](</new docs> 'Docs')\n"
+//! );
+//! ```
+use std::fmt::{self, Display};
+use std::ops::Range;
+use crate::Node;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::ListItem;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::renderers::markdown::link_destination;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Replacement of a byte range in the source.
+pub struct TextEdit {
+    /// Byte range of the original source that is replaced (empty for insertions).
+    pub range: Range<usize>,
+    /// Text to put in its place.
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self { range, text: text.into() }
+    }
+
+    // shrink edit to the part that actually changes
+    fn minimize(mut self, source: &str) -> Self {
+        let old = &source[self.range.clone()];
+
+        let prefix = old.char_indices().zip(self.text.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((pos, _), _)| pos)
+            .unwrap_or(old.len().min(self.text.len()));
+
+        let suffix = old[prefix..].chars().rev().zip(self.text[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum::<usize>();
+
+        self.range = self.range.start + prefix .. self.range.end - suffix;
+        self.text = self.text[prefix..self.text.len() - suffix].to_owned();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Error returned when an edit can't be made.
+pub enum PatchError {
+    /// Node doesn't have source mapping (e.g. it was created after parsing).
+    NoSourceMap,
+    /// This operation doesn't work on this node type.
+    UnsupportedNode {
+        /// Node type name, see [Node::name].
+        name: &'static str,
+    },
+    /// Expected syntax isn't found in the node source (e.g. url of a reference link
+    /// is in its definition, or list item isn't a task).
+    NotFound,
+    /// New value can't be written there (e.g. line break in a heading).
+    InvalidValue,
+    /// Edits passed to [apply] overlap or don't fit the source.
+    InvalidEdit,
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSourceMap => write!(f, "node doesn't have source mapping"),
+            Self::UnsupportedNode { name } => write!(f, "node {name} can't be edited this way"),
+            Self::NotFound => write!(f, "expected syntax isn't found in node source"),
+            Self::InvalidValue => write!(f, "value can't be written in this position"),
+            Self::InvalidEdit => write!(f, "edits overlap or are out of source bounds"),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Replace the whole source of a node.
+///
+/// Text is inserted as is, so if it has multiple lines inside of a container
+/// (e.g. blockquote), it should include container markers.
+pub fn replace_node(source: &str, node: &Node, text: &str) -> Result<TextEdit, PatchError> {
+    let range = node_range(node)?;
+    Ok(TextEdit::new(range, text).minimize(source))
+}
+
+/// Change url of a [Link], [Image] or [Autolink], leaving link text and title as they are.
+///
+/// Returns [PatchError::NotFound] for reference links.
+pub fn set_link_url(source: &str, node: &Node, url: &str) -> Result<TextEdit, PatchError> {
+    let range = node_range(node)?;
+
+    if node.is::<Autolink>() {
+        if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c.is_control() || c == '<' || c == '>') {
+            return Err(PatchError::InvalidValue);
+        }
+        return Ok(TextEdit::new(range.start + 1 .. range.end - 1, url).minimize(source));
+    }
+
+    if !node.is::<Link>() && !node.is::<Image>() {
+        return Err(PatchError::UnsupportedNode { name: node.name() });
+    }
+
+    let text_end = match node.children.last() {
+        Some(child) => node_range(child)?.end,
+        None if node.is::<Image>() => range.start + 2,
+        None => range.start + 1,
+    };
+
+    // skip `](` and whitespace before destination
+    let rest = source[text_end..range.end].strip_prefix("](").ok_or(PatchError::NotFound)?;
+    let start = range.end - rest.trim_start().len();
+    let end = start + destination_len(&source[start..range.end]);
+
+    // destination can't be omitted if link has a title
+    let url = if url.is_empty() { "<>".to_owned() } else { link_destination(url) };
+    Ok(TextEdit::new(start..end, url).minimize(source))
+}
+
+/// Check or uncheck a task list item (list item starting with `[ ]` or `[x]`).
+pub fn toggle_task(source: &str, item: &Node) -> Result<TextEdit, PatchError> {
+    if !item.is::<ListItem>() {
+        return Err(PatchError::UnsupportedNode { name: item.name() });
+    }
+
+    let range = node_range(item)?;
+    let text = &source[range.clone()];
+
+    // skip list marker and spaces after it
+    let rest = text.trim_start_matches(|c: char| c.is_ascii_digit());
+    let rest = rest.strip_prefix(['-', '+', '*', '.', ')']).ok_or(PatchError::NotFound)?;
+    let content = rest.trim_start_matches([' ', '\t']);
+    if content.len() == rest.len() { return Err(PatchError::NotFound); }
+
+    let checked = if content.starts_with("[ ]") {
+        false
+    } else if content.starts_with("[x]") || content.starts_with("[X]") {
+        true
+    } else {
+        return Err(PatchError::NotFound);
+    };
+
+    if !content[3..].is_empty() && !content[3..].starts_with(char::is_whitespace) {
+        return Err(PatchError::NotFound);
+    }
+
+    let pos = range.start + text.len() - content.len() + 1;
+    Ok(TextEdit::new(pos..pos + 1, if checked { " " } else { "x" }))
+}
+
+/// Replace content of an [ATXHeading] or a [SetextHeader], keeping its markers.
+///
+/// Text is inserted as is, so it can contain inline markup, but not line breaks.
+pub fn set_heading_text(source: &str, node: &Node, text: &str) -> Result<TextEdit, PatchError> {
+    if !node.is::<ATXHeading>() && !node.is::<SetextHeader>() {
+        return Err(PatchError::UnsupportedNode { name: node.name() });
+    }
+
+    if text.contains(['\n', '\r']) {
+        return Err(PatchError::InvalidValue);
+    }
+
+    let range = node_range(node)?;
+    if let (Some(first), Some(last)) = (node.children.first(), node.children.last()) {
+        let content = node_range(first)?.start .. node_range(last)?.end;
+        return Ok(TextEdit::new(content, text).minimize(source));
+    }
+
+    // empty heading, insert text after opening sequence
+    let marker = source[range.clone()].len() - source[range.clone()].trim_start_matches('#').len();
+    if marker == 0 { return Err(PatchError::NotFound); }
+    let text = if text.is_empty() { String::new() } else { format!(" {text}") };
+    Ok(TextEdit::new(range.start + marker .. range.start + marker, text))
+}
+
+/// Apply edits to the source, they can be in any order but must not overlap.
+pub fn apply(source: &str, edits: &[TextEdit]) -> Result<String, PatchError> {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+    let mut result = String::with_capacity(source.len());
+    let mut pos = 0;
+
+    for edit in edits {
+        let Range { start, end } = edit.range;
+        if start < pos || start > end || end > source.len() ||
+                !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            return Err(PatchError::InvalidEdit);
+        }
+        result.push_str(&source[pos..start]);
+        result.push_str(&edit.text);
+        pos = end;
+    }
+
+    result.push_str(&source[pos..]);
+    Ok(result)
+}
+
+fn node_range(node: &Node) -> Result<Range<usize>, PatchError> {
+    let (start, end) = node.srcmap.ok_or(PatchError::NoSourceMap)?.get_byte_offsets();
+    Ok(start..end)
+}
+
+// length of link destination at the start of the text (0 if it's omitted)
+fn destination_len(text: &str) -> usize {
+    let mut chars = text.char_indices();
+
+    if text.starts_with('<') {
+        chars.next();
+        while let Some((pos, ch)) = chars.next() {
+            match ch {
+                '\\' => { chars.next(); }
+                '>' => return pos + 1,
+                _ => {}
+            }
+        }
+        return text.len();
+    }
+
+    let mut depth = 0;
+    while let Some((pos, ch)) = chars.next() {
+        match ch {
+            '\\' => { chars.next(); }
+            '(' => depth += 1,
+            ')' if depth == 0 => return pos,
+            ')' => depth -= 1,
+            ch if ch.is_ascii_whitespace() || ch.is_ascii_control() => return pos,
+            _ => {}
+        }
+    }
+    text.len()
+}
@@ -5,18 +5,38 @@
 //! <https://spec.commonmark.org/0.30/#indented-code-block>
 use crate::{MarkdownIt, Node, NodeValue, Renderer};
 use crate::parser::block::{BlockRule, BlockState};
+use crate::parser::extset::NodeExt;
 
 #[derive(Debug)]
 pub struct CodeBlock {
     pub content: String,
 }
 
+/// Highlighted html of a code block, added to [CodeBlock] and
+/// [CodeFence](super::fence::CodeFence) nodes by a highlighter
+/// (e.g. [highlight](crate::plugins::extra::highlight) plugin).
+#[derive(Debug, Default)]
+pub struct HighlightedCode {
+    pub html: String,
+    /// Html of each line, only present if highlighter produced it line by line.
+    pub lines: Option<Vec<String>>,
+    pub pre_attrs: Vec<(&'static str, String)>,
+}
+
+impl NodeExt for HighlightedCode {}
+
 impl NodeValue for CodeBlock {
     fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
+        let highlighted = node.ext.get::<HighlightedCode>();
+
         fmt.cr();
-        fmt.open("pre", &[]);
+        fmt.open("pre", highlighted.map(|h| h.pre_attrs.as_slice()).unwrap_or_default());
             fmt.open("code", &node.attrs);
-            fmt.text(&self.content);
+            if let Some(highlighted) = highlighted {
+                fmt.text_raw(&highlighted.html);
+            } else {
+                fmt.text(&self.content);
+            }
             fmt.close("code");
         fmt.close("pre");
         fmt.cr();
@@ -3,50 +3,354 @@
 //! ` ```lang ` or `~~~lang`
 //!
 //! <https://spec.commonmark.org/0.30/#code-fence>
+//!
+//! Info string is also parsed into [FenceInfo], so plugins don't need
+//! to split it themselves:
+//!
+//! ```rust
+//! use markdown_it::plugins::cmark::block::fence::CodeFence;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("```rust {1,3-5} title=\"main.rs\" showLineNumbers\nfn main() {}\n```");
+//! let info = &ast.children[0].cast::<CodeFence>().unwrap().parsed_info;
+//! assert_eq!(info.lang.as_deref(), Some("rust"));
+//! assert_eq!(info.highlight_lines, vec![1..=1, 3..=5]);
+//! assert_eq!(info.title.as_deref(), Some("main.rs"));
+//! assert_eq!(info.meta, "showLineNumbers");
+//! ```
+//!
+//! With [set_line_wrappers] enabled, fences with highlighted lines or `showLineNumbers`
+//! flag are rendered line by line:
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! markdown_it::plugins::cmark::block::fence::set_line_wrappers(md, true);
+//!
+//! let html = md.parse("```text {2} showLineNumbers\nfoo\nbar\n```").render();
+//! assert_eq!(html, "<pre><code class=\"language-text\">\
+//!     <span class=\"line\"><span class=\"line-number\">1</span>foo</span>\n\
+//!     <span class=\"line highlighted\"><span class=\"line-number\">2</span>bar</span>\n\
+//!     </code></pre>\n");
+//! ```
+//!
+//! Diff fences (`diff`, or `diff-rust` to highlight code as rust) mark lines
+//! as `added`, `removed` or `context` in that mode, file and hunk headers
+//! (`---`, `+++`, `@@`) are marked as `header`:
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! markdown_it::plugins::cmark::block::fence::set_line_wrappers(md, true);
+//!
+//! let html = md.parse("```diff\n@@ -1 +1 @@\n-foo\n+bar\n baz\n```").render();
+//! assert_eq!(html, "<pre><code class=\"language-diff\">\
+//!     <span class=\"line header\">@@ -1 +1 @@</span>\n\
+//!     <span class=\"line removed\">-foo</span>\n\
+//!     <span class=\"line added\">+bar</span>\n\
+//!     <span class=\"line context\"> baz</span>\n\
+//!     </code></pre>\n");
+//! ```
+use std::ops::RangeInclusive;
 use crate::{MarkdownIt, Node, NodeValue, Renderer};
 use crate::parser::block::{BlockRule, BlockState};
 use crate::parser::extset::MarkdownItExt;
 use crate::common::utils::unescape_all;
+use super::code::HighlightedCode;
 
 #[derive(Debug)]
 pub struct CodeFence {
     pub info: String,
+    pub parsed_info: FenceInfo,
     pub marker: char,
     pub marker_len: usize,
     pub content: String,
     pub lang_prefix: &'static str,
+    /// Render lines in `<span>` wrappers when info string asks for it, see [set_line_wrappers].
+    pub line_wrappers: bool,
 }
 
 impl NodeValue for CodeFence {
     fn render(&self, node: &Node, fmt: &mut dyn Renderer) {
-        let info = unescape_all(&self.info);
-        let mut split = info.split_whitespace();
-        let lang_name = split.next().unwrap_or("");
         let mut attrs = node.attrs.clone();
-        let class;
 
-        if !lang_name.is_empty() {
-            class = format!("{}{}", self.lang_prefix, lang_name);
-            attrs.push(("class", class));
+        // by default class is the first word of info string, as in commonmark reference
+        // implementation, parsed language (without line ranges) is only used with line wrappers
+        let lang_name = if self.wraps_lines() {
+            self.parsed_info.lang.clone()
+        } else {
+            unescape_all(&self.info).split_whitespace().next().map(str::to_owned)
+        };
+
+        if let Some(lang_name) = lang_name {
+            attrs.push(("class", format!("{}{}", self.lang_prefix, lang_name)));
         }
 
+        let highlighted = node.ext.get::<HighlightedCode>();
+
         fmt.cr();
-        fmt.open("pre", &[]);
+        fmt.open("pre", highlighted.map(|h| h.pre_attrs.as_slice()).unwrap_or_default());
             fmt.open("code", &attrs);
-            fmt.text(&self.content);
+            if self.wraps_lines() {
+                let lines = highlighted.and_then(|h| h.lines.as_deref());
+                self.render_lines(lines, fmt);
+            } else if let Some(highlighted) = highlighted {
+                fmt.text_raw(&highlighted.html);
+            } else {
+                fmt.text(&self.content);
+            }
             fmt.close("code");
         fmt.close("pre");
         fmt.cr();
     }
 }
 
+impl CodeFence {
+    /// Check if this fence is rendered line by line: [set_line_wrappers] is enabled
+    /// and [FenceInfo::needs_line_wrappers] returns true.
+    pub fn wraps_lines(&self) -> bool {
+        self.line_wrappers && self.parsed_info.needs_line_wrappers()
+    }
+
+    // each line is wrapped in `<span class="line">`, with `highlighted` class added
+    // for lines in highlight ranges, and `<span class="line-number">` prepended
+    // if `showLineNumbers` flag is present
+    //
+    // in diff fences lines also get `added`, `removed`, `context` or `header` class,
+    // and for `diff-lang` highlighted html doesn't include the marker, so it's added here
+    fn render_lines(&self, highlighted: Option<&[String]>, fmt: &mut dyn Renderer) {
+        let info = &self.parsed_info;
+        let show_numbers = info.has_flag("showLineNumbers");
+        let is_diff = info.is_diff();
+        let has_diff_lang = info.diff_lang().is_some();
+        let mut in_hunk = false;
+
+        for (idx, line) in self.content.lines().enumerate() {
+            let number = idx + 1;
+            let mut class = String::from("line");
+            let is_header = is_diff && is_diff_header(line, &mut in_hunk);
+            if is_header {
+                class.push_str(" header");
+            } else if is_diff {
+                class.push_str(match line.as_bytes().first() {
+                    Some(b'+') => " added",
+                    Some(b'-') => " removed",
+                    _ => " context",
+                });
+            }
+            if info.is_highlighted(number) {
+                class.push_str(" highlighted");
+            }
+
+            fmt.open("span", &[("class", class)]);
+            if show_numbers {
+                fmt.open("span", &[("class", "line-number".to_owned())]);
+                fmt.text(&number.to_string());
+                fmt.close("span");
+            }
+            match highlighted.and_then(|lines| lines.get(idx)) {
+                Some(_) if is_header => fmt.text(line),
+                Some(html) if has_diff_lang => {
+                    fmt.text(split_diff_marker(line).0);
+                    fmt.text_raw(html);
+                }
+                Some(html) => fmt.text_raw(html),
+                None => fmt.text(line),
+            }
+            fmt.close("span");
+            fmt.text("\n");
+        }
+    }
+}
+
+/// Split diff line into marker (`+`, `-` or space) and the rest of the line,
+/// marker is empty if line doesn't start with one.
+pub fn split_diff_marker(line: &str) -> (&str, &str) {
+    if line.starts_with(['+', '-', ' ']) {
+        line.split_at(1)
+    } else {
+        ("", line)
+    }
+}
+
+// Check if diff line is a header: `@@` hunk header, or `---` / `+++` file header
+// outside of a hunk (inside one those are removed/added lines starting with `--`
+// or `++`), `in_hunk` keeps track of that between lines.
+pub(crate) fn is_diff_header(line: &str, in_hunk: &mut bool) -> bool {
+    if line.starts_with("@@") {
+        *in_hunk = true;
+        true
+    } else if line.starts_with("diff ") {
+        *in_hunk = false;
+        true
+    } else {
+        !*in_hunk && (line.starts_with("---") || line.starts_with("+++"))
+    }
+}
+
+/// Parsed code fence info string.
+///
+/// It is split into language (first word), attribute blocks in curly braces,
+/// `title="..."` and the rest of the words (meta), e.g.:
+///
+/// ```text
+/// rust {1,3-5} {.numbered #example data-x=1} title="main.rs" showLineNumbers
+/// ```
+///
+/// Braces right after language (`rust{1,3}`) are allowed as well. Braces with
+/// digits, commas and dashes only are line ranges, otherwise they are attributes.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FenceInfo {
+    /// Language name, first word of info string unless it's an attribute.
+    pub lang: Option<String>,
+    /// Classes from attribute blocks (`.class`).
+    pub classes: Vec<String>,
+    /// Id from attribute blocks (`#id`), last one wins.
+    pub id: Option<String>,
+    /// Other attributes from attribute blocks (`key=val`, or `key` with empty value).
+    pub attrs: Vec<(String, String)>,
+    /// Line ranges to highlight, 1-based (`{1,3-5}`).
+    pub highlight_lines: Vec<RangeInclusive<usize>>,
+    /// Title, typically a file name (`title="main.rs"`).
+    pub title: Option<String>,
+    /// Everything else, words joined with a single space.
+    pub meta: String,
+}
+
+impl FenceInfo {
+    /// Parse info string (backslash escapes and entities are decoded first).
+    pub fn parse(info: &str) -> Self {
+        let info = unescape_all(info);
+        let mut result = Self::default();
+        let mut meta = Vec::new();
+        let mut rest = info.trim_start();
+
+        let lang_end = rest.find(|c: char| c.is_whitespace() || c == '{').unwrap_or(rest.len());
+        let lang = &rest[..lang_end];
+        if !lang.is_empty() && !lang.contains('=') {
+            result.lang = Some(lang.to_owned());
+            rest = &rest[lang_end..];
+        }
+
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() { break; }
+
+            if rest.starts_with('{') {
+                if let Some(end) = find_unquoted(rest, '}') {
+                    result.parse_braces(&rest[1..end]);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+
+            let end = find_unquoted(rest, ' ').unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+
+            match word.split_once('=') {
+                Some(("title", value)) => result.title = Some(unquote(value).to_owned()),
+                _ => meta.push(word),
+            }
+        }
+
+        result.meta = meta.join(" ");
+        result
+    }
+
+    /// Check if line (1-based) is in one of [highlight_lines](Self::highlight_lines) ranges.
+    pub fn is_highlighted(&self, line: usize) -> bool {
+        self.highlight_lines.iter().any(|range| range.contains(&line))
+    }
+
+    /// Check if info string asks for code to be rendered line by line, which happens
+    /// when there are highlighted lines, `showLineNumbers` flag is present,
+    /// or it's a diff (it's only done if [set_line_wrappers] is enabled).
+    pub fn needs_line_wrappers(&self) -> bool {
+        !self.highlight_lines.is_empty() || self.has_flag("showLineNumbers") || self.is_diff()
+    }
+
+    /// Check if language is `diff` or `diff-<lang>`.
+    pub fn is_diff(&self) -> bool {
+        match &self.lang {
+            Some(lang) => lang == "diff" || lang.starts_with("diff-"),
+            None => false,
+        }
+    }
+
+    /// Language of the code inside `diff-<lang>` fence.
+    pub fn diff_lang(&self) -> Option<&str> {
+        self.lang.as_deref()?.strip_prefix("diff-").filter(|lang| !lang.is_empty())
+    }
+
+    /// Check if meta contains given word (e.g. `showLineNumbers`).
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.meta.split(' ').any(|word| word == flag)
+    }
+
+    fn parse_braces(&mut self, content: &str) {
+        if content.chars().all(|c| matches!(c, '0'..='9' | ',' | '-' | ' ')) {
+            for part in content.split(',') {
+                let part = part.trim();
+                let (start, end) = part.split_once('-').unwrap_or((part, part));
+                if let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse()) {
+                    if start <= end { self.highlight_lines.push(start..=end); }
+                }
+            }
+            return;
+        }
+
+        let mut rest = content;
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() { break; }
+
+            let end = find_unquoted(rest, ' ').unwrap_or(rest.len());
+            let word = &rest[..end];
+            rest = &rest[end..];
+
+            if let Some(class) = word.strip_prefix('.') {
+                self.classes.push(class.to_owned());
+            } else if let Some(id) = word.strip_prefix('#') {
+                self.id = Some(id.to_owned());
+            } else if let Some((key, value)) = word.split_once('=') {
+                self.attrs.push((key.to_owned(), unquote(value).to_owned()));
+            } else {
+                self.attrs.push((word.to_owned(), String::new()));
+            }
+        }
+    }
+}
+
+// find first occurrence of a character (any whitespace for ' ') outside of double quotes
+fn find_unquoted(str: &str, needle: char) -> Option<usize> {
+    let mut quoted = false;
+    for (pos, ch) in str.char_indices() {
+        if ch == '"' {
+            quoted = !quoted;
+        } else if !quoted && (ch == needle || needle == ' ' && ch.is_whitespace()) {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+fn unquote(str: &str) -> &str {
+    str.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(str)
+}
+
 #[derive(Debug, Clone, Copy)]
-struct FenceSettings(&'static str);
+struct FenceSettings {
+    lang_prefix: &'static str,
+    line_wrappers: bool,
+}
 impl MarkdownItExt for FenceSettings {}
 
 impl Default for FenceSettings {
     fn default() -> Self {
-        Self("language-")
+        Self { lang_prefix: "language-", line_wrappers: false }
     }
 }
 
@@ -55,7 +359,13 @@ pub fn add(md: &mut MarkdownIt) {
 }
 
 pub fn set_lang_prefix(md: &mut MarkdownIt, lang_prefix: &'static str) {
-    md.ext.insert(FenceSettings(lang_prefix));
+    md.ext.get_or_insert_default::<FenceSettings>().lang_prefix = lang_prefix;
+}
+
+/// Render fences with highlighted lines, `showLineNumbers` flag or `diff` language
+/// line by line, with each line wrapped in `<span class="line">` (disabled by default).
+pub fn set_line_wrappers(md: &mut MarkdownIt, enabled: bool) {
+    md.ext.get_or_insert_default::<FenceSettings>().line_wrappers = enabled;
 }
 
 #[doc(hidden)]
@@ -152,13 +462,15 @@ impl BlockRule for FenceScanner {
         let indent = state.line_offsets[state.line].indent_nonspace;
         let (content, _) = state.get_lines(state.line + 1, next_line, indent as usize, true);
 
-        let lang_prefix = state.md.ext.get::<FenceSettings>().copied().unwrap_or_default().0;
+        let settings = state.md.ext.get::<FenceSettings>().copied().unwrap_or_default();
         let node = Node::new(CodeFence {
+            parsed_info: FenceInfo::parse(&params),
             info: params,
             marker,
             marker_len: len,
             content,
-            lang_prefix,
+            lang_prefix: settings.lang_prefix,
+            line_wrappers: settings.line_wrappers,
         });
         Some((node, next_line - state.line + if have_end_marker { 1 } else { 0 }))
     }
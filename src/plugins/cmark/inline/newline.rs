@@ -22,7 +22,7 @@ pub struct Softbreak;
 
 impl NodeValue for Softbreak {
     fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
-        fmt.cr();
+        fmt.softbreak();
     }
 }
 
@@ -35,6 +35,16 @@ pub struct NewlineScanner;
 impl InlineRule for NewlineScanner {
     const MARKER: char = '\n';
 
+    // trailing spaces are only removed from pending text when a node is actually
+    // created, validation mode (e.g. link label scan) must not change it
+    fn check(state: &mut InlineState) -> Option<usize> {
+        let mut chars = state.src[state.pos..state.pos_max].chars();
+
+        if chars.next().unwrap() != '\n' { return None; }
+
+        Some(1 + chars.take_while(|ch| matches!(ch, ' ' | '\t')).count())
+    }
+
     fn run(state: &mut InlineState) -> Option<(Node, usize)> {
         let mut chars = state.src[state.pos..state.pos_max].chars();
 
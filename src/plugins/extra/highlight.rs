@@ -0,0 +1,168 @@
+//! Syntax highlighting for code blocks with pluggable backends.
+//!
+//! [CodeFence] and [CodeBlock] nodes are left in the AST as is, highlighted html
+//! is stored in [HighlightedCode] extension of the node and used when rendering.
+//! So other plugins can still look up code blocks along with their language.
+//!
+//! ```rust
+//! use markdown_it::plugins::extra::highlight::{self, Highlighter};
+//!
+//! #[derive(Debug)]
+//! struct Shouting;
+//!
+//! impl Highlighter for Shouting {
+//!     fn highlight(&self, _lang: Option<&str>, code: &str) -> Option<String> {
+//!         Some(format!("<b>{}</b>", code.to_uppercase()))
+//!     }
+//! }
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! highlight::set_highlighter(md, Shouting);
+//!
+//! let html = md.parse("```rust\nfn main() {}\n```").render();
+//! assert_eq!(html.trim(), "<pre><code class=\"language-rust\"><b>FN MAIN() {}\n</b></code></pre>");
+//! ```
+use downcast_rs::{Downcast, impl_downcast};
+use std::borrow::Cow;
+use std::fmt::Debug;
+use crate::{MarkdownIt, Node};
+use crate::parser::core::CoreRule;
+use crate::parser::extset::MarkdownItExt;
+use crate::parser::inline::builtin::InlineParserRule;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::{is_diff_header, split_diff_marker, CodeFence};
+
+pub use crate::plugins::cmark::block::code::HighlightedCode;
+
+/// Syntax highlighting backend.
+pub trait Highlighter : Debug + Downcast + Send + Sync {
+    /// Return html for the given code (it is inserted inside `<code>` tag as is),
+    /// or `None` if it can't be highlighted, in which case it'll be rendered as plain text.
+    ///
+    /// Language is taken from [FenceInfo](crate::plugins::cmark::block::fence::FenceInfo), and it is `None`
+    /// for indented code blocks or fences without info string. For `diff-<lang>` fences
+    /// it is the inner language, and code is passed with diff markers removed.
+    fn highlight(&self, lang: Option<&str>, code: &str) -> Option<String>;
+
+    /// Return html for each line of code separately (without line endings), each one
+    /// must close all tags it opens. It is used for code fences that are rendered
+    /// line by line, see [CodeFence::wraps_lines].
+    ///
+    /// If `None` is returned, such code fences are rendered as plain text.
+    fn highlight_lines(&self, lang: Option<&str>, code: &str) -> Option<Vec<String>> {
+        let _ = (lang, code);
+        None
+    }
+
+    /// Additional attributes for `<pre>` tag of highlighted code (e.g. background color).
+    fn pre_attrs(&self, lang: Option<&str>) -> Vec<(&'static str, String)> {
+        let _ = lang;
+        Vec::new()
+    }
+}
+
+impl_downcast!(Highlighter);
+
+#[derive(Debug)]
+struct HighlighterSettings(Box<dyn Highlighter>);
+impl MarkdownItExt for HighlighterSettings {}
+
+/// Use given highlighter for code blocks, replacing previous one if any.
+pub fn set_highlighter(md: &mut MarkdownIt, highlighter: impl Highlighter) {
+    md.ext.insert(HighlighterSettings(Box::new(highlighter)));
+
+    if !md.has_rule::<HighlightRule>() {
+        md.add_rule::<HighlightRule>()
+            .after::<InlineParserRule>();
+    }
+}
+
+/// Get currently registered highlighter if it is of a given type.
+pub fn get_highlighter<T: Highlighter>(md: &MarkdownIt) -> Option<&T> {
+    md.ext.get::<HighlighterSettings>()?.0.downcast_ref::<T>()
+}
+
+/// Get currently registered highlighter if it is of a given type.
+pub fn get_highlighter_mut<T: Highlighter>(md: &mut MarkdownIt) -> Option<&mut T> {
+    md.ext.get_mut::<HighlighterSettings>()?.0.downcast_mut::<T>()
+}
+
+// settings of a highlighter backend, stored until its plugin is added
+#[derive(Debug, Default)]
+struct BackendSettings<T>(T);
+impl<T: Highlighter + Default> MarkdownItExt for BackendSettings<T> {}
+
+/// Settings of highlighter backend `T` changed with [configure] (if any),
+/// backend plugins use them to create the highlighter.
+pub fn settings<T: Highlighter + Default>(md: &MarkdownIt) -> Option<&T> {
+    md.ext.get::<BackendSettings<T>>().map(|settings| &settings.0)
+}
+
+/// Change settings of highlighter backend `T`, they are applied both to registered
+/// highlighter (if any) and to the one that will be created by its plugin later.
+pub fn configure<T: Highlighter + Default>(md: &mut MarkdownIt, f: impl Fn(&mut T)) {
+    f(&mut md.ext.get_or_insert_default::<BackendSettings<T>>().0);
+    if let Some(highlighter) = get_highlighter_mut::<T>(md) {
+        f(highlighter);
+    }
+}
+
+/// Remove currently registered highlighter, code blocks will be rendered as plain text.
+pub fn remove_highlighter(md: &mut MarkdownIt) {
+    md.ext.remove::<HighlighterSettings>();
+    md.remove_rule::<HighlightRule>();
+}
+
+#[doc(hidden)]
+pub struct HighlightRule;
+impl CoreRule for HighlightRule {
+    const INCREMENTAL: bool = true;
+
+    fn run(root: &mut Node, md: &MarkdownIt) {
+        let highlighter = match md.ext.get::<HighlighterSettings>() {
+            Some(settings) => settings.0.as_ref(),
+            None => return,
+        };
+
+        root.walk_mut(|node, _| {
+            let (lang, content, by_line) = if let Some(data) = node.cast::<CodeFence>() {
+                let info = &data.parsed_info;
+                if let Some(diff_lang) = info.diff_lang().filter(|_| data.wraps_lines()) {
+                    // diff markers are removed, so code can be highlighted as is
+                    // (headers aren't code, so they are left empty)
+                    let mut code = String::with_capacity(data.content.len());
+                    let mut in_hunk = false;
+                    for line in data.content.lines() {
+                        if !is_diff_header(line, &mut in_hunk) {
+                            code.push_str(split_diff_marker(line).1);
+                        }
+                        code.push('\n');
+                    }
+                    (Some(diff_lang.to_owned()), Cow::Owned(code), true)
+                } else {
+                    (info.lang.clone(), Cow::Borrowed(data.content.as_str()), data.wraps_lines())
+                }
+            } else if let Some(data) = node.cast::<CodeBlock>() {
+                (None, Cow::Borrowed(data.content.as_str()), false)
+            } else {
+                return;
+            };
+
+            let (html, lines) = if by_line {
+                match highlighter.highlight_lines(lang.as_deref(), &content) {
+                    Some(lines) => (lines.join("\n"), Some(lines)),
+                    None => return,
+                }
+            } else {
+                match highlighter.highlight(lang.as_deref(), &content) {
+                    Some(html) => (html, None),
+                    None => return,
+                }
+            };
+
+            let pre_attrs = highlighter.pre_attrs(lang.as_deref());
+            node.ext.insert(HighlightedCode { html, lines, pre_attrs });
+        });
+    }
+}
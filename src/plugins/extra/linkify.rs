@@ -50,6 +50,8 @@ struct LinkifyPosition {
 #[doc(hidden)]
 pub struct LinkifyPrescan;
 impl CoreRule for LinkifyPrescan {
+    const INCREMENTAL: bool = true;
+
     fn run(root: &mut Node, _: &MarkdownIt) {
         let root_data = root.cast_mut::<Root>().unwrap();
         let source = root_data.content.as_str();
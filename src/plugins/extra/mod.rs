@@ -16,12 +16,16 @@
 pub mod strikethrough;
 pub mod tables;
 pub mod beautify_links;
+pub mod highlight;
 #[cfg(feature = "linkify")]
 pub mod linkify;
 pub mod smartquotes;
 #[cfg(feature = "syntect")]
 pub mod syntect;
+#[cfg(feature = "tree-sitter")]
+pub mod tree_sitter;
 pub mod typographer;
+pub mod url_rewrite;
 
 use crate::MarkdownIt;
 
@@ -148,7 +148,7 @@ impl<
         let mut current_index: usize = 0;
         root.walk_mut(|node, _| {
             if let Some(current_replacements) = replacement_ops.get(&current_index) {
-                let mut text_node = node.cast_mut::<Text>().expect("Expected to find a text node at this index because we constructed our replacements HashMap accordingly.");
+                let text_node = node.cast_mut::<Text>().expect("Expected to find a text node at this index because we constructed our replacements HashMap accordingly.");
                 text_node.content = execute_replacements(current_replacements, &text_node.content);
             };
             current_index += 1;
@@ -314,7 +314,7 @@ impl<
 /// Every entry in the `Vec` will produce an entry in the result, meaning that
 /// the index of a token in the resulting `Vec` will be the same as the index it
 /// would get during a `root.walk` call.
-fn all_text_tokens(root: &Node) -> Vec<FlatToken> {
+fn all_text_tokens(root: &Node) -> Vec<FlatToken<'_>> {
     let mut result = Vec::new();
     let mut walk_index = 0;
     root.walk(|node, nesting_level| {
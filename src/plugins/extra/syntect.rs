@@ -1,74 +1,354 @@
-//! Syntax highlighting for code blocks
-use crate::{MarkdownIt, Node, NodeValue, Renderer};
-use crate::parser::extset::MarkdownItExt;
-use crate::parser::core::CoreRule;
-use crate::plugins::cmark::block::code::CodeBlock;
-use crate::plugins::cmark::block::fence::CodeFence;
+//! Syntax highlighting for code blocks using [syntect](https://docs.rs/syntect)
+//!
+//! This is a [Highlighter] backend, see [highlight] module
+//! for details on how highlighted code is stored and rendered.
+//!
+//! Default syntax and theme sets are loaded once per process on first use,
+//! and shared between all parser instances. You can supply your own instead:
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use syntect::parsing::SyntaxSet;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! markdown_it::plugins::extra::syntect::add(md);
+//!
+//! // e.g. syntaxes loaded from a binary dump
+//! let syntax_set = Arc::new(SyntaxSet::load_defaults_newlines());
+//! markdown_it::plugins::extra::syntect::set_syntax_set(md, syntax_set);
+//! ```
+//!
+//! By default, output has inline styles. If you want to use css classes instead
+//! (e.g. because of CSP or dark mode support), enable them with [set_class_style]
+//! and generate a stylesheet with [stylesheet]:
+//!
+//! ```rust
+//! use syntect::html::ClassStyle;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! markdown_it::plugins::extra::syntect::add(md);
+//! markdown_it::plugins::extra::syntect::set_class_style(md, ClassStyle::Spaced);
+//!
+//! let html = md.parse("```rust\nfn main() {}\n```").render();
+//! assert!(html.starts_with(r#"<pre class="code"><code class="language-rust"><span class="source rust">"#));
+//!
+//! let css = markdown_it::plugins::extra::syntect::stylesheet(md, "InspiredGitHub", Some("base16-ocean.dark"));
+//! assert!(css.unwrap().contains("@media (prefers-color-scheme: dark)"));
+//! ```
+//!
+//! Additional syntaxes (`.sublime-syntax`) and themes (`.tmTheme`) can be loaded
+//! from local folders with [load_syntaxes_from_folder] and [load_themes_from_folder].
+use once_cell::sync::Lazy;
+use std::fmt::{self, Display};
+use std::path::Path;
+use std::sync::Arc;
+use crate::MarkdownIt;
+use crate::plugins::extra::highlight::{self, Highlighter};
 
-use syntect::highlighting::ThemeSet;
-use syntect::html::highlighted_html_for_string;
-use syntect::parsing::SyntaxSet;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{css_for_theme_with_class_style, line_tokens_to_classed_spans, styled_line_to_highlighted_html};
+use syntect::html::{ClassedHTMLGenerator, ClassStyle, IncludeBackground};
+use syntect::parsing::{ParseState, Scope, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use syntect::LoadingError;
+
+static DEFAULT_SYNTAX_SET : Lazy<Arc<SyntaxSet>> = Lazy::new(|| Arc::new(SyntaxSet::load_defaults_newlines()));
+static DEFAULT_THEME_SET : Lazy<Arc<ThemeSet>> = Lazy::new(|| Arc::new(ThemeSet::load_defaults()));
+
+/// Syntaxes bundled with syntect (loaded once and cached).
+pub fn default_syntax_set() -> Arc<SyntaxSet> {
+    DEFAULT_SYNTAX_SET.clone()
+}
+
+/// Themes bundled with syntect (loaded once and cached).
+pub fn default_theme_set() -> Arc<ThemeSet> {
+    DEFAULT_THEME_SET.clone()
+}
 
 #[derive(Debug)]
-pub struct SyntectSnippet {
-    pub html: String,
+/// Error returned when configuring syntect plugin.
+pub enum SyntectError {
+    /// Theme with this name doesn't exist in the theme set.
+    UnknownTheme(String),
+    /// Failed to load syntax or theme files.
+    Loading(LoadingError),
 }
 
-impl NodeValue for SyntectSnippet {
-    fn render(&self, _: &Node, fmt: &mut dyn Renderer) {
-        fmt.text_raw(&self.html);
+impl Display for SyntectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownTheme(name) => write!(f, "unknown theme: {name:?}"),
+            Self::Loading(err) => err.fmt(f),
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct SyntectSettings(&'static str);
-impl MarkdownItExt for SyntectSettings {}
+impl std::error::Error for SyntectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::UnknownTheme(_) => None,
+            Self::Loading(err) => Some(err),
+        }
+    }
+}
 
-impl Default for SyntectSettings {
-    fn default() -> Self {
-        Self("InspiredGitHub")
+impl From<LoadingError> for SyntectError {
+    fn from(err: LoadingError) -> Self {
+        Self::Loading(err)
     }
 }
 
 pub fn add(md: &mut MarkdownIt) {
-    md.add_rule::<SyntectRule>();
+    let highlighter = settings(md);
+    highlight::set_highlighter(md, highlighter);
+}
+
+/// Select theme by name, it must exist in the theme set of the registered
+/// highlighter (or the current theme set if it isn't registered yet).
+pub fn set_theme(md: &mut MarkdownIt, theme: &str) -> Result<(), SyntectError> {
+    if !settings(md).theme_set().themes.contains_key(theme) {
+        return Err(SyntectError::UnknownTheme(theme.to_owned()));
+    }
+    highlight::configure::<SyntectHighlighter>(md, |h| h.theme = theme.to_owned());
+    Ok(())
+}
+
+/// Add all `.sublime-syntax` files from a folder (recursively) to the current syntax set.
+pub fn load_syntaxes_from_folder(md: &mut MarkdownIt, folder: impl AsRef<Path>) -> Result<(), SyntectError> {
+    let mut builder = settings(md).syntax_set().clone().into_builder();
+    builder.add_from_folder(folder, true)?;
+    set_syntax_set(md, Arc::new(builder.build()));
+    Ok(())
+}
+
+/// Add all `.tmTheme` files from a folder (recursively) to the current theme set,
+/// themes are named after their file names without extension.
+pub fn load_themes_from_folder(md: &mut MarkdownIt, folder: impl AsRef<Path>) -> Result<(), SyntectError> {
+    let mut theme_set = ThemeSet { themes: settings(md).theme_set().themes.clone() };
+    theme_set.add_from_folder(folder)?;
+    set_theme_set(md, Arc::new(theme_set));
+    Ok(())
+}
+
+/// Syntax set used by the plugin, default one unless it was replaced or extended
+/// (e.g. to highlight code in other renderers the same way).
+pub fn syntax_set(md: &MarkdownIt) -> Arc<SyntaxSet> {
+    settings(md).syntax_set.unwrap_or_else(default_syntax_set)
+}
+
+/// Theme set used by the plugin, default one unless it was replaced or extended.
+pub fn theme_set(md: &MarkdownIt) -> Arc<ThemeSet> {
+    settings(md).theme_set.unwrap_or_else(default_theme_set)
+}
+
+/// Use custom syntax set instead of default one.
+pub fn set_syntax_set(md: &mut MarkdownIt, syntax_set: Arc<SyntaxSet>) {
+    highlight::configure::<SyntectHighlighter>(md, |h| h.syntax_set = Some(syntax_set.clone()));
+}
+
+/// Output css classes instead of inline styles, see [stylesheet].
+pub fn set_class_style(md: &mut MarkdownIt, class_style: ClassStyle) {
+    highlight::configure::<SyntectHighlighter>(md, |h| h.class_style = Some(class_style));
+}
+
+/// Use custom theme set instead of default one.
+pub fn set_theme_set(md: &mut MarkdownIt, theme_set: Arc<ThemeSet>) {
+    highlight::configure::<SyntectHighlighter>(md, |h| h.theme_set = Some(theme_set.clone()));
+}
+
+/// Generate css for classed output (see [set_class_style]) from a `light` theme,
+/// optionally followed by a `dark` theme wrapped in `prefers-color-scheme` media query.
+///
+/// Returns `None` if theme doesn't exist.
+pub fn stylesheet(md: &MarkdownIt, light: &str, dark: Option<&str>) -> Option<String> {
+    let settings = settings(md);
+    let class_style = settings.class_style.unwrap_or(ClassStyle::Spaced);
+    let themes = &settings.theme_set().themes;
+
+    let mut css = css_for_theme_with_class_style(themes.get(light)?, class_style).ok()?;
+
+    if let Some(dark) = dark {
+        let dark_css = css_for_theme_with_class_style(themes.get(dark)?, class_style).ok()?;
+        css.push_str("\n@media (prefers-color-scheme: dark) {\n");
+        for line in dark_css.lines() {
+            if !line.is_empty() { css.push_str("  "); }
+            css.push_str(line);
+            css.push('\n');
+        }
+        css.push_str("}\n");
+    }
+
+    Some(css)
+}
+
+// registered highlighter takes precedence, because it might've been created
+// with its own sets instead of using `configure`
+fn settings(md: &MarkdownIt) -> SyntectHighlighter {
+    highlight::get_highlighter::<SyntectHighlighter>(md)
+        .or_else(|| highlight::settings::<SyntectHighlighter>(md))
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+/// Syntect highlighter, outputs either inline styles or css classes.
+pub struct SyntectHighlighter {
+    theme: String,
+    class_style: Option<ClassStyle>,
+    syntax_set: Option<Arc<SyntaxSet>>,
+    theme_set: Option<Arc<ThemeSet>>,
+}
+
+impl SyntectHighlighter {
+    pub fn new(theme: impl Into<String>) -> Self {
+        Self { theme: theme.into(), class_style: None, syntax_set: None, theme_set: None }
+    }
+
+    /// Output css classes instead of inline styles.
+    pub fn with_class_style(mut self, class_style: ClassStyle) -> Self {
+        self.class_style = Some(class_style);
+        self
+    }
+
+    /// Use custom syntax set instead of default one.
+    pub fn with_syntax_set(mut self, syntax_set: Arc<SyntaxSet>) -> Self {
+        self.syntax_set = Some(syntax_set);
+        self
+    }
+
+    /// Use custom theme set instead of default one.
+    pub fn with_theme_set(mut self, theme_set: Arc<ThemeSet>) -> Self {
+        self.theme_set = Some(theme_set);
+        self
+    }
+
+    fn syntax_set(&self) -> &SyntaxSet {
+        self.syntax_set.as_deref().unwrap_or(&DEFAULT_SYNTAX_SET)
+    }
+
+    fn theme_set(&self) -> &ThemeSet {
+        self.theme_set.as_deref().unwrap_or(&DEFAULT_THEME_SET)
+    }
+
+    fn theme(&self) -> Option<&Theme> {
+        self.theme_set().themes.get(&self.theme)
+    }
+
+    fn find_syntax(&self, lang: Option<&str>) -> &SyntaxReference {
+        let ss = self.syntax_set();
+
+        let mut syntax = None;
+        if let Some(lang) = lang {
+            syntax = ss.find_syntax_by_token(lang);
+        }
+        syntax.unwrap_or_else(|| ss.find_syntax_plain_text())
+    }
 }
 
-pub fn set_theme(md: &mut MarkdownIt, theme: &'static str) {
-    md.ext.insert(SyntectSettings(theme));
+// same as class names produced by syntect's own (private) function
+fn push_scope_classes(html: &mut String, scope: Scope, class_style: ClassStyle) {
+    let prefix = match class_style {
+        ClassStyle::SpacedPrefixed { prefix } => prefix,
+        _ => "",
+    };
+
+    for (idx, atom) in scope.build_string().split('.').enumerate() {
+        if idx != 0 { html.push(' '); }
+        html.push_str(prefix);
+        html.push_str(atom);
+    }
 }
 
-pub struct SyntectRule;
-impl CoreRule for SyntectRule {
-    fn run(root: &mut Node, md: &MarkdownIt) {
-        let ss = SyntaxSet::load_defaults_newlines();
-        let ts = ThemeSet::load_defaults();
-        let theme = &ts.themes[md.ext.get::<SyntectSettings>().copied().unwrap_or_default().0];
+impl Default for SyntectHighlighter {
+    fn default() -> Self {
+        Self::new("InspiredGitHub")
+    }
+}
 
-        root.walk_mut(|node, _| {
-            let mut content = None;
-            let mut language = None;
+impl Highlighter for SyntectHighlighter {
+    fn highlight(&self, lang: Option<&str>, code: &str) -> Option<String> {
+        let ss = self.syntax_set();
+        let syntax = self.find_syntax(lang);
 
-            if let Some(data) = node.cast::<CodeBlock>() {
-                content = Some(&data.content);
-            } else if let Some(data) = node.cast::<CodeFence>() {
-                language = Some(data.info.clone());
-                content = Some(&data.content);
+        if let Some(class_style) = self.class_style {
+            let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, ss, class_style);
+            for line in LinesWithEndings::from(code) {
+                generator.parse_html_for_line_which_includes_newline(line).ok()?;
             }
+            return Some(generator.finalize());
+        }
 
-            if let Some(content) = content {
-                let mut syntax = None;
-                if let Some(language) = language {
-                    syntax = ss.find_syntax_by_token(&language);
-                }
-                let syntax = syntax.unwrap_or_else(|| ss.find_syntax_plain_text());
+        let theme = self.theme()?;
+        let background = theme.settings.background.unwrap_or(Color::WHITE);
+        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut html = String::new();
+
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, ss).ok()?;
+            html.push_str(&styled_line_to_highlighted_html(&regions, IncludeBackground::IfDifferent(background)).ok()?);
+        }
+
+        Some(html)
+    }
+
+    fn highlight_lines(&self, lang: Option<&str>, code: &str) -> Option<Vec<String>> {
+        let ss = self.syntax_set();
+        let syntax = self.find_syntax(lang);
+        let mut lines = Vec::new();
 
-                let html = highlighted_html_for_string(content, &ss, syntax, theme);
+        if let Some(class_style) = self.class_style {
+            let mut parse_state = ParseState::new(syntax);
+            let mut stack = ScopeStack::new();
 
-                if let Ok(html) = html {
-                    node.replace(SyntectSnippet { html });
+            for line in LinesWithEndings::from(code) {
+                // spans that were left open on previous line are closed there
+                // and opened again here, so each line is self-contained
+                let mut html = String::new();
+                for scope in stack.as_slice() {
+                    html.push_str("<span class=\"");
+                    push_scope_classes(&mut html, *scope, class_style);
+                    html.push_str("\">");
                 }
+
+                let ops = parse_state.parse_line(line, ss).ok()?;
+                let (line_html, _) = line_tokens_to_classed_spans(line, &ops, class_style, &mut stack).ok()?;
+                html.push_str(&line_html);
+                html.push_str(&"</span>".repeat(stack.len()));
+                lines.push(html.replace('\n', ""));
             }
-        });
+
+            return Some(lines);
+        }
+
+        let theme = self.theme()?;
+        let background = theme.settings.background.unwrap_or(Color::WHITE);
+        let mut highlighter = HighlightLines::new(syntax, theme);
+
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, ss).ok()?;
+            let html = styled_line_to_highlighted_html(&regions, IncludeBackground::IfDifferent(background)).ok()?;
+            lines.push(html.replace('\n', ""));
+        }
+
+        Some(lines)
+    }
+
+    fn pre_attrs(&self, _: Option<&str>) -> Vec<(&'static str, String)> {
+        match self.class_style {
+            Some(ClassStyle::SpacedPrefixed { prefix }) => return vec![("class", format!("{prefix}code"))],
+            Some(_) => return vec![("class", "code".to_owned())],
+            None => {}
+        }
+
+        let theme = match self.theme() {
+            Some(theme) => theme,
+            None => return Vec::new(),
+        };
+
+        let c = theme.settings.background.unwrap_or(Color::WHITE);
+        vec![("style", format!("background-color:#{:02x}{:02x}{:02x};", c.r, c.g, c.b))]
     }
 }
@@ -135,18 +135,15 @@ struct RowContent {
     srcmap: Vec<(usize, usize)>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum ColumnAlignment {
+    #[default]
     None,
     Left,
     Right,
     Center,
 }
 
-impl Default for ColumnAlignment {
-    fn default() -> Self { Self::None }
-}
-
 impl TableScanner {
     fn scan_row(line: &str) -> Vec<RowContent> {
         let mut result = Vec::new();
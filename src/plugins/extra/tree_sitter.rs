@@ -0,0 +1,235 @@
+//! Syntax highlighting for code blocks using [tree-sitter](https://tree-sitter.github.io)
+//!
+//! This is a [Highlighter] backend, see [highlight] module
+//! for details on how highlighted code is stored and rendered. It can be used
+//! instead of [syntect](super::syntect) (whichever is added last wins).
+//!
+//! Output uses css classes named after tree-sitter highlight names, so `function.builtin`
+//! becomes `<span class="function builtin">`, see [HIGHLIGHT_NAMES] for the full list.
+//!
+//! Grammars for Rust, JavaScript (with JSX), TypeScript, TSX and Python are bundled,
+//! other languages can be added with [add_language]:
+//!
+//! ```rust
+//! use markdown_it::plugins::extra::tree_sitter;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! tree_sitter::add(md);
+//!
+//! let html = md.parse("```rust\nfn main() {}\n```").render();
+//! assert_eq!(html.trim(), "<pre><code class=\"language-rust\">\
+//!     <span class=\"keyword\">fn</span> <span class=\"function\">main</span>\
+//!     <span class=\"punctuation bracket\">(</span><span class=\"punctuation bracket\">)</span> \
+//!     <span class=\"punctuation bracket\">{</span><span class=\"punctuation bracket\">}</span>\n\
+//!     </code></pre>");
+//!
+//! // any grammar compatible with tree-sitter 0.20 works, e.g. from `tree-sitter-go` crate
+//! # let (go, go_highlights) = (::tree_sitter_rust::language(), ::tree_sitter_rust::HIGHLIGHT_QUERY);
+//! tree_sitter::add_language(md, &["go", "golang"], go, go_highlights, "", "").unwrap();
+//! ```
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+use crate::MarkdownIt;
+use crate::plugins::extra::highlight::{self, Highlighter};
+
+use tree_sitter::{Language, QueryError};
+use tree_sitter_highlight::{HighlightConfiguration, HtmlRenderer};
+
+/// Highlight names recognized by this highlighter, any other captures
+/// in highlight queries are ignored.
+pub const HIGHLIGHT_NAMES : &[&str] = &[
+    "attribute",
+    "comment",
+    "constant",
+    "constant.builtin",
+    "constructor",
+    "embedded",
+    "escape",
+    "function",
+    "function.builtin",
+    "function.macro",
+    "function.method",
+    "keyword",
+    "label",
+    "number",
+    "operator",
+    "property",
+    "punctuation",
+    "punctuation.bracket",
+    "punctuation.delimiter",
+    "punctuation.special",
+    "string",
+    "string.special",
+    "tag",
+    "type",
+    "type.builtin",
+    "variable",
+    "variable.builtin",
+    "variable.parameter",
+];
+
+static CLASS_ATTRS : Lazy<Vec<String>> = Lazy::new(|| {
+    HIGHLIGHT_NAMES.iter().map(|name| format!("class=\"{}\"", name.replace('.', " "))).collect()
+});
+
+static DEFAULT_LANGUAGES : Lazy<HashMap<String, Arc<LanguageConfig>>> = Lazy::new(|| {
+    let mut languages = HashMap::new();
+
+    let mut add = |names: &[&str], language, highlights: &str, injections: &str, locals: &str| {
+        let config = LanguageConfig::new(language, highlights, injections, locals)
+            .expect("bundled tree-sitter queries should be valid");
+        let config = Arc::new(config);
+        for name in names {
+            languages.insert((*name).to_owned(), config.clone());
+        }
+    };
+
+    add(
+        &["rust", "rs"],
+        tree_sitter_rust::language(),
+        tree_sitter_rust::HIGHLIGHT_QUERY,
+        tree_sitter_rust::INJECTIONS_QUERY,
+        "",
+    );
+
+    add(
+        &["javascript", "js", "jsx", "mjs", "cjs"],
+        tree_sitter_javascript::language(),
+        &[tree_sitter_javascript::JSX_HIGHLIGHT_QUERY, tree_sitter_javascript::HIGHLIGHT_QUERY].join("\n"),
+        tree_sitter_javascript::INJECTION_QUERY,
+        tree_sitter_javascript::LOCALS_QUERY,
+    );
+
+    // typescript queries only contain additions to javascript ones
+    add(
+        &["typescript", "ts"],
+        tree_sitter_typescript::language_typescript(),
+        &[tree_sitter_typescript::HIGHLIGHT_QUERY, tree_sitter_javascript::HIGHLIGHT_QUERY].join("\n"),
+        tree_sitter_javascript::INJECTION_QUERY,
+        &[tree_sitter_typescript::LOCALS_QUERY, tree_sitter_javascript::LOCALS_QUERY].join("\n"),
+    );
+
+    add(
+        &["tsx"],
+        tree_sitter_typescript::language_tsx(),
+        &[
+            tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+            tree_sitter_typescript::HIGHLIGHT_QUERY,
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+        ].join("\n"),
+        tree_sitter_javascript::INJECTION_QUERY,
+        &[tree_sitter_typescript::LOCALS_QUERY, tree_sitter_javascript::LOCALS_QUERY].join("\n"),
+    );
+
+    add(
+        &["python", "py"],
+        tree_sitter_python::language(),
+        tree_sitter_python::HIGHLIGHT_QUERY,
+        "",
+        "",
+    );
+
+    languages
+});
+
+pub fn add(md: &mut MarkdownIt) {
+    let highlighter = highlight::settings::<TreeSitterHighlighter>(md).cloned().unwrap_or_default();
+    highlight::set_highlighter(md, highlighter);
+}
+
+/// Register a grammar under one or more names (matched against code fence language).
+///
+/// Queries are usually exported by grammar crates as `HIGHLIGHT_QUERY`,
+/// `INJECTIONS_QUERY` and `LOCALS_QUERY`, last two can be empty.
+pub fn add_language(
+    md: &mut MarkdownIt,
+    names: &[&str],
+    language: Language,
+    highlights: &str,
+    injections: &str,
+    locals: &str,
+) -> Result<(), QueryError> {
+    let config = Arc::new(LanguageConfig::new(language, highlights, injections, locals)?);
+    highlight::configure::<TreeSitterHighlighter>(md, |h| {
+        for name in names {
+            h.languages.insert((*name).to_owned(), config.clone());
+        }
+    });
+    Ok(())
+}
+
+struct LanguageConfig(HighlightConfiguration);
+
+impl LanguageConfig {
+    fn new(language: Language, highlights: &str, injections: &str, locals: &str) -> Result<Self, QueryError> {
+        let mut config = HighlightConfiguration::new(language, highlights, injections, locals)?;
+        config.configure(HIGHLIGHT_NAMES);
+        Ok(Self(config))
+    }
+}
+
+impl Debug for LanguageConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LanguageConfig").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Tree-sitter highlighter, outputs css classes.
+pub struct TreeSitterHighlighter {
+    languages: HashMap<String, Arc<LanguageConfig>>,
+}
+
+impl TreeSitterHighlighter {
+    /// Create highlighter with bundled grammars.
+    pub fn new() -> Self {
+        Self { languages: DEFAULT_LANGUAGES.clone() }
+    }
+
+    /// Create highlighter without any grammars.
+    pub fn empty() -> Self {
+        Self { languages: HashMap::new() }
+    }
+
+    /// Check if language is registered under this name.
+    pub fn has_language(&self, name: &str) -> bool {
+        self.languages.contains_key(name)
+    }
+
+    fn render(&self, lang: Option<&str>, code: &str) -> Option<HtmlRenderer> {
+        let config = &self.languages.get(lang?)?.0;
+        let mut highlighter = tree_sitter_highlight::Highlighter::new();
+
+        let events = highlighter.highlight(config, code.as_bytes(), None, |name| {
+            self.languages.get(name).map(|config| &config.0)
+        }).ok()?;
+
+        let mut renderer = HtmlRenderer::new();
+        renderer.render(events, code.as_bytes(), &|h| CLASS_ATTRS[h.0].as_bytes()).ok()?;
+        Some(renderer)
+    }
+}
+
+impl Default for TreeSitterHighlighter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Highlighter for TreeSitterHighlighter {
+    fn highlight(&self, lang: Option<&str>, code: &str) -> Option<String> {
+        let renderer = self.render(lang, code)?;
+        let mut html = renderer.lines().collect::<String>();
+        // renderer always terminates output with a newline
+        if !code.ends_with('\n') { html.pop(); }
+        Some(html)
+    }
+
+    fn highlight_lines(&self, lang: Option<&str>, code: &str) -> Option<Vec<String>> {
+        let renderer = self.render(lang, code)?;
+        Some(renderer.lines().map(|line| line.trim_end_matches('\n').to_owned()).collect())
+    }
+}
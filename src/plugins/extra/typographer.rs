@@ -81,9 +81,11 @@ pub fn add(md: &mut MarkdownIt) {
 pub struct TypographerRule;
 
 impl CoreRule for TypographerRule {
+    const INCREMENTAL: bool = true;
+
     fn run(root: &mut Node, _: &MarkdownIt) {
         root.walk_mut(|node, _| {
-            if let Some(mut text_node) = node.cast_mut::<Text>() {
+            if let Some(text_node) = node.cast_mut::<Text>() {
                 if SCOPED_RE.is_match(&text_node.content) {
                     text_node.content = SCOPED_RE
                         .replace_all(&text_node.content, |caps: &regex::Captures| {
@@ -0,0 +1,193 @@
+//! Rewrite urls of links and images (add base url, change file extensions, etc.)
+//!
+//! Rewriters are executed on every [Link], [Image], [Autolink], `Linkified`
+//! and [ReferenceMap] url after inline parsing is done. Unlike
+//! [LinkFormatter](crate::parser::linkfmt::LinkFormatter), each rewriter
+//! receives [UrlContext] with the type and source position of the node.
+//!
+//! ```rust
+//! use markdown_it::plugins::extra::url_rewrite::{self, BaseUrl, MarkdownToHtml};
+//! use markdown_it::plugins::cmark::inline::image::Image;
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! url_rewrite::add(md, MarkdownToHtml);
+//! url_rewrite::add(md, BaseUrl::new("https://cdn.example.org/").only::<Image>());
+//!
+//! let html = md.parse("[intro](intro.md#setup) ![logo](logo.png)").render();
+//! assert_eq!(
+//!     html.trim(),
+//!     r#"<p><a href="intro.html#setup">intro</a> <img src="https://cdn.example.org/logo.png" alt="logo"></p>"#
+//! );
+//! ```
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fmt::Debug;
+use crate::{MarkdownIt, Node};
+use crate::common::TypeKey;
+use crate::common::sourcemap::SourcePos;
+use crate::parser::core::{CoreRule, Root};
+use crate::parser::extset::MarkdownItExt;
+use crate::parser::inline::builtin::InlineParserRule;
+use crate::plugins::cmark::block::reference::{ReferenceMap, ReferenceMapEntry};
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+#[cfg(feature = "linkify")]
+use crate::plugins::extra::linkify::Linkified;
+
+static SCHEME_RE : Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.\-]*:").unwrap()
+});
+
+/// Information about the url being rewritten.
+#[derive(Debug, Clone, Copy)]
+pub struct UrlContext {
+    /// Type of the node this url belongs to (`Link`, `Image`, etc.),
+    /// or [ReferenceMapEntry] for link reference definitions.
+    pub node_type: TypeKey,
+    /// Source position of the node (not available for references).
+    pub srcmap: Option<SourcePos>,
+}
+
+impl UrlContext {
+    /// Check that this url belongs to a node of given type.
+    pub fn is<T: 'static>(&self) -> bool {
+        self.node_type == TypeKey::of::<T>()
+    }
+}
+
+/// Url rewriter, register it with [add].
+pub trait UrlRewriter : Debug + Send + Sync {
+    /// Return rewritten url, or `None` if it should stay unchanged.
+    fn rewrite(&self, url: &str, ctx: &UrlContext) -> Option<String>;
+}
+
+/// Use plain function as a rewriter.
+#[derive(Debug, Clone, Copy)]
+pub struct RewriteFn(pub fn (url: &str, ctx: &UrlContext) -> Option<String>);
+
+impl UrlRewriter for RewriteFn {
+    fn rewrite(&self, url: &str, ctx: &UrlContext) -> Option<String> {
+        self.0(url, ctx)
+    }
+}
+
+/// Prefix all relative urls with a base url.
+///
+/// Urls with scheme (`https:`, `mailto:`), protocol-relative urls (`//host`),
+/// fragment-only urls (`#anchor`) and empty urls are kept as is. Absolute
+/// paths (`/foo`) are resolved against base as well, which is useful for sites
+/// hosted in a subdirectory.
+#[derive(Debug, Clone)]
+pub struct BaseUrl {
+    base: String,
+    only: Vec<TypeKey>,
+}
+
+impl BaseUrl {
+    pub fn new(base: impl Into<String>) -> Self {
+        Self { base: base.into(), only: Vec::new() }
+    }
+
+    /// Restrict this rewriter to nodes of given type (can be called multiple times).
+    pub fn only<T: 'static>(mut self) -> Self {
+        self.only.push(TypeKey::of::<T>());
+        self
+    }
+}
+
+impl UrlRewriter for BaseUrl {
+    fn rewrite(&self, url: &str, ctx: &UrlContext) -> Option<String> {
+        if !self.only.is_empty() && !self.only.contains(&ctx.node_type) { return None; }
+        if url.is_empty() || !is_relative(url) || url.starts_with('#') { return None; }
+
+        if let Some(path) = url.strip_prefix('/') {
+            Some(format!("{}/{}", self.base.trim_end_matches('/'), path))
+        } else if self.base.ends_with('/') {
+            Some(format!("{}{}", self.base, url))
+        } else {
+            Some(format!("{}/{}", self.base, url))
+        }
+    }
+}
+
+/// Replace `.md` extension with `.html` in relative urls, so `foo.md#x` becomes `foo.html#x`.
+#[derive(Debug, Clone, Copy)]
+pub struct MarkdownToHtml;
+
+impl UrlRewriter for MarkdownToHtml {
+    fn rewrite(&self, url: &str, _: &UrlContext) -> Option<String> {
+        if !is_relative(url) { return None; }
+
+        let path_end = url.find(['?', '#']).unwrap_or(url.len());
+        let path = url[..path_end].strip_suffix(".md")?;
+        Some(format!("{}.html{}", path, &url[path_end..]))
+    }
+}
+
+fn is_relative(url: &str) -> bool {
+    !SCHEME_RE.is_match(url) && !url.starts_with("//")
+}
+
+#[derive(Debug, Default)]
+struct UrlRewriters(Vec<Box<dyn UrlRewriter>>);
+impl MarkdownItExt for UrlRewriters {}
+
+impl UrlRewriters {
+    fn apply(&self, url: &mut String, ctx: &UrlContext) {
+        for rewriter in self.0.iter() {
+            if let Some(result) = rewriter.rewrite(url, ctx) {
+                *url = result;
+            }
+        }
+    }
+}
+
+/// Add url rewriter, rewriters are executed in the order they were added.
+pub fn add(md: &mut MarkdownIt, rewriter: impl UrlRewriter + 'static) {
+    md.ext.get_or_insert_default::<UrlRewriters>().0.push(Box::new(rewriter));
+
+    if !md.has_rule::<UrlRewriteRule>() {
+        md.add_rule::<UrlRewriteRule>()
+            .after::<InlineParserRule>();
+    }
+}
+
+#[doc(hidden)]
+pub struct UrlRewriteRule;
+impl CoreRule for UrlRewriteRule {
+    fn run(root: &mut Node, md: &MarkdownIt) {
+        let rewriters = match md.ext.get::<UrlRewriters>() {
+            Some(rewriters) => rewriters,
+            None => return,
+        };
+
+        root.walk_mut(|node, _| {
+            let ctx = UrlContext { node_type: node.node_type, srcmap: node.srcmap };
+
+            if let Some(data) = node.cast_mut::<Link>() {
+                rewriters.apply(&mut data.url, &ctx);
+            } else if let Some(data) = node.cast_mut::<Image>() {
+                rewriters.apply(&mut data.url, &ctx);
+            } else if let Some(data) = node.cast_mut::<Autolink>() {
+                rewriters.apply(&mut data.url, &ctx);
+            }
+
+            #[cfg(feature = "linkify")]
+            if let Some(data) = node.cast_mut::<Linkified>() {
+                rewriters.apply(&mut data.url, &ctx);
+            }
+        });
+
+        // links are already resolved at this point, but references are
+        // rewritten as well for the sake of rules that run after this one
+        let data = root.cast_mut::<Root>().unwrap();
+        if let Some(references) = data.ext.get_mut::<ReferenceMap>() {
+            let ctx = UrlContext { node_type: TypeKey::of::<ReferenceMapEntry>(), srcmap: None };
+            for entry in references.values_mut() {
+                rewriters.apply(&mut entry.destination, &ctx);
+            }
+        }
+    }
+}
@@ -89,6 +89,13 @@ static HTML_SEQUENCES : Lazy<[HTMLSequence; 7]> = Lazy::new(|| {
     ]
 });
 
+// check if a line starts an html block that can interrupt a paragraph
+pub(crate) fn interrupts_paragraph(line: &str) -> bool {
+    HTML_SEQUENCES.iter()
+        .find(|seq| seq.open.is_match(line))
+        .is_some_and(|seq| seq.can_terminate_paragraph)
+}
+
 #[doc(hidden)]
 pub struct HtmlBlockScanner;
 
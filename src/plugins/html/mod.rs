@@ -19,7 +19,7 @@
 
 pub mod html_inline;
 pub mod html_block;
-mod utils;
+pub(crate) mod utils;
 
 use crate::MarkdownIt;
 
@@ -18,3 +18,4 @@ pub mod cmark;
 pub mod html;
 pub mod extra;
 pub mod sourcepos;
+pub mod trivia;
@@ -0,0 +1,131 @@
+//! Keep all source text in the AST (markers, indentation, blank lines, etc.),
+//! so the original markdown can be reproduced byte-for-byte from the tree.
+//!
+//! Each node gets [Trivia] extension with parts of its source that aren't covered
+//! by its children: text before the first child, between children and after the last
+//! one (leaf nodes have their whole source in it). Source is reproduced with
+//! [to_source], which interleaves these parts with reproduced children.
+//!
+//! Since trivia is stored per node, a tool that edits the tree can replace
+//! trivia (or children) of the nodes it changes, and everything else will be
+//! written out exactly as it was.
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//! markdown_it::plugins::trivia::add(md);
+//!
+//! let source = "  #  Title ##\n\n*  item \\*\n\n\n> quote";
+//! let ast = md.parse(source);
+//! assert_eq!(markdown_it::plugins::trivia::to_source(&ast), source);
+//! ```
+use crate::{MarkdownIt, Node};
+use crate::parser::core::{CoreRule, Root};
+use crate::parser::extset::NodeExt;
+use crate::parser::inline::{Text, TextSpecial};
+
+pub fn add(md: &mut MarkdownIt) {
+    md.add_rule::<TriviaRule>()
+        .after_all();
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// Source text of a node that isn't covered by its children.
+///
+/// There are always `children.len() + 1` parts: `parts[0]` goes before the first child,
+/// `parts[i]` goes between children `i - 1` and `i`, and the last one goes after
+/// the last child.
+pub struct Trivia {
+    pub parts: Vec<String>,
+}
+
+impl NodeExt for Trivia {}
+
+/// Reproduce markdown source of a node and all its descendants.
+///
+/// Trivia takes precedence over node fields, so remove it from the nodes you edit.
+/// Nodes without [Trivia] (i.e. edited ones or ones added after parsing) produce
+/// text content for [Text] and [TextSpecial], and their children for anything else.
+pub fn to_source(node: &Node) -> String {
+    let mut result = String::new();
+    write_source(node, &mut result);
+    result
+}
+
+/// Same as [to_source], but appends result to a string.
+pub fn write_source(node: &Node, result: &mut String) {
+    let Some(trivia) = node.ext.get::<Trivia>().filter(|t| t.parts.len() == node.children.len() + 1) else {
+        if let Some(data) = node.cast::<Text>() {
+            result.push_str(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            result.push_str(&data.markup);
+        }
+        for child in node.children.iter() {
+            stacker::maybe_grow(64*1024, 1024*1024, || {
+                write_source(child, result);
+            });
+        }
+        return;
+    };
+
+    result.push_str(&trivia.parts[0]);
+    for (child, part) in node.children.iter().zip(trivia.parts[1..].iter()) {
+        stacker::maybe_grow(64*1024, 1024*1024, || {
+            write_source(child, result);
+        });
+        result.push_str(part);
+    }
+}
+
+#[doc(hidden)]
+pub struct TriviaRule;
+impl CoreRule for TriviaRule {
+    fn run(root: &mut Node, _: &MarkdownIt) {
+        let source = std::mem::take(&mut root.cast_mut::<Root>().unwrap().content);
+        collect(root, &source, 0, source.len());
+        root.cast_mut::<Root>().unwrap().content = source;
+    }
+}
+
+// node occupies `start..end` in the source, children are clamped to it and to each other,
+// so together they cover the source exactly once (children without srcmap are empty)
+fn collect(node: &mut Node, source: &str, start: usize, end: usize) {
+    let mut parts = Vec::with_capacity(node.children.len() + 1);
+    let mut pos = start;
+
+    for child in node.children.iter_mut() {
+        let (child_start, child_end) = match child.srcmap {
+            Some(map) => {
+                let (child_start, child_end) = map.get_byte_offsets();
+                let child_start = child_start.clamp(pos, end);
+                (child_start, child_end.clamp(child_start, end))
+            }
+            None => (pos, pos),
+        };
+
+        parts.push(source[pos..child_start].to_owned());
+        stacker::maybe_grow(64*1024, 1024*1024, || {
+            collect(child, source, child_start, child_end);
+        });
+        pos = child_end;
+    }
+
+    parts.push(source[pos..end].to_owned());
+    node.ext.insert(Trivia { parts });
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn header_test() {
+        // same as doctest, keep in sync!
+        // used for code coverage and quicker rust-analyzer hints
+        let md = &mut crate::MarkdownIt::new();
+        crate::plugins::cmark::add(md);
+        crate::plugins::trivia::add(md);
+
+        let source = "  #  Title ##\n\n*  item \\*\n\n\n> quote";
+        let ast = md.parse(source);
+        assert_eq!(crate::plugins::trivia::to_source(&ast), source);
+    }
+}
@@ -0,0 +1,565 @@
+//! Terminal renderer, produces text with ANSI escape codes.
+//!
+//! Emphasis, links and headings are styled with SGR escape sequences,
+//! paragraphs are wrapped at a given width, code blocks are boxed (and highlighted
+//! with syntect when `syntect` feature is enabled), tables are drawn with
+//! box-drawing characters.
+//!
+//! ```rust
+//! use markdown_it::renderers::ansi::{AnsiOptions, AnsiRenderer};
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("Some **bold** text, wrapped at ten columns");
+//! let fmt = AnsiRenderer::new(AnsiOptions { width: 10, ..Default::default() });
+//! assert_eq!(
+//!     fmt.render(&ast),
+//!     "Some \x1b[1mbold\x1b[0m\ntext,\nwrapped at\nten\ncolumns\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::mem;
+#[cfg(feature = "syntect")]
+use std::sync::Arc;
+#[cfg(feature = "syntect")]
+use syntect::highlighting::ThemeSet;
+#[cfg(feature = "syntect")]
+use syntect::parsing::SyntaxSet;
+use unicode_width::UnicodeWidthStr;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+
+const BOLD : &str = "1";
+const DIM : &str = "2";
+const ITALIC : &str = "3";
+const STRIKE : &str = "9";
+const LINK : &str = "4;34";
+const CODE : &str = "33";
+const HEADING1 : &str = "1;4;35";
+const HEADING2 : &str = "1;35";
+const HEADING : &str = "1;36";
+
+/// Custom handler for a node type, see [AnsiRenderer::set_handler].
+pub type AnsiFn = fn (node: &Node, fmt: &mut AnsiRenderer);
+
+/// Options for [AnsiRenderer].
+#[derive(Debug, Clone)]
+pub struct AnsiOptions {
+    /// Wrap paragraphs at this many columns, `0` disables wrapping.
+    pub width: usize,
+    /// Add link urls after link text.
+    pub link_urls: bool,
+    /// Marker for bullet list items.
+    pub bullet: &'static str,
+    /// Syntect theme used for code blocks, `None` disables highlighting.
+    #[cfg(feature = "syntect")]
+    pub theme: Option<String>,
+    /// Syntaxes used for code blocks, e.g. [syntax_set](crate::plugins::extra::syntect::syntax_set)
+    /// of a parser with custom syntaxes loaded; `None` for the default ones.
+    #[cfg(feature = "syntect")]
+    pub syntax_set: Option<Arc<SyntaxSet>>,
+    /// Themes to look up [theme](Self::theme) in, `None` for the default ones.
+    #[cfg(feature = "syntect")]
+    pub theme_set: Option<Arc<ThemeSet>>,
+}
+
+impl Default for AnsiOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            link_urls: true,
+            bullet: "•",
+            #[cfg(feature = "syntect")]
+            theme: Some("base16-ocean.dark".to_owned()),
+            #[cfg(feature = "syntect")]
+            syntax_set: None,
+            #[cfg(feature = "syntect")]
+            theme_set: None,
+        }
+    }
+}
+
+/// Render node for a terminal with default options.
+pub fn render(node: &Node) -> String {
+    AnsiRenderer::new(AnsiOptions::default()).render(node)
+}
+
+#[derive(Debug)]
+struct Prefix {
+    first: String,
+    rest: String,
+    first_used: bool,
+}
+
+#[derive(Debug)]
+/// Terminal renderer, see [module documentation](self).
+///
+/// Inline content is accumulated and written out (wrapped) when the next block
+/// starts, so custom handlers for inline nodes only need to call [text](Self::text)
+/// and [styled](Self::styled), and handlers for block nodes should call
+/// [block](Self::block) first.
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::inline::emphasis::Em;
+/// use markdown_it::renderers::ansi::{AnsiOptions, AnsiRenderer};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("*red* text");
+///
+/// let mut fmt = AnsiRenderer::new(AnsiOptions::default());
+/// fmt.set_handler::<Em>(|node, fmt| fmt.styled("31", |fmt| fmt.contents(&node.children)));
+/// assert_eq!(fmt.render(&ast), "\x1b[31mred\x1b[0m text\n");
+/// ```
+pub struct AnsiRenderer {
+    options: AnsiOptions,
+    handlers: HashMap<TypeKey, AnsiFn>,
+    result: String,
+    inline: String,
+    styles: Vec<String>,
+    prefixes: Vec<Prefix>,
+    // nothing was written since the start of the current container
+    fresh: bool,
+    // blocks are separated by line breaks instead of empty lines
+    tight: bool,
+}
+
+impl AnsiRenderer {
+    pub fn new(options: AnsiOptions) -> Self {
+        Self {
+            options,
+            handlers: HashMap::new(),
+            result: String::new(),
+            inline: String::new(),
+            styles: Vec::new(),
+            prefixes: Vec::new(),
+            fresh: true,
+            tight: false,
+        }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &AnsiOptions {
+        &self.options
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: AnsiFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting text.
+    pub fn render(mut self, node: &Node) -> String {
+        self.node(node);
+        self.flush();
+        self.result
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() {
+            self.text(if self.options.width == 0 { "\n" } else { " " });
+        } else if node.is::<Hardbreak>() {
+            self.text("\n");
+        } else if node.is::<Em>() {
+            self.styled(ITALIC, |fmt| fmt.contents(&node.children));
+        } else if node.is::<Strong>() {
+            self.styled(BOLD, |fmt| fmt.contents(&node.children));
+        } else if node.is::<Strikethrough>() {
+            self.styled(STRIKE, |fmt| fmt.contents(&node.children));
+        } else if node.is::<CodeInline>() {
+            self.styled(CODE, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<HtmlInline>() {
+            self.styled(DIM, |fmt| fmt.text(&data.content));
+        } else if let Some(data) = node.cast::<Link>() {
+            self.styled(LINK, |fmt| fmt.contents(&node.children));
+            self.link_url(&data.url);
+        } else if node.is::<Autolink>() {
+            self.styled(LINK, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<Image>() {
+            self.styled(DIM, |fmt| {
+                fmt.text("[");
+                fmt.contents(&node.children);
+                fmt.text("]");
+            });
+            self.link_url(&data.url);
+        } else if node.is::<Paragraph>() {
+            self.block();
+            self.contents(&node.children);
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level);
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level);
+        } else if node.is::<ThematicBreak>() {
+            self.block();
+            let width = match self.available_width() { 0 => 40, width => width };
+            self.line(&format!("\x1b[{DIM}m{}\x1b[0m", "─".repeat(width)));
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.code(&data.content, None);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.code(&data.content, data.parsed_info.lang.as_deref());
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            self.block();
+            let content = data.content.strip_suffix('\n').unwrap_or(&data.content);
+            for line in content.split('\n') {
+                self.line(&format!("\x1b[{DIM}m{}\x1b[0m", sanitize(line)));
+            }
+        } else if node.is::<Blockquote>() {
+            self.block();
+            let tight = mem::replace(&mut self.tight, false);
+            let prefix = format!("\x1b[{DIM}m│\x1b[0m ");
+            self.with_prefix(&prefix, &prefix, |fmt| fmt.contents(&node.children));
+            self.tight = tight;
+        } else if node.is::<BulletList>() {
+            let bullet = self.options.bullet;
+            self.list(node, |_| bullet.to_owned());
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            let start = data.start as usize;
+            self.list(node, |idx| format!("{}.", start + idx));
+        } else if let Some(data) = node.cast::<Table>() {
+            self.table(node, &data.alignments);
+        } else {
+            self.contents(&node.children);
+        }
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes.iter() {
+            self.node(node);
+        }
+    }
+
+    /// Add inline text, it is wrapped and written out when the current block ends.
+    pub fn text(&mut self, text: &str) {
+        self.inline.push_str(&sanitize(text));
+    }
+
+    /// Execute `f` with inline content styled with SGR parameters (e.g. `"1"` for bold).
+    pub fn styled(&mut self, sgr: &str, f: impl FnOnce(&mut Self)) {
+        self.inline.push_str(&format!("\x1b[{sgr}m"));
+        self.styles.push(sgr.to_owned());
+
+        f(self);
+
+        self.styles.pop();
+        self.inline.push_str("\x1b[0m");
+        for sgr in self.styles.iter() {
+            self.inline.push_str(&format!("\x1b[{sgr}m"));
+        }
+    }
+
+    /// Start a new block, it is separated from previous content with an empty line
+    /// (or with a line break inside of tight lists).
+    pub fn block(&mut self) {
+        self.flush();
+        if self.fresh { return; }
+        if !self.tight { self.line(""); }
+    }
+
+    /// Write a line of block content (prefixed according to the current nesting),
+    /// it is not wrapped.
+    pub fn line(&mut self, line: &str) {
+        let mut result = String::new();
+        for prefix in self.prefixes.iter_mut() {
+            if prefix.first_used {
+                result.push_str(&prefix.rest);
+            } else {
+                result.push_str(&prefix.first);
+                prefix.first_used = true;
+            }
+        }
+        result.push_str(line);
+        self.result.push_str(result.trim_end_matches(' '));
+        self.result.push('\n');
+        self.fresh = false;
+    }
+
+    /// Render inline nodes into a separate string (without wrapping),
+    /// used for table cells.
+    pub fn render_inline(&mut self, nodes: &[Node]) -> String {
+        self.capture(|fmt| fmt.contents(nodes))
+    }
+
+    fn capture(&mut self, f: impl FnOnce(&mut Self)) -> String {
+        let inline = mem::take(&mut self.inline);
+        let styles = mem::take(&mut self.styles);
+
+        f(self);
+
+        self.styles = styles;
+        mem::replace(&mut self.inline, inline)
+    }
+
+    /// Execute `f` with all lines prefixed with a given string (`first` for the first line,
+    /// `rest` for the following ones), this is how quotes and list items are rendered.
+    pub fn with_prefix(&mut self, first: &str, rest: &str, f: impl FnOnce(&mut Self)) {
+        self.flush();
+        self.prefixes.push(Prefix { first: first.to_owned(), rest: rest.to_owned(), first_used: false });
+        self.fresh = true;
+
+        f(self);
+        self.flush();
+
+        // make sure that empty list items still have a bullet
+        if !self.prefixes.last().unwrap().first_used {
+            self.line("");
+        }
+        self.prefixes.pop();
+    }
+
+    // width available for content after prefixes, 0 if unlimited
+    fn available_width(&self) -> usize {
+        if self.options.width == 0 { return 0; }
+        let prefix = self.prefixes.iter().map(|p| visible_width(&p.rest)).sum::<usize>();
+        self.options.width.saturating_sub(prefix).max(20.min(self.options.width))
+    }
+
+    fn flush(&mut self) {
+        let inline = mem::take(&mut self.inline);
+        if visible_width(&inline) == 0 { return; }
+
+        for line in wrap(&inline, self.available_width()) {
+            self.line(&line);
+        }
+    }
+
+    fn link_url(&mut self, url: &str) {
+        if self.options.link_urls && !url.is_empty() {
+            self.styled(DIM, |fmt| fmt.text(&format!(" ({url})")));
+        }
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) {
+        self.block();
+        let sgr = match level { 1 => HEADING1, 2 => HEADING2, _ => HEADING };
+        self.styled(sgr, |fmt| fmt.contents(&node.children));
+        self.flush();
+    }
+
+    fn code(&mut self, content: &str, lang: Option<&str>) {
+        self.block();
+        // highlighter copies text into its output as is, so it's sanitized beforehand
+        let content = sanitize(&content.strip_suffix('\n').unwrap_or(content).replace('\t', "    "));
+        let lines = highlight(&self.options, &content, lang).unwrap_or_else(
+            || content.split('\n').map(str::to_owned).collect()
+        );
+
+        let label = lang.map(|lang| format!(" {} ", sanitize(lang))).unwrap_or_default();
+        let mut width = lines.iter().map(|line| visible_width(line)).max().unwrap_or_default() + 2;
+        width = width.max(visible_width(&label) + 2);
+        if self.available_width() != 0 {
+            width = width.min(self.available_width() - 1);
+        }
+        let rest = width.saturating_sub(visible_width(&label) + 1);
+
+        self.line(&format!("\x1b[{DIM}m┌─{label}{}\x1b[0m", "─".repeat(rest)));
+        for line in lines {
+            self.line(&format!("\x1b[{DIM}m│\x1b[0m {line}\x1b[0m"));
+        }
+        self.line(&format!("\x1b[{DIM}m└{}\x1b[0m", "─".repeat(width)));
+    }
+
+    fn list(&mut self, node: &Node, marker: impl Fn(usize) -> String) {
+        self.block();
+
+        // paragraphs are only kept in loose lists
+        let tight = !node.children.iter().any(
+            |item| item.children.iter().any(|child| child.is::<Paragraph>())
+        );
+        let old_tight = mem::replace(&mut self.tight, tight);
+
+        for (idx, item) in node.children.iter().enumerate() {
+            if idx > 0 { self.block(); }
+            let marker = marker(idx);
+            let rest = " ".repeat(visible_width(&marker) + 1);
+            self.with_prefix(&format!("{marker} "), &rest, |fmt| fmt.contents(&item.children));
+        }
+
+        self.tight = old_tight;
+    }
+
+    fn table(&mut self, node: &Node, alignments: &[ColumnAlignment]) {
+        self.block();
+
+        let mut rows = Vec::new();
+        let mut head_rows = 0;
+
+        for section in node.children.iter() {
+            let head = section.is::<TableHead>();
+            for row in section.children.iter() {
+                let cells = row.children.iter().map(|cell| {
+                    let text = if head {
+                        self.capture(|fmt| fmt.styled(BOLD, |fmt| fmt.contents(&cell.children)))
+                    } else {
+                        self.render_inline(&cell.children)
+                    };
+                    text.replace('\n', " ")
+                }).collect::<Vec<_>>();
+                rows.push(cells);
+            }
+            if head { head_rows = rows.len(); }
+        }
+
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+        let mut widths = vec![0; columns];
+        for row in rows.iter() {
+            for (idx, cell) in row.iter().enumerate() {
+                widths[idx] = widths[idx].max(visible_width(cell));
+            }
+        }
+
+        let border = |left: &str, middle: &str, right: &str| {
+            let line = widths.iter().map(|width| "─".repeat(width + 2)).collect::<Vec<_>>().join(middle);
+            format!("\x1b[{DIM}m{left}{line}{right}\x1b[0m")
+        };
+        let separator = format!("\x1b[{DIM}m│\x1b[0m");
+
+        let mut lines = vec![border("┌", "┬", "┐")];
+        for (row_idx, row) in rows.iter().enumerate() {
+            let mut line = separator.clone();
+            for (idx, width) in widths.iter().enumerate() {
+                let cell = row.get(idx).map(|s| s.as_str()).unwrap_or_default();
+                let pad = width - visible_width(cell);
+                let (left, right) = match alignments.get(idx).copied().unwrap_or_default() {
+                    ColumnAlignment::Right => (pad, 0),
+                    ColumnAlignment::Center => (pad / 2, pad - pad / 2),
+                    ColumnAlignment::Left | ColumnAlignment::None => (0, pad),
+                };
+                line.push_str(&format!(" {}{}{} {}", " ".repeat(left), cell, " ".repeat(right), separator));
+            }
+            lines.push(line);
+
+            if row_idx + 1 == head_rows && rows.len() > head_rows {
+                lines.push(border("├", "┼", "┤"));
+            }
+        }
+        lines.push(border("└", "┴", "┘"));
+
+        for line in lines {
+            self.line(&line);
+        }
+    }
+}
+
+#[cfg(feature = "syntect")]
+fn highlight(options: &AnsiOptions, code: &str, lang: Option<&str>) -> Option<Vec<String>> {
+    use crate::plugins::extra::syntect::{default_syntax_set, default_theme_set};
+    use syntect::easy::HighlightLines;
+    use syntect::util::as_24_bit_terminal_escaped;
+
+    let ss = options.syntax_set.clone().unwrap_or_else(default_syntax_set);
+    let ts = options.theme_set.clone().unwrap_or_else(default_theme_set);
+    let theme = ts.themes.get(options.theme.as_ref()?)?;
+    let syntax = ss.find_syntax_by_token(lang?)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in code.split('\n') {
+        let line = format!("{line}\n");
+        let regions = highlighter.highlight_line(&line, &ss).ok()?;
+        let mut line = as_24_bit_terminal_escaped(&regions, false);
+        line.truncate(line.trim_end_matches('\n').len());
+        lines.push(line);
+    }
+    Some(lines)
+}
+
+#[cfg(not(feature = "syntect"))]
+fn highlight(_: &AnsiOptions, _: &str, _: Option<&str>) -> Option<Vec<String>> {
+    None
+}
+
+// remove control characters from user content, so it can't mess up terminal state
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|c| *c == '\n' || !c.is_control()).collect()
+}
+
+// iterate over text, yielding escape sequences and regular characters separately
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.chars();
+        let len = match chars.next()? {
+            '\x1b' => rest.find('m').map(|pos| pos + 1).unwrap_or(rest.len()),
+            ch => ch.len_utf8(),
+        };
+        let (token, tail) = rest.split_at(len);
+        rest = tail;
+        Some(token)
+    })
+}
+
+// display width in terminal columns (wide characters take two), escape sequences excluded
+fn visible_width(text: &str) -> usize {
+    tokens(text).filter(|token| !token.starts_with('\x1b')).map(UnicodeWidthStr::width).sum()
+}
+
+// word-wrap text containing escape sequences, styles are reset at the end of each
+// line and restored at the start of the next one, so prefixes are not affected
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut active : Vec<&str> = Vec::new();
+
+    for hard_line in text.split('\n') {
+        let mut line = active.concat();
+        let mut line_width = 0;
+
+        for word in hard_line.split(' ') {
+            let word_width = visible_width(word);
+            if word_width != 0 {
+                if line_width != 0 && width != 0 && line_width + 1 + word_width > width {
+                    if !active.is_empty() { line.push_str("\x1b[0m"); }
+                    lines.push(mem::replace(&mut line, active.concat()));
+                    line_width = 0;
+                }
+                if line_width != 0 {
+                    line.push(' ');
+                    line_width += 1;
+                }
+                line_width += word_width;
+            }
+            line.push_str(word);
+
+            for token in tokens(word) {
+                if token == "\x1b[0m" {
+                    active.clear();
+                } else if token.starts_with('\x1b') {
+                    active.push(token);
+                }
+            }
+        }
+
+        if !active.is_empty() { line.push_str("\x1b[0m"); }
+        lines.push(line);
+    }
+
+    lines
+}
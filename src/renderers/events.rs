@@ -0,0 +1,247 @@
+//! Event stream over the AST, similar to `pulldown_cmark::Event`.
+//!
+//! Lets this parser (with all its plugins) feed consumers written for event-based
+//! parsers. Container nodes produce [Event::Start] and [Event::End], leaf nodes
+//! produce a single event. Nodes this module doesn't know about are reported
+//! as [Tag::Custom] containers, so their children are still visited.
+//!
+//! Like in pulldown-cmark, there are no paragraphs inside of tight lists,
+//! table cells in the head are not wrapped in a row, and table body isn't reported.
+//!
+//! ```rust
+//! use markdown_it::renderers::events::{events, Event, Tag};
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("Hello *world*");
+//! assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+//!     Event::Start(Tag::Paragraph),
+//!     Event::Text("Hello ".into()),
+//!     Event::Start(Tag::Emphasis),
+//!     Event::Text("world".into()),
+//!     Event::End(Tag::Emphasis),
+//!     Event::End(Tag::Paragraph),
+//! ]);
+//! ```
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use crate::Node;
+use crate::parser::core::Root;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, ListItem, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableBody, TableCell, TableHead, TableRow};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use super::{linkified_url, text_content};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single event, see [module documentation](self).
+pub enum Event<'a> {
+    /// Start of a container node.
+    Start(Tag<'a>),
+    /// End of a container node.
+    End(Tag<'a>),
+    /// Text, including content of code blocks.
+    Text(Cow<'a, str>),
+    /// Inline code.
+    Code(Cow<'a, str>),
+    /// Html block.
+    Html(&'a str),
+    /// Inline html.
+    InlineHtml(&'a str),
+    SoftBreak,
+    HardBreak,
+    /// Thematic break.
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Container node types.
+pub enum Tag<'a> {
+    Paragraph,
+    /// Heading with a level from 1 to 6.
+    Heading(u8),
+    BlockQuote,
+    CodeBlock(CodeBlockKind<'a>),
+    /// List, with a starting number if it's ordered.
+    List(Option<u32>),
+    Item,
+    Table(&'a [ColumnAlignment]),
+    TableHead,
+    TableRow,
+    TableCell,
+    Emphasis,
+    Strong,
+    Strikethrough,
+    /// Link (also used for autolinks).
+    Link { url: &'a str, title: Option<&'a str> },
+    /// Image, its alt text is reported as content.
+    Image { url: &'a str, title: Option<&'a str> },
+    /// Any node not listed above.
+    Custom(CustomNode<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Type of a code block.
+pub enum CodeBlockKind<'a> {
+    Indented,
+    /// Fenced code block with its info string.
+    Fenced(&'a str),
+}
+
+#[derive(Clone, Copy)]
+/// Reference to a node unknown to this module, compared by identity.
+pub struct CustomNode<'a>(pub &'a Node);
+
+impl Debug for CustomNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomNode").field(&self.0.name()).finish()
+    }
+}
+
+impl PartialEq for CustomNode<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+/// Iterate over events produced by a node and its descendants.
+pub fn events(node: &Node) -> Events<'_> {
+    Events::new(node)
+}
+
+#[derive(Debug)]
+enum Step<'a> {
+    Enter(&'a Node),
+    Emit(Event<'a>),
+}
+
+#[derive(Debug)]
+/// Iterator over events, see [events].
+pub struct Events<'a> {
+    stack: Vec<Step<'a>>,
+}
+
+impl<'a> Events<'a> {
+    pub fn new(node: &'a Node) -> Self {
+        Self { stack: vec![Step::Enter(node)] }
+    }
+
+    fn container(&mut self, tag: Tag<'a>, children: &'a [Node]) -> Event<'a> {
+        self.stack.push(Step::Emit(Event::End(tag.clone())));
+        self.stack.extend(children.iter().rev().map(Step::Enter));
+        Event::Start(tag)
+    }
+
+    fn leaf_text(&mut self, tag: Tag<'a>, content: &'a str) -> Event<'a> {
+        self.stack.push(Step::Emit(Event::End(tag.clone())));
+        if !content.is_empty() {
+            self.stack.push(Step::Emit(Event::Text(content.into())));
+        }
+        Event::Start(tag)
+    }
+
+    // returns None for nodes that don't produce events themselves
+    fn enter(&mut self, node: &'a Node) -> Option<Event<'a>> {
+        let event = if let Some(data) = node.cast::<Text>() {
+            Event::Text(data.content.as_str().into())
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            Event::Text(data.content.as_str().into())
+        } else if node.is::<Softbreak>() {
+            Event::SoftBreak
+        } else if node.is::<Hardbreak>() {
+            Event::HardBreak
+        } else if node.is::<ThematicBreak>() {
+            Event::Rule
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            Event::Html(&data.content)
+        } else if let Some(data) = node.cast::<HtmlInline>() {
+            Event::InlineHtml(&data.content)
+        } else if node.is::<CodeInline>() {
+            Event::Code(text_content(node))
+        } else if node.is::<Root>() || node.is::<TableBody>() {
+            self.stack.extend(node.children.iter().rev().map(Step::Enter));
+            return None;
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.leaf_text(Tag::CodeBlock(CodeBlockKind::Indented), &data.content)
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.leaf_text(Tag::CodeBlock(CodeBlockKind::Fenced(&data.info)), &data.content)
+        } else if node.is::<TableHead>() {
+            self.stack.push(Step::Emit(Event::End(Tag::TableHead)));
+            for row in node.children.iter().rev() {
+                self.stack.extend(row.children.iter().rev().map(Step::Enter));
+            }
+            Event::Start(Tag::TableHead)
+        } else {
+            let tag = if node.is::<Paragraph>() {
+                Tag::Paragraph
+            } else if let Some(data) = node.cast::<ATXHeading>() {
+                Tag::Heading(data.level)
+            } else if let Some(data) = node.cast::<SetextHeader>() {
+                Tag::Heading(data.level)
+            } else if node.is::<Blockquote>() {
+                Tag::BlockQuote
+            } else if node.is::<BulletList>() {
+                Tag::List(None)
+            } else if let Some(data) = node.cast::<OrderedList>() {
+                Tag::List(Some(data.start))
+            } else if node.is::<ListItem>() {
+                Tag::Item
+            } else if let Some(data) = node.cast::<Table>() {
+                Tag::Table(&data.alignments)
+            } else if node.is::<TableRow>() {
+                Tag::TableRow
+            } else if node.is::<TableCell>() {
+                Tag::TableCell
+            } else if node.is::<Em>() {
+                Tag::Emphasis
+            } else if node.is::<Strong>() {
+                Tag::Strong
+            } else if node.is::<Strikethrough>() {
+                Tag::Strikethrough
+            } else if let Some(data) = node.cast::<Link>() {
+                Tag::Link { url: &data.url, title: data.title.as_deref() }
+            } else if let Some(data) = node.cast::<Image>() {
+                Tag::Image { url: &data.url, title: data.title.as_deref() }
+            } else if let Some(data) = node.cast::<Autolink>() {
+                Tag::Link { url: &data.url, title: None }
+            } else if let Some(url) = linkified_url(node) {
+                Tag::Link { url, title: None }
+            } else {
+                Tag::Custom(CustomNode(node))
+            };
+            self.container(tag, &node.children)
+        };
+        Some(event)
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Step::Emit(event) => return Some(event),
+                Step::Enter(node) => {
+                    if let Some(event) = self.enter(node) { return Some(event); }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Gemtext renderer, for publishing on [Gemini](https://geminiprotocol.net/).
+//!
+//! Gemtext is line-based and has no inline markup, so:
+//!  - paragraphs are written on a single line (clients wrap them),
+//!  - links are collected and written as `=>` lines after each paragraph
+//!    (or after a whole list or quote),
+//!  - headings are clamped to 3 levels,
+//!  - nested lists are flattened to `*` items,
+//!  - code fences keep their language and title as alt-text,
+//!  - tables become preformatted text.
+//!
+//! Text lines that would be read as links, headings, list items, quotes or
+//! preformatting toggles, and code lines starting with `` ``` ``, are indented
+//! with a space.
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("#### Intro\n\nRead [the docs](https://example.org/)\nfor *more*.\n\n- a\n  - b");
+//! assert_eq!(
+//!     markdown_it::renderers::gemtext::render(&ast),
+//!     "### Intro\n\nRead the docs for more.\n=> https://example.org/ the docs\n\n* a\n* b\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::mem;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::tables::{Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use super::linkified_url;
+use super::plain::table_lines;
+
+/// Custom handler for a node type, see [GemtextRenderer::set_handler].
+pub type GemtextFn = fn (node: &Node, fmt: &mut GemtextRenderer);
+
+/// Render node to gemtext.
+pub fn render(node: &Node) -> String {
+    GemtextRenderer::new().render(node)
+}
+
+#[derive(Debug, Default)]
+/// Gemtext renderer, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::block::hr::ThematicBreak;
+/// use markdown_it::renderers::gemtext::GemtextRenderer;
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("a\n\n---\n\nb");
+///
+/// let mut fmt = GemtextRenderer::new();
+/// fmt.set_handler::<ThematicBreak>(|_, fmt| {
+///     fmt.block();
+///     fmt.line("~~~");
+///     fmt.end_block();
+/// });
+/// assert_eq!(fmt.render(&ast), "a\n\n~~~\n\nb\n");
+/// ```
+pub struct GemtextRenderer {
+    handlers: HashMap<TypeKey, GemtextFn>,
+    result: String,
+    inline: String,
+    links: Vec<(String, String)>,
+    // depth of lists and quotes, blocks inside of them are not separated
+    nesting: usize,
+    quote: bool,
+    list_item: bool,
+}
+
+impl GemtextRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: GemtextFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting gemtext.
+    pub fn render(mut self, node: &Node) -> String {
+        self.node(node);
+        self.end_block();
+        self.result
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() {
+            self.text(" ");
+        } else if node.is::<Hardbreak>() {
+            self.text("\n");
+        } else if node.is::<HtmlInline>() || node.is::<HtmlBlock>() {
+            // raw html is dropped
+        } else if let Some(data) = node.cast::<Link>() {
+            let start = self.inline.len();
+            self.contents(&node.children);
+            let text = collapse_whitespace(&self.inline[start..]);
+            self.link(&data.url, &text);
+        } else if let Some(data) = node.cast::<Image>() {
+            let start = self.inline.len();
+            self.contents(&node.children);
+            let text = collapse_whitespace(&self.inline[start..]);
+            self.link(&data.url, &text);
+        } else if let Some(data) = node.cast::<Autolink>() {
+            self.contents(&node.children);
+            self.link(&data.url, "");
+        } else if let Some(url) = linkified_url(node) {
+            self.contents(&node.children);
+            self.link(url, "");
+        } else if node.is::<Paragraph>() {
+            if self.list_item {
+                // list items are written on a single line
+                self.text(" ");
+                self.contents(&node.children);
+            } else {
+                self.block();
+                self.contents(&node.children);
+                self.end_block();
+            }
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level);
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level);
+        } else if node.is::<ThematicBreak>() {
+            self.block();
+            self.line("---");
+            self.end_block();
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.preformatted("", &data.content);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            // alt-text is parsed from info string, so it's unescaped
+            let info = &data.parsed_info;
+            let alt = [info.lang.as_deref(), info.title.as_deref()].into_iter().flatten().collect::<Vec<_>>();
+            self.preformatted(&alt.join(" "), &data.content);
+        } else if node.is::<Blockquote>() {
+            self.block();
+            self.nesting += 1;
+            let quote = mem::replace(&mut self.quote, true);
+            let list_item = mem::replace(&mut self.list_item, false);
+            self.contents(&node.children);
+            self.flush_inline();
+            self.list_item = list_item;
+            self.quote = quote;
+            self.nesting -= 1;
+            self.end_block();
+        } else if node.is::<BulletList>() || node.is::<OrderedList>() {
+            self.block();
+            self.nesting += 1;
+            let list_item = self.list_item;
+            for item in node.children.iter() {
+                self.list_item = true;
+                self.contents(&item.children);
+                self.flush_inline();
+            }
+            self.list_item = list_item;
+            self.nesting -= 1;
+            self.end_block();
+        } else if node.is::<Table>() {
+            self.table(node);
+        } else {
+            self.contents(&node.children);
+        }
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes.iter() {
+            self.node(node);
+        }
+    }
+
+    /// Add inline text to the current line.
+    pub fn text(&mut self, text: &str) {
+        self.inline.push_str(text);
+    }
+
+    /// Add a link, it will be written on its own line after the current block.
+    pub fn link(&mut self, url: &str, text: &str) {
+        if !url.is_empty() {
+            self.links.push((url.to_owned(), text.to_owned()));
+        }
+    }
+
+    /// Start a new block, writes out pending inline text and separates
+    /// top-level blocks with an empty line.
+    pub fn block(&mut self) {
+        self.flush_inline();
+        if self.nesting == 0 && !self.result.is_empty() {
+            self.result.push('\n');
+        }
+    }
+
+    /// End a block, writes out pending inline text and collected links
+    /// (unless inside of a list or a quote).
+    pub fn end_block(&mut self) {
+        self.flush_inline();
+        if self.nesting > 0 { return; }
+        for (url, text) in mem::take(&mut self.links) {
+            if text.is_empty() {
+                self.result.push_str(&format!("=> {url}\n"));
+            } else {
+                self.result.push_str(&format!("=> {url} {text}\n"));
+            }
+        }
+    }
+
+    /// Write a line (it gets prefixed with `>` inside of quotes).
+    pub fn line(&mut self, line: &str) {
+        if self.quote { self.result.push_str("> "); }
+        self.result.push_str(line);
+        self.result.push('\n');
+    }
+
+    fn flush_inline(&mut self) {
+        let inline = mem::take(&mut self.inline);
+        let mut prefix = if self.list_item { "* " } else { "" };
+        for line in inline.split('\n') {
+            let line = collapse_whitespace(line);
+            if !line.is_empty() {
+                let guard = if prefix.is_empty() && is_line_markup(&line) { " " } else { "" };
+                self.line(&format!("{prefix}{guard}{line}"));
+                // line breaks inside of list items don't start a new item
+                prefix = "";
+            }
+        }
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) {
+        self.block();
+        let hashes = "#".repeat(level.clamp(1, 3) as usize);
+        self.contents(&node.children);
+        let text = mem::take(&mut self.inline).replace('\n', " ");
+        self.line(&format!("{hashes} {}", text.trim()));
+        self.end_block();
+    }
+
+    fn preformatted(&mut self, alt: &str, content: &str) {
+        self.block();
+        // toggle lines can't be prefixed, so quotes are interrupted
+        self.result.push_str(&format!("```{}\n", alt.trim()));
+        for line in content.split_inclusive('\n') {
+            // this line would close preformatted block early
+            if line.starts_with("```") { self.result.push(' '); }
+            self.result.push_str(line);
+        }
+        if !content.is_empty() && !content.ends_with('\n') { self.result.push('\n'); }
+        self.result.push_str("```\n");
+        self.end_block();
+    }
+
+    fn table(&mut self, node: &Node) {
+        self.flush_inline();
+
+        let mut rows = Vec::new();
+        let mut head_rows = 0;
+        for section in node.children.iter() {
+            for row in section.children.iter() {
+                rows.push(row.children.iter().map(|cell| {
+                    self.contents(&cell.children);
+                    mem::take(&mut self.inline).replace('\n', " ")
+                }).collect::<Vec<_>>());
+            }
+            if section.is::<TableHead>() { head_rows = rows.len(); }
+        }
+
+        let alignments = &node.cast::<Table>().unwrap().alignments;
+        let mut content = String::new();
+        for line in table_lines(&rows, head_rows, alignments) {
+            content.push_str(line.trim_end());
+            content.push('\n');
+        }
+        self.preformatted("", &content);
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// check if text line starts with gemtext line type markup
+fn is_line_markup(line: &str) -> bool {
+    ["=>", "#", "*", ">", "```"].iter().any(|markup| line.starts_with(markup))
+}
@@ -0,0 +1,441 @@
+//! LaTeX renderer.
+//!
+//! Supports nodes from [cmark](crate::plugins::cmark) and [extra](crate::plugins::extra)
+//! plugins. Headings become sections, lists become `itemize`/`enumerate`, code blocks
+//! become `verbatim` (or `lstlisting`), tables become `tabular` and links use `\href`.
+//! Raw html blocks are kept as LaTeX comments, inline html is dropped.
+//!
+//! Generated code expects `hyperref`, `graphicx` and `ulem` (for strikethrough)
+//! packages to be loaded, plus `listings` if that option is enabled. Use
+//! [standalone](LatexOptions::standalone) option to get a complete document.
+//!
+//! Any other node results in [LatexError::UnsupportedNode], unless you
+//! add a handler for it with [LatexRenderer::set_handler].
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("# Results\n\nWe got *50%* better & faster, see [this](https://example.org).");
+//! assert_eq!(
+//!     markdown_it::renderers::latex::render(&ast).unwrap(),
+//!     "\\section{Results}\n\nWe got \\emph{50\\%} better \\& faster, see \\href{https://example.org}{this}.\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::mem;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::common::sourcemap::SourcePos;
+use crate::parser::core::Root;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, ListItem, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use super::linkified_url;
+
+/// Custom handler for a node type, see [LatexRenderer::set_handler].
+pub type LatexFn = fn (node: &Node, fmt: &mut LatexRenderer) -> Result<(), LatexError>;
+
+#[derive(Debug, Clone)]
+/// Error returned when rendering LaTeX.
+pub enum LatexError {
+    /// Renderer doesn't know how to output this node.
+    UnsupportedNode {
+        /// Node type name, see [Node::name].
+        name: &'static str,
+        /// Position of the node in the source, if known.
+        srcmap: Option<SourcePos>,
+    },
+}
+
+impl Display for LatexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedNode { name, srcmap } => {
+                write!(f, "node {name} can't be rendered to LaTeX")?;
+                if let Some(srcmap) = srcmap {
+                    let (start, end) = srcmap.get_byte_offsets();
+                    write!(f, " (at bytes {start}..{end})")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LatexError {}
+
+/// Options for [LatexRenderer].
+#[derive(Debug, Clone)]
+pub struct LatexOptions {
+    /// Sectioning commands used for heading levels 1 to 6.
+    pub sections: [&'static str; 6],
+    /// Use `lstlisting` environment (with `language` option) instead of `verbatim` for code.
+    pub listings: bool,
+    /// Wrap output in `\documentclass{article}` with all required packages.
+    pub standalone: bool,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        Self {
+            sections: ["section", "subsection", "subsubsection", "paragraph", "subparagraph", "subparagraph"],
+            listings: false,
+            standalone: false,
+        }
+    }
+}
+
+/// Render node to LaTeX with default options.
+pub fn render(node: &Node) -> Result<String, LatexError> {
+    LatexRenderer::new(LatexOptions::default()).render(node)
+}
+
+/// Escape special characters in text.
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => result.push_str("\\textbackslash{}"),
+            '~' => result.push_str("\\textasciitilde{}"),
+            '^' => result.push_str("\\textasciicircum{}"),
+            '<' => result.push_str("\\textless{}"),
+            '>' => result.push_str("\\textgreater{}"),
+            '|' => result.push_str("\\textbar{}"),
+            '{' | '}' | '$' | '&' | '%' | '#' | '_' => {
+                result.push('\\');
+                result.push(ch);
+            }
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+// check if `\end{name}` (with optional spaces before `{`) is in the text
+fn ends_environment(text: &str, name: &str) -> bool {
+    text.match_indices("\\end").any(|(pos, end)| {
+        text[pos + end.len()..].trim_start().strip_prefix('{')
+            .and_then(|rest| rest.strip_prefix(name))
+            .is_some_and(|rest| rest.starts_with('}'))
+    })
+}
+
+// urls are written as is by hyperref, except for these
+fn escape_url(url: &str) -> String {
+    let mut result = String::with_capacity(url.len());
+    for ch in url.chars() {
+        if matches!(ch, '\\' | '{' | '}' | '%' | '#') { result.push('\\'); }
+        result.push(ch);
+    }
+    result
+}
+
+#[derive(Debug)]
+/// LaTeX renderer, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::inline::emphasis::Strong;
+/// use markdown_it::renderers::latex::{LatexOptions, LatexRenderer};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("**Note:** text");
+///
+/// let mut fmt = LatexRenderer::new(LatexOptions::default());
+/// fmt.set_handler::<Strong>(|node, fmt| {
+///     fmt.write("\\textsc{");
+///     fmt.contents(&node.children)?;
+///     fmt.write("}");
+///     Ok(())
+/// });
+/// assert_eq!(fmt.render(&ast).unwrap(), "\\textsc{Note:} text\n");
+/// ```
+pub struct LatexRenderer {
+    options: LatexOptions,
+    handlers: HashMap<TypeKey, LatexFn>,
+    result: String,
+    // nothing was written since the start of the current environment
+    fresh: bool,
+    // blocks are not separated with empty lines (inside of tight lists)
+    tight: bool,
+}
+
+impl LatexRenderer {
+    pub fn new(options: LatexOptions) -> Self {
+        Self {
+            options,
+            handlers: HashMap::new(),
+            result: String::new(),
+            fresh: true,
+            tight: false,
+        }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &LatexOptions {
+        &self.options
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: LatexFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting LaTeX code.
+    pub fn render(mut self, node: &Node) -> Result<String, LatexError> {
+        if self.options.standalone {
+            self.write("\\documentclass{article}\n\\usepackage[T1]{fontenc}\n\\usepackage{graphicx}\n");
+            if self.options.listings { self.write("\\usepackage{listings}\n"); }
+            self.write("\\usepackage[normalem]{ulem}\n\\usepackage{hyperref}\n\n\\begin{document}\n");
+            self.fresh = true;
+        }
+
+        self.node(node)?;
+        self.end_line();
+
+        if self.options.standalone {
+            self.write("\\end{document}\n");
+        }
+        Ok(self.result)
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) -> Result<(), LatexError> {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            return f(node, self);
+        }
+
+        if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() {
+            self.write("\n");
+        } else if node.is::<Hardbreak>() {
+            // `{}` keeps `[` on the next line from being read as an argument
+            self.write("\\\\{}\n");
+        } else if node.is::<Em>() {
+            self.command("emph", &node.children)?;
+        } else if node.is::<Strong>() {
+            self.command("textbf", &node.children)?;
+        } else if node.is::<Strikethrough>() {
+            self.command("sout", &node.children)?;
+        } else if node.is::<CodeInline>() {
+            self.command("texttt", &node.children)?;
+        } else if let Some(data) = node.cast::<Link>() {
+            self.write(&format!("\\href{{{}}}{{", escape_url(&data.url)));
+            self.contents(&node.children)?;
+            self.write("}");
+        } else if let Some(data) = node.cast::<Autolink>() {
+            self.write(&format!("\\url{{{}}}", escape_url(&data.url)));
+        } else if let Some(url) = linkified_url(node) {
+            self.write(&format!("\\url{{{}}}", escape_url(url)));
+        } else if let Some(data) = node.cast::<Image>() {
+            self.write(&format!("\\includegraphics{{{}}}", escape_url(&data.url)));
+        } else if node.is::<HtmlInline>() {
+            // raw html is dropped
+        } else if node.is::<Root>() {
+            self.contents(&node.children)?;
+        } else if node.is::<Paragraph>() {
+            self.block();
+            self.contents(&node.children)?;
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level)?;
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level)?;
+        } else if node.is::<ThematicBreak>() {
+            self.block();
+            self.write("\\begin{center}\\rule{0.5\\linewidth}{0.4pt}\\end{center}");
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.code(&data.content, None);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.code(&data.content, data.parsed_info.lang.as_deref());
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            self.block();
+            // lone `\r` ends a line in TeX as well, so it can't be left in a comment
+            for line in data.content.lines().flat_map(|line| line.split('\r')) {
+                self.write(&format!("% {line}\n"));
+            }
+        } else if node.is::<Blockquote>() {
+            let tight = mem::replace(&mut self.tight, false);
+            self.environment("quote", |fmt| fmt.contents(&node.children))?;
+            self.tight = tight;
+        } else if node.is::<BulletList>() {
+            self.list(node, "itemize", None)?;
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            self.list(node, "enumerate", Some(data.start))?;
+        } else if let Some(data) = node.cast::<Table>() {
+            self.table(node, &data.alignments)?;
+        } else {
+            return Err(LatexError::UnsupportedNode { name: node.name(), srcmap: node.srcmap });
+        }
+
+        Ok(())
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) -> Result<(), LatexError> {
+        for node in nodes.iter() {
+            self.node(node)?;
+        }
+        Ok(())
+    }
+
+    /// Write raw LaTeX code.
+    pub fn write(&mut self, latex: &str) {
+        self.result.push_str(latex);
+        self.fresh = false;
+    }
+
+    /// Write text, escaping special characters.
+    pub fn text(&mut self, text: &str) {
+        self.write(&escape(text));
+    }
+
+    /// Start a new block, it is separated from previous content with an empty line
+    /// (unless it's the first block in an environment, or inside of a tight list).
+    pub fn block(&mut self) {
+        if self.fresh { return; }
+        self.end_line();
+        if !self.tight { self.result.push('\n'); }
+    }
+
+    /// Write an environment (`\begin{name}`, content, `\end{name}`).
+    pub fn environment(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut Self) -> Result<(), LatexError>
+    ) -> Result<(), LatexError> {
+        self.block();
+        self.write(&format!("\\begin{{{name}}}\n"));
+        self.fresh = true;
+        f(self)?;
+        self.end_line();
+        self.write(&format!("\\end{{{name}}}"));
+        Ok(())
+    }
+
+    fn command(&mut self, name: &str, children: &[Node]) -> Result<(), LatexError> {
+        self.write(&format!("\\{name}{{"));
+        self.contents(children)?;
+        self.write("}");
+        Ok(())
+    }
+
+    fn end_line(&mut self) {
+        if !self.result.is_empty() && !self.result.ends_with('\n') {
+            self.result.push('\n');
+        }
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) -> Result<(), LatexError> {
+        self.block();
+        let section = self.options.sections[(level as usize).clamp(1, 6) - 1];
+        self.command(section, &node.children)
+    }
+
+    fn code(&mut self, content: &str, lang: Option<&str>) {
+        self.block();
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        let env = if self.options.listings { "lstlisting" } else { "verbatim" };
+
+        // environment can't be escaped from inside, so if its end is in the
+        // content, code is written as escaped text instead
+        if ends_environment(content, env) {
+            self.write("\\begin{flushleft}\n");
+            for (idx, line) in content.split('\n').enumerate() {
+                if idx > 0 { self.write("\\\\\n"); }
+                let line = escape(line.trim_end_matches('\r')).replace('\t', "    ").replace(' ', "~");
+                self.write(&format!("\\texttt{{{line}}}"));
+            }
+            self.write("\n\\end{flushleft}");
+            return;
+        }
+
+        match lang {
+            // language goes into listings options, so anything that isn't
+            // a plain name could change other options
+            Some(lang) if self.options.listings && lang.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '+' || ch == '-') => {
+                self.write(&format!("\\begin{{lstlisting}}[language={lang}]\n"));
+            }
+            _ => self.write(&format!("\\begin{{{env}}}\n")),
+        }
+        self.write(content);
+        self.write(&format!("\n\\end{{{env}}}"));
+    }
+
+    fn list(&mut self, node: &Node, name: &str, start: Option<u32>) -> Result<(), LatexError> {
+        // paragraphs are only kept in loose lists
+        let tight = !node.children.iter().any(
+            |item| item.children.iter().any(|child| child.is::<Paragraph>())
+        );
+
+        self.environment(name, |fmt| {
+            let old_tight = mem::replace(&mut fmt.tight, tight);
+            for (idx, item) in node.children.iter().enumerate() {
+                if idx > 0 { fmt.block(); }
+                match start {
+                    Some(start) if start != 1 => fmt.write(&format!("\\item[{}.] ", start as usize + idx)),
+                    // `{}` keeps `[` at the start of the item from being read as a label
+                    _ => fmt.write("\\item{} "),
+                }
+                fmt.fresh = true;
+                if item.is::<ListItem>() {
+                    fmt.contents(&item.children)?;
+                } else {
+                    fmt.node(item)?;
+                }
+            }
+            fmt.tight = old_tight;
+            Ok(())
+        })
+    }
+
+    fn table(&mut self, node: &Node, alignments: &[ColumnAlignment]) -> Result<(), LatexError> {
+        let columns = alignments.iter().map(|align| match align {
+            ColumnAlignment::Center => 'c',
+            ColumnAlignment::Right => 'r',
+            ColumnAlignment::Left | ColumnAlignment::None => 'l',
+        }).collect::<String>();
+
+        self.block();
+        self.write(&format!("\\begin{{tabular}}{{{columns}}}\n\\hline\n"));
+
+        for section in node.children.iter() {
+            for row in section.children.iter() {
+                for (idx, cell) in row.children.iter().enumerate() {
+                    if idx > 0 { self.write(" & "); }
+                    if section.is::<TableHead>() {
+                        self.command("textbf", &cell.children)?;
+                    } else {
+                        self.contents(&cell.children)?;
+                    }
+                }
+                self.write(" \\\\\n");
+            }
+            if section.is::<TableHead>() { self.write("\\hline\n"); }
+        }
+
+        self.write("\\hline\n\\end{tabular}");
+        Ok(())
+    }
+}
@@ -0,0 +1,1083 @@
+//! Markdown renderer, rewrites a document in a canonical style.
+//!
+//! Output uses ATX headings, fenced code, `-` bullets, `_emphasis_` and `**strong**`
+//! (other markers are only used where these ones don't work, e.g. intraword or in
+//! adjacent lists), aligned tables and `\` for hard breaks. Paragraphs are kept as they
+//! are, joined into single lines or re-wrapped at a given width, see [Wrap].
+//!
+//! Use [format()] to get a formatted document from source: it also checks that
+//! result renders to the same HTML as the original, so meaning never changes
+//! silently. Formatting is idempotent, formatted document stays the same when
+//! formatted again.
+//!
+//! Literal text is escaped where it could be parsed as markup (including block
+//! markers at the start of a line), so the result never has markup that wasn't
+//! in the AST.
+//!
+//! Reference links are written out as inline links (definitions aren't kept in the AST),
+//! unknown nodes are written using [trivia] if it's enabled,
+//! and as their children otherwise.
+//!
+//! ```rust
+//! use markdown_it::renderers::markdown::{format, MarkdownOptions};
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let source = "Title\n=====\n\n    code\n\n* one *two*\n* __three__\n";
+//! assert_eq!(
+//!     format(md, source, MarkdownOptions::default()).unwrap(),
+//!     "# Title\n\n```\ncode\n```\n\n- one _two_\n- **three**\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::mem;
+use crate::{MarkdownIt, Node, NodeValue};
+use crate::common::TypeKey;
+use crate::parser::core::Root;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::{self, HtmlBlock};
+use crate::plugins::html::html_inline::HtmlInline;
+use crate::plugins::trivia::{self, Trivia};
+use super::{is_block, text_content};
+
+/// Custom handler for a node type, see [MarkdownRenderer::set_handler].
+pub type MarkdownFn = fn (node: &Node, fmt: &mut MarkdownRenderer);
+
+#[derive(Debug, Clone)]
+/// Error returned by [format()].
+pub enum FormatError {
+    /// Formatted document renders to different HTML than the original one
+    /// (this is a bug in the formatter or in a custom handler).
+    MeaningChanged {
+        /// HTML rendered from the original source.
+        expected: String,
+        /// HTML rendered from the formatted source.
+        found: String,
+    },
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MeaningChanged { .. } => {
+                write!(f, "formatted document renders differently from the original one")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// How paragraphs are wrapped, see [MarkdownOptions::wrap].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    /// Keep line breaks where they are in the source.
+    Preserve,
+    /// Join each paragraph into a single line.
+    Never,
+    /// Re-wrap paragraphs at this many columns.
+    Width(usize),
+}
+
+/// Options for [MarkdownRenderer].
+#[derive(Debug, Clone)]
+pub struct MarkdownOptions {
+    /// How paragraphs are wrapped.
+    pub wrap: Wrap,
+    /// Marker for bullet list items (`-`, `*` or `+`).
+    pub bullet: char,
+    /// Marker for emphasis (`_` or `*`).
+    pub emphasis: char,
+    /// Marker for strong emphasis (`*` or `_`), it's doubled.
+    pub strong: char,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        Self {
+            wrap: Wrap::Preserve,
+            bullet: '-',
+            emphasis: '_',
+            strong: '*',
+        }
+    }
+}
+
+/// Render node as markdown with default options.
+pub fn render(node: &Node) -> String {
+    MarkdownRenderer::new(MarkdownOptions::default()).render(node)
+}
+
+/// Parse source with a given parser and write it back in canonical style.
+///
+/// Both documents are rendered to HTML and compared (with whitespace runs collapsed,
+/// except inside of `<pre>` and `<code>`), [FormatError::MeaningChanged] is returned
+/// if they differ.
+pub fn format(md: &MarkdownIt, source: &str, options: MarkdownOptions) -> Result<String, FormatError> {
+    let ast = md.parse(source);
+    let result = MarkdownRenderer::new(options).render(&ast);
+
+    let expected = ast.render();
+    let found = md.parse(&result).render();
+    if normalize_html(&expected) != normalize_html(&found) {
+        return Err(FormatError::MeaningChanged { expected, found });
+    }
+
+    Ok(result)
+}
+
+// collapse whitespace runs outside of elements where whitespace is significant
+fn normalize_html(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some((start, close)) = find_preformatted(rest) {
+        let end = rest[start..].find(close).map_or(rest.len(), |pos| start + pos + close.len());
+        result.push_str(&collapse_whitespace(&rest[..start]));
+        result.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+
+    result.push_str(&collapse_whitespace(rest));
+    result
+}
+
+// position of the first `<pre>` or `<code>` tag and its closing tag
+fn find_preformatted(html: &str) -> Option<(usize, &'static str)> {
+    let mut pos = 0;
+    while let Some(found) = html[pos..].find('<') {
+        let start = pos + found;
+        let tag = &html[start + 1..];
+        for (name, close) in [("pre", "</pre>"), ("code", "</code>")] {
+            if tag.starts_with(name) && tag[name.len()..].starts_with([' ', '>']) {
+                return Some((start, close));
+            }
+        }
+        pos = start + 1;
+    }
+    None
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (idx, word) in text.split(|c: char| c.is_ascii_whitespace()).enumerate() {
+        if idx > 0 && !result.ends_with(' ') { result.push(' '); }
+        result.push_str(word);
+    }
+    result
+}
+
+#[derive(Debug)]
+struct Prefix {
+    first: String,
+    rest: String,
+    first_used: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    // literal text, can be broken at spaces and escaped at the start of a line
+    Text(String),
+    // markup, it is written as is
+    Markup(String),
+    Softbreak,
+    Hardbreak,
+}
+
+#[derive(Debug)]
+/// Markdown renderer, see [module documentation](self).
+///
+/// Inline content is accumulated and written out (wrapped) when the next block
+/// starts, so custom handlers for inline nodes only need to call [text](Self::text)
+/// and [markup](Self::markup), and handlers for block nodes should call
+/// [block](Self::block) first.
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::inline::emphasis::Strong;
+/// use markdown_it::renderers::markdown::{MarkdownOptions, MarkdownRenderer};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("__bold__ text");
+///
+/// let mut fmt = MarkdownRenderer::new(MarkdownOptions::default());
+/// fmt.set_handler::<Strong>(|node, fmt| {
+///     fmt.markup("<b>");
+///     fmt.contents(&node.children);
+///     fmt.markup("</b>");
+/// });
+/// assert_eq!(fmt.render(&ast), "<b>bold</b> text\n");
+/// ```
+pub struct MarkdownRenderer {
+    options: MarkdownOptions,
+    handlers: HashMap<TypeKey, MarkdownFn>,
+    result: String,
+    inline: Vec<Piece>,
+    prefixes: Vec<Prefix>,
+    // nothing was written since the start of the current container
+    fresh: bool,
+    // blocks are separated by line breaks instead of empty lines
+    tight: bool,
+    // unknown nodes are written as inline content rather than as blocks
+    inline_context: bool,
+    // last written line is inline content (so `---` would make it a heading)
+    after_inline: bool,
+    // marker of the list immediately before the current block, so adjacent
+    // lists can use different markers and don't merge
+    last_list: Option<char>,
+    // first character written after the current inline node, if known
+    next_char: Option<char>,
+    // emphasis is written with markers used in the source
+    source_markers: bool,
+}
+
+impl MarkdownRenderer {
+    pub fn new(options: MarkdownOptions) -> Self {
+        Self {
+            options,
+            handlers: HashMap::new(),
+            result: String::new(),
+            inline: Vec::new(),
+            prefixes: Vec::new(),
+            fresh: true,
+            tight: false,
+            inline_context: false,
+            after_inline: false,
+            last_list: None,
+            next_char: None,
+            source_markers: false,
+        }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &MarkdownOptions {
+        &self.options
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: MarkdownFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting markdown.
+    pub fn render(mut self, node: &Node) -> String {
+        self.node(node);
+        self.flush();
+        self.result
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.markup(&data.markup);
+        } else if node.is::<Softbreak>() {
+            self.inline.push(Piece::Softbreak);
+        } else if node.is::<Hardbreak>() {
+            self.markup("\\");
+            self.inline.push(Piece::Hardbreak);
+        } else if let Some(data) = node.cast::<Em>() {
+            self.emphasis(node, [self.options.emphasis, data.marker], 1);
+        } else if let Some(data) = node.cast::<Strong>() {
+            self.emphasis(node, [self.options.strong, data.marker], 2);
+        } else if node.is::<Strikethrough>() {
+            self.markup("~~");
+            self.contents(&node.children);
+            self.markup("~~");
+        } else if let Some(data) = node.cast::<CodeInline>() {
+            self.code_inline(&text_content(node), data.marker_len);
+        } else if let Some(data) = node.cast::<HtmlInline>() {
+            self.markup(&data.content);
+        } else if let Some(data) = node.cast::<Link>() {
+            self.link(node, &data.url, data.title.as_deref());
+        } else if let Some(data) = node.cast::<Image>() {
+            self.image(node, &data.url, data.title.as_deref());
+        } else if node.is::<Autolink>() {
+            self.markup(&format!("<{}>", text_content(node)));
+        } else if node.is::<Paragraph>() {
+            self.block();
+            self.with_inline(true, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level);
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level);
+        } else if node.is::<ThematicBreak>() {
+            self.thematic_break();
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.code(&data.content, "");
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.code(&data.content, data.info.trim());
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            self.block();
+            let content = data.content.strip_suffix('\n').unwrap_or(&data.content);
+            for line in content.split('\n') {
+                self.line(line);
+            }
+        } else if node.is::<Blockquote>() {
+            self.block();
+            let tight = mem::replace(&mut self.tight, false);
+            self.with_prefix("> ", "> ", |fmt| {
+                fmt.with_inline(false, |fmt| fmt.contents(&node.children));
+            });
+            self.tight = tight;
+        } else if node.is::<BulletList>() {
+            // empty `-` item after inline content would be taken for a table delimiter row
+            let avoid = [self.last_list, has_empty_item(node).then_some('-')];
+            let bullet = [self.options.bullet, '-', '*', '+'].into_iter()
+                .find(|&ch| !avoid.contains(&Some(ch)))
+                .unwrap();
+            self.list(node, bullet, |_| bullet.to_string());
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            let delimiter = if self.last_list == Some('.') { ')' } else { '.' };
+            let start = data.start as usize;
+            self.list(node, delimiter, |idx| format!("{}{delimiter}", start + idx));
+        } else if let Some(data) = node.cast::<Table>() {
+            self.table(node, &data.alignments);
+        } else if node.ext.get::<Trivia>().is_some() && !node.is::<Root>() {
+            self.verbatim(&trivia::to_source(node));
+        } else {
+            self.contents(&node.children);
+        }
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        let next_char = self.next_char;
+        for (idx, node) in nodes.iter().enumerate() {
+            self.next_char = match nodes.get(idx + 1) {
+                Some(next) => leading_char(next),
+                None => next_char,
+            };
+            self.node(node);
+        }
+        self.next_char = next_char;
+    }
+
+    /// Add literal inline text, it is wrapped and written out when the current block ends.
+    ///
+    /// Text is escaped (it's supposed to come from [Text] nodes), so it's never
+    /// parsed as markup, including block markers at the start of a line.
+    pub fn text(&mut self, text: &str) {
+        self.inline.push(Piece::Text(escape_text(text)));
+    }
+
+    /// Add inline markup, it is never wrapped or escaped.
+    pub fn markup(&mut self, markup: &str) {
+        self.inline.push(Piece::Markup(markup.to_owned()));
+    }
+
+    /// Start a new block, it is separated from previous content with an empty line
+    /// (or with a line break inside of tight lists).
+    pub fn block(&mut self) {
+        self.flush();
+        self.last_list = None;
+        if self.fresh { return; }
+        if !self.tight { self.line(""); }
+    }
+
+    /// Write a line of block content (prefixed according to the current nesting),
+    /// it is not wrapped.
+    pub fn line(&mut self, line: &str) {
+        let mut result = String::new();
+        for prefix in self.prefixes.iter_mut() {
+            if prefix.first_used {
+                result.push_str(&prefix.rest);
+            } else {
+                result.push_str(&prefix.first);
+                prefix.first_used = true;
+            }
+        }
+        if line.is_empty() {
+            result.truncate(result.trim_end_matches(' ').len());
+        }
+        result.push_str(line);
+        self.result.push_str(&result);
+        self.result.push('\n');
+        self.fresh = false;
+        self.after_inline = false;
+    }
+
+    /// Render inline nodes into a single line (without wrapping),
+    /// used for headings and table cells.
+    pub fn render_inline(&mut self, nodes: &[Node]) -> String {
+        let pieces = self.capture(|fmt| fmt.contents(nodes));
+        join_inline(&pieces)
+    }
+
+    // render inline content separately, it's inserted into the current content later
+    fn capture(&mut self, f: impl FnOnce(&mut Self)) -> Vec<Piece> {
+        let inline = mem::take(&mut self.inline);
+        let next_char = self.next_char.take();
+        let inline_context = mem::replace(&mut self.inline_context, true);
+
+        f(self);
+
+        self.inline_context = inline_context;
+        self.next_char = next_char;
+        mem::replace(&mut self.inline, inline)
+    }
+
+    fn with_inline(&mut self, inline_context: bool, f: impl FnOnce(&mut Self)) {
+        let old = mem::replace(&mut self.inline_context, inline_context);
+        f(self);
+        self.inline_context = old;
+    }
+
+    /// Execute `f` with all lines prefixed with a given string (`first` for the first line,
+    /// `rest` for the following ones), this is how quotes and list items are rendered.
+    pub fn with_prefix(&mut self, first: &str, rest: &str, f: impl FnOnce(&mut Self)) {
+        self.flush();
+        self.prefixes.push(Prefix { first: first.to_owned(), rest: rest.to_owned(), first_used: false });
+        self.fresh = true;
+        self.last_list = None;
+
+        f(self);
+        self.flush();
+
+        // make sure that empty list items still have a bullet
+        if !self.prefixes.last().unwrap().first_used {
+            self.line("");
+        }
+        self.prefixes.pop();
+    }
+
+    // width available for content after prefixes, 0 if unlimited
+    fn available_width(&self) -> usize {
+        let Wrap::Width(width) = self.options.wrap else { return 0; };
+        if width == 0 { return 0; }
+        let prefix = self.prefixes.iter().map(|p| p.rest.chars().count()).sum::<usize>();
+        width.saturating_sub(prefix).max(20.min(width))
+    }
+
+    fn flush(&mut self) {
+        let inline = mem::take(&mut self.inline);
+        let empty = inline.iter().all(|piece| matches!(piece, Piece::Text(s) | Piece::Markup(s) if s.is_empty()));
+        if empty { return; }
+
+        // whitespace is significant inside of raw `<pre>` (which may be opened in a previous
+        // html block), so lines of paragraphs with its tags are kept as they are
+        let raw_pre = inline.iter().any(|piece| match piece {
+            Piece::Markup(markup) => {
+                let markup = markup.to_ascii_lowercase();
+                markup.starts_with("<pre") || markup.starts_with("</pre")
+            }
+            _ => false,
+        });
+        let (width, preserve) = if raw_pre {
+            (0, true)
+        } else {
+            (self.available_width(), self.options.wrap == Wrap::Preserve)
+        };
+
+        for line in layout(inline, width, preserve) {
+            self.line(&line);
+        }
+        self.after_inline = true;
+    }
+
+    // write source of a node that renderer doesn't know about
+    fn verbatim(&mut self, source: &str) {
+        if self.inline_context {
+            self.markup(source);
+        } else {
+            self.block();
+            for line in source.trim_end_matches('\n').split('\n') {
+                self.line(line);
+            }
+        }
+    }
+
+    fn last_char(&self) -> Option<char> {
+        self.inline.iter().rev().find_map(|piece| match piece {
+            Piece::Text(text) | Piece::Markup(text) => text.chars().next_back(),
+            Piece::Softbreak | Piece::Hardbreak => Some(' '),
+        })
+    }
+
+    // `markers` are the preferred marker and the one used in the source
+    fn emphasis(&mut self, node: &Node, markers: [char; 2], len: usize) {
+        if self.source_markers {
+            let marker = markers[1].to_string().repeat(len);
+            self.markup(&marker);
+            self.contents(&node.children);
+            self.markup(&marker);
+            return;
+        }
+
+        let content = self.capture(|fmt| fmt.contents(&node.children));
+        let first = content.iter().find_map(|piece| match piece {
+            Piece::Text(text) | Piece::Markup(text) => text.chars().next(),
+            Piece::Softbreak | Piece::Hardbreak => Some(' '),
+        });
+        let last = content.iter().rev().find_map(|piece| match piece {
+            Piece::Text(text) | Piece::Markup(text) => text.chars().next_back(),
+            Piece::Softbreak | Piece::Hardbreak => Some(' '),
+        });
+        let prev = self.last_char();
+        let next = self.next_char;
+
+        // marker can't touch the same character (it would make a longer delimiter run),
+        // and underscores don't work inside of words; delimiters in the content are
+        // escaped, so they can't be paired with the marker
+        let other = if markers[0] == '*' { '_' } else { '*' };
+        let marker = [markers[0], other].into_iter().find(|&marker| {
+            let adjacent = [prev, next, first, last].contains(&Some(marker));
+            let intraword = marker == '_' && (
+                prev.is_some_and(char::is_alphanumeric) || next.is_some_and(char::is_alphanumeric)
+            );
+            !adjacent && !intraword
+        });
+
+        // if no marker fits, or delimiters wouldn't be flanking between a word and punctuation
+        // (e.g. nested emphasis in `foo******bar`), the whole subtree is written with source
+        // markers, so delimiter runs merge the same way they did in the source
+        let (marker, content) = match marker {
+            Some(marker) if is_flanking(prev, first) && is_flanking(next, last) => (marker, content),
+            _ => {
+                let source_markers = mem::replace(&mut self.source_markers, true);
+                let content = self.capture(|fmt| fmt.contents(&node.children));
+                self.source_markers = source_markers;
+                (markers[1], content)
+            }
+        };
+
+        let marker = marker.to_string().repeat(len);
+        self.markup(&marker);
+        self.inline.extend(content);
+        self.markup(&marker);
+    }
+
+    fn code_inline(&mut self, content: &str, marker_len: usize) {
+        // keep the source marker length unless content has a backtick run of the same length,
+        // a shorter marker could pair with literal backticks around it
+        let len = if has_run(content, '`', marker_len) { longest_run(content, '`') + 1 } else { marker_len };
+        let backticks = "`".repeat(len);
+        let pad = content.starts_with('`') || content.ends_with('`') ||
+            (content.starts_with(' ') && content.ends_with(' ') && content.contains(|c| c != ' '));
+        let pad = if pad { " " } else { "" };
+        self.markup(&format!("{backticks}{pad}{content}{pad}{backticks}"));
+    }
+
+    fn link(&mut self, node: &Node, url: &str, title: Option<&str>) {
+        self.markup("[");
+        let next_char = self.next_char.take();
+        self.contents(&node.children);
+        self.next_char = next_char;
+        self.markup(&link_suffix(url, title));
+    }
+
+    // image description is written as is, since line breaks there are significant
+    // (alt attribute is rendered without them)
+    fn image(&mut self, node: &Node, url: &str, title: Option<&str>) {
+        let content = self.capture(|fmt| fmt.contents(&node.children));
+        let mut markup = "![".to_owned();
+        for piece in content {
+            match piece {
+                Piece::Text(text) | Piece::Markup(text) => markup.push_str(&text),
+                Piece::Softbreak | Piece::Hardbreak => markup.push('\n'),
+            }
+        }
+        markup.push_str(&link_suffix(url, title));
+        self.markup(&markup);
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) {
+        self.block();
+        let content = self.capture(|fmt| fmt.contents(&node.children));
+
+        // line breaks can only be kept in setext headings
+        let multiline = content.iter().any(|piece| match piece {
+            Piece::Hardbreak => true,
+            Piece::Markup(text) => text.contains('\n'),
+            _ => false,
+        });
+        if multiline && level <= 2 {
+            for line in layout(content, 0, true) {
+                self.line(&line);
+            }
+            self.line(if level == 1 { "===" } else { "---" });
+            return;
+        }
+
+        let mut text = join_inline(&content);
+
+        // closing sequence would be removed from the heading
+        let trimmed = text.trim_end_matches('#');
+        if trimmed.len() != text.len() && (trimmed.is_empty() || trimmed.ends_with(' ')) {
+            text.insert(trimmed.len(), '\\');
+        }
+
+        let marker = "#".repeat(level as usize);
+        if text.is_empty() {
+            self.line(&marker);
+        } else {
+            self.line(&format!("{marker} {text}"));
+        }
+    }
+
+    fn thematic_break(&mut self) {
+        let after_inline = self.tight && (self.after_inline || !self.inline.is_empty());
+        self.block();
+
+        // avoid setext underline after inline content, and markers of list items
+        // on the same line (`- ---` is a thematic break)
+        let pending = self.prefixes.iter()
+            .filter(|prefix| !prefix.first_used)
+            .map(|prefix| prefix.first.as_str())
+            .collect::<String>();
+        let marker = ['-', '*', '_'].into_iter()
+            .find(|&ch| (!after_inline || ch != '-') && !pending.contains(ch))
+            .unwrap_or('_');
+        self.line(&marker.to_string().repeat(3));
+    }
+
+    fn code(&mut self, content: &str, info: &str) {
+        self.block();
+        let ch = if info.contains('`') { '~' } else { '`' };
+        let lines = content.strip_suffix('\n').unwrap_or(content);
+        let longest = lines.split('\n').map(|line| {
+            line.trim_start_matches(' ').chars().take_while(|c| *c == ch).count()
+        }).max().unwrap_or_default();
+        let fence = ch.to_string().repeat((longest + 1).max(3));
+
+        self.line(&format!("{fence}{info}"));
+        if !content.is_empty() {
+            for line in lines.split('\n') {
+                self.line(line);
+            }
+        }
+        self.line(&fence);
+    }
+
+    fn list(&mut self, node: &Node, marker_char: char, marker: impl Fn(usize) -> String) {
+        self.block();
+
+        // paragraphs are only kept in loose lists
+        let tight = !node.children.iter().any(
+            |item| item.children.iter().any(|child| child.is::<Paragraph>())
+        );
+        let old_tight = mem::replace(&mut self.tight, tight);
+
+        for (idx, item) in node.children.iter().enumerate() {
+            if idx > 0 { self.block(); }
+            let marker = marker(idx);
+            let rest = " ".repeat(marker.chars().count() + 1);
+            let inline_context = item.children.iter().any(|child| !is_block(child));
+            self.with_prefix(&format!("{marker} "), &rest, |fmt| {
+                fmt.with_inline(inline_context, |fmt| fmt.contents(&item.children));
+            });
+        }
+
+        self.tight = old_tight;
+        self.last_list = Some(marker_char);
+    }
+
+    fn table(&mut self, node: &Node, alignments: &[ColumnAlignment]) {
+        self.block();
+
+        let mut rows = Vec::new();
+        let mut head_rows = 0;
+
+        for section in node.children.iter() {
+            for row in section.children.iter() {
+                let cells = row.children.iter().map(
+                    |cell| escape_pipes(&self.render_inline(&cell.children))
+                ).collect::<Vec<_>>();
+                rows.push(cells);
+            }
+            if section.is::<TableHead>() { head_rows = rows.len(); }
+        }
+
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or_default().max(alignments.len());
+        let mut widths = vec![3; columns];
+        for row in rows.iter() {
+            for (idx, cell) in row.iter().enumerate() {
+                widths[idx] = widths[idx].max(cell.chars().count());
+            }
+        }
+
+        for (row_idx, row) in rows.iter().enumerate() {
+            let mut line = Vec::with_capacity(columns);
+            for (idx, width) in widths.iter().enumerate() {
+                let cell = row.get(idx).map(|s| s.as_str()).unwrap_or_default();
+                let pad = width - cell.chars().count();
+                let (left, right) = match alignments.get(idx).copied().unwrap_or_default() {
+                    ColumnAlignment::Right => (pad, 0),
+                    ColumnAlignment::Center => (pad / 2, pad - pad / 2),
+                    ColumnAlignment::Left | ColumnAlignment::None => (0, pad),
+                };
+                line.push(format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right)));
+            }
+            self.line(&format!("| {} |", line.join(" | ")));
+
+            if row_idx + 1 == head_rows {
+                let line = widths.iter().enumerate().map(|(idx, width)| {
+                    match alignments.get(idx).copied().unwrap_or_default() {
+                        ColumnAlignment::None => "-".repeat(*width),
+                        ColumnAlignment::Left => format!(":{}", "-".repeat(width - 1)),
+                        ColumnAlignment::Right => format!("{}:", "-".repeat(width - 1)),
+                        ColumnAlignment::Center => format!(":{}:", "-".repeat(width - 2)),
+                    }
+                }).collect::<Vec<_>>();
+                self.line(&format!("| {} |", line.join(" | ")));
+            }
+        }
+    }
+}
+
+fn join_inline(pieces: &[Piece]) -> String {
+    let mut result = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) | Piece::Markup(text) => result.push_str(&text.replace('\n', " ")),
+            Piece::Softbreak | Piece::Hardbreak => result.push(' '),
+        }
+    }
+    result
+}
+
+#[derive(Debug, Default)]
+struct Word {
+    text: String,
+    width: usize,
+    // first character comes from literal text, so it can be escaped
+    literal: bool,
+    // what separates this word from the previous one
+    sep: Sep,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Sep {
+    #[default]
+    Start,
+    Space,
+    Softbreak,
+    Hardbreak,
+    // line break inside of markup (e.g. html tag), written as is
+    Raw,
+}
+
+impl Word {
+    fn push(&mut self, text: &str, literal: bool) {
+        if self.text.is_empty() && !text.is_empty() {
+            self.literal = literal;
+        }
+        self.text.push_str(text);
+        self.width += text.chars().count();
+    }
+}
+
+// split inline content into lines: at soft and hard breaks (when line breaks are
+// preserved), and at spaces when line gets longer than `width` (0 if unlimited)
+fn layout(pieces: Vec<Piece>, width: usize, preserve: bool) -> Vec<String> {
+    let mut words = vec![Word::default()];
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => {
+                for (idx, part) in text.split(' ').enumerate() {
+                    if idx > 0 { words.push(Word { sep: Sep::Space, ..Default::default() }); }
+                    words.last_mut().unwrap().push(part, true);
+                }
+            }
+            Piece::Markup(text) => {
+                for (idx, part) in text.split('\n').enumerate() {
+                    if idx > 0 { words.push(Word { sep: Sep::Raw, ..Default::default() }); }
+                    words.last_mut().unwrap().push(part, false);
+                }
+            }
+            Piece::Softbreak => words.push(Word { sep: Sep::Softbreak, ..Default::default() }),
+            Piece::Hardbreak => words.push(Word { sep: Sep::Hardbreak, ..Default::default() }),
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut line_width = 0;
+
+    for word in words {
+        let forced = matches!(word.sep, Sep::Hardbreak | Sep::Raw);
+        let dangerous = word.sep != Sep::Raw && starts_block(&word.text);
+        let mut new_line = forced || (preserve && word.sep == Sep::Softbreak);
+
+        if !new_line && width != 0 && word.sep != Sep::Start && !word.text.is_empty() &&
+                line_width != 0 && line_width + 1 + word.width > width && can_end_line(&line) {
+            new_line = true;
+        }
+
+        // word that would start a block can't be moved to the next line, unless it can be escaped
+        if new_line && !forced && (word.text.is_empty() || dangerous && !word.literal) {
+            new_line = false;
+        }
+
+        if new_line {
+            if word.sep != Sep::Raw {
+                line.truncate(line.trim_end_matches(' ').len());
+            }
+            lines.push(mem::take(&mut line));
+            line_width = 0;
+            let text = if dangerous && word.literal { escape_start(&word.text) } else { word.text };
+            line_width += text.chars().count();
+            line.push_str(&text);
+        } else {
+            // spaces at the start of a line are dropped (they would be stripped or
+            // make an indented code block), and first word is escaped as above
+            let line_start = line.is_empty() && word.sep != Sep::Raw;
+            if word.sep != Sep::Start && !line_start {
+                line.push(' ');
+                line_width += 1;
+            }
+            let text = if line_start && dangerous && word.literal { escape_start(&word.text) } else { word.text };
+            line_width += text.chars().count();
+            line.push_str(&text);
+        }
+    }
+
+    line.truncate(line.trim_end_matches(' ').len());
+    lines.push(line);
+    lines
+}
+
+// check if a paragraph continuation line starting with this word would be parsed as
+// something else (heading, quote, list item, thematic break, setext underline, fence, html);
+// it errs on the side of caution, since escaping literal text is harmless
+fn starts_block(word: &str) -> bool {
+    let Some(first) = word.chars().next() else { return false; };
+    match first {
+        '>' | '|' => true,
+        '<' => html_block::interrupts_paragraph(word),
+        '#' | '-' | '+' | '*' | '_' | '=' => word.chars().all(|c| c == first),
+        '`' | '~' => word.starts_with("```") || word.starts_with("~~~"),
+        '0'..='9' => {
+            let rest = word.trim_start_matches(|c: char| c.is_ascii_digit());
+            word.len() - rest.len() <= 9 && (rest == "." || rest == ")")
+        }
+        _ => false,
+    }
+}
+
+// line that would be parsed as a thematic break or a reference definition
+// if the paragraph was broken after it
+fn can_end_line(line: &str) -> bool {
+    let thematic_break = ['-', '*', '_'].into_iter().any(|ch| {
+        line.chars().all(|c| c == ch || c == ' ') && line.chars().filter(|c| *c == ch).count() >= 3
+    });
+    let reference = line.starts_with('[') && line.contains("]:");
+    !thematic_break && !reference
+}
+
+fn escape_start(word: &str) -> String {
+    match word.chars().next() {
+        // delimiter runs are escaped entirely, otherwise the rest of the run could match
+        Some(ch @ ('*' | '_' | '`' | '~')) => {
+            let rest = word.trim_start_matches(ch);
+            let mut result = format!("\\{ch}").repeat(word.len() - rest.len());
+            result.push_str(rest);
+            result
+        }
+        Some('0'..='9') => {
+            let pos = word.find(['.', ')']).unwrap_or_default();
+            format!("{}\\{}", &word[..pos], &word[pos..])
+        }
+        _ => format!("\\{word}"),
+    }
+}
+
+// first character of inline node output if it's known before rendering it
+fn leading_char(node: &Node) -> Option<char> {
+    if let Some(data) = node.cast::<Text>() {
+        escape_text(&data.content).chars().next()
+    } else if let Some(data) = node.cast::<TextSpecial>() {
+        data.markup.chars().next()
+    } else if node.is::<Softbreak>() || node.is::<Hardbreak>() {
+        Some(' ')
+    } else {
+        None
+    }
+}
+
+// delimiter run between these characters is left-flanking (or right-flanking,
+// if they are given in reverse), `outer` is outside of emphasis
+fn is_flanking(outer: Option<char>, inner: Option<char>) -> bool {
+    let is_punct = |ch: char| !ch.is_alphanumeric() && !ch.is_whitespace();
+    match inner {
+        None => false,
+        Some(inner) if inner.is_whitespace() => false,
+        Some(inner) if is_punct(inner) => outer.is_none_or(|ch| ch.is_whitespace() || is_punct(ch)),
+        Some(_) => true,
+    }
+}
+
+// bullet list has an empty item following another one
+fn has_empty_item(node: &Node) -> bool {
+    node.children.iter().skip(1).any(|item| item.children.is_empty())
+}
+
+fn has_run(text: &str, ch: char, len: usize) -> bool {
+    text.split(|c| c != ch).any(|run| run.chars().count() == len)
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == ch {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    longest
+}
+
+// escape characters that could start inline markup, `_` inside of words
+// and `!` not followed by `[` are left as is since they can't
+fn escape_text(text: &str) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut result = String::with_capacity(text.len());
+    for (idx, &ch) in chars.iter().enumerate() {
+        let prev = idx.checked_sub(1).map(|idx| chars[idx]);
+        let next = chars.get(idx + 1).copied();
+        let escape = match ch {
+            '\\' | '*' | '`' | '[' | ']' | '<' | '>' | '|' | '~' => true,
+            '_' => !prev.is_some_and(char::is_alphanumeric) || !next.is_some_and(char::is_alphanumeric),
+            '!' => next == Some('['),
+            _ => false,
+        };
+        if escape { result.push('\\'); }
+        result.push(ch);
+    }
+    escape_entities(&result)
+}
+
+// escape pipes in table cell, unless they are already escaped
+fn escape_pipes(cell: &str) -> String {
+    let mut result = String::with_capacity(cell.len());
+    let mut backslashes = 0;
+    for ch in cell.chars() {
+        if ch == '|' && backslashes % 2 == 0 { result.push('\\'); }
+        backslashes = if ch == '\\' { backslashes + 1 } else { 0 };
+        result.push(ch);
+    }
+    result
+}
+
+// `&` followed by something that looks like an entity would be decoded
+fn escape_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (pos, ch) in text.char_indices() {
+        if ch == '&' {
+            let rest = &text[pos + 1..];
+            let name = rest.trim_start_matches('#').trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+            if name.starts_with(';') && name.len() + 1 < rest.len() {
+                result.push('\\');
+            }
+        }
+        result.push(ch);
+    }
+    result
+}
+
+// parser percent-encodes urls, undo it for spaces and non-ascii characters
+// (they are encoded the same way when document is parsed again)
+fn decode_url(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut result = String::with_capacity(url.len());
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let mut decoded = Vec::new();
+        let mut end = pos;
+        while bytes.get(end) == Some(&b'%') {
+            let Some(byte) = url.get(end + 1..end + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) else { break; };
+            if byte != b' ' && byte < 0x80 { break; }
+            decoded.push(byte);
+            end += 3;
+        }
+
+        match std::str::from_utf8(&decoded) {
+            Ok(text) if !text.is_empty() => {
+                result.push_str(text);
+                pos = end;
+            }
+            _ => {
+                let len = url[pos..].chars().next().unwrap().len_utf8();
+                result.push_str(&url[pos..pos + len]);
+                pos += len;
+            }
+        }
+    }
+
+    result
+}
+
+// write url as link destination, using `<>` form if needed
+pub(crate) fn link_destination(url: &str) -> String {
+    let mut depth = 0i32;
+    let mut balanced = true;
+    for ch in url.chars() {
+        match ch {
+            '(' => depth += 1,
+            ')' => { depth -= 1; balanced &= depth >= 0; }
+            _ => {}
+        }
+    }
+    balanced &= depth == 0;
+
+    let url = escape_entities(&url.replace('\\', "\\\\"));
+    if balanced && !url.contains(|c: char| c == ' ' || c == '<' || c == '>' || c.is_control()) {
+        url
+    } else {
+        format!("<{}>", url.replace('<', "\\<").replace('>', "\\>"))
+    }
+}
+
+fn link_suffix(url: &str, title: Option<&str>) -> String {
+    let mut markup = format!("]({}", link_destination(&decode_url(url)));
+    if let Some(title) = title {
+        markup.push_str(&format!(" \"{}\"", escape_title(title)));
+    }
+    markup.push(')');
+    markup
+}
+
+fn escape_title(title: &str) -> String {
+    escape_entities(&title.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_html;
+
+    #[test]
+    fn whitespace_is_kept_in_code() {
+        assert_eq!(normalize_html("<p>a \n  b</p>\n"), normalize_html("<p>a b</p> "));
+        assert_ne!(normalize_html("<p>a b</p>"), normalize_html("<p>ab</p>"));
+        assert_ne!(normalize_html("<pre><code>a\n  b\n</code></pre>"), normalize_html("<pre><code>a b\n</code></pre>"));
+        assert_ne!(normalize_html("<p><code>a  b</code></p>"), normalize_html("<p><code>a b</code></p>"));
+        assert_ne!(normalize_html("<pre class=\"x\">a\nb</pre>"), normalize_html("<pre class=\"x\">a b</pre>"));
+        assert_eq!(normalize_html("<p>a<prefix> \n</prefix></p>"), normalize_html("<p>a<prefix> </prefix></p>"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Export to [mdast](https://github.com/syntax-tree/mdast) JSON, used by remark/unified.
+//!
+//! Available with `json` feature. Nodes from [cmark](crate::plugins::cmark) and
+//! [extra](crate::plugins::extra) plugins are converted to their mdast equivalents
+//! (GFM ones for tables and strikethrough). Positions are derived from node source maps,
+//! with offsets and columns counted in UTF-16 code units, same as in JavaScript.
+//!
+//! Unknown nodes get their type from the Rust type name (`MyNode` becomes `myNode`),
+//! use [MdastExporter::set_handler] to convert them properly.
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let source = "## Hi";
+//! let json = markdown_it::renderers::mdast::to_mdast(&md.parse(source), source);
+//! assert_eq!(json, serde_json::json!({
+//!     "type": "root",
+//!     "children": [{
+//!         "type": "heading",
+//!         "depth": 2,
+//!         "children": [{
+//!             "type": "text",
+//!             "value": "Hi",
+//!             "position": {
+//!                 "start": { "line": 1, "column": 4, "offset": 3 },
+//!                 "end": { "line": 1, "column": 6, "offset": 5 },
+//!             },
+//!         }],
+//!         "position": {
+//!             "start": { "line": 1, "column": 1, "offset": 0 },
+//!             "end": { "line": 1, "column": 6, "offset": 5 },
+//!         },
+//!     }],
+//!     "position": {
+//!         "start": { "line": 1, "column": 1, "offset": 0 },
+//!         "end": { "line": 1, "column": 6, "offset": 5 },
+//!     },
+//! }));
+//! ```
+use std::collections::HashMap;
+use serde_json::{json, Map, Value};
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::common::utils::unescape_all;
+use crate::parser::core::Root;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, ListItem, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableCell, TableRow};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use super::{is_block, linkified_url, text_content};
+
+/// Custom handler for a node type, see [MdastExporter::set_handler].
+pub type MdastFn = fn (node: &Node, fmt: &mut MdastExporter) -> Value;
+
+/// Convert node to mdast, `source` is the markdown it was parsed from (used for positions).
+pub fn to_mdast(node: &Node, source: &str) -> Value {
+    MdastExporter::new(source).export(node)
+}
+
+#[derive(Debug)]
+/// Converts nodes to mdast, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::block::hr::ThematicBreak;
+/// use markdown_it::renderers::mdast::MdastExporter;
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let source = "***";
+/// let ast = md.parse(source);
+///
+/// let mut fmt = MdastExporter::new(source);
+/// fmt.set_handler::<ThematicBreak>(|_, _| serde_json::json!({ "type": "pageBreak" }));
+/// assert_eq!(fmt.export(&ast)["children"][0]["type"], "pageBreak");
+/// ```
+pub struct MdastExporter {
+    handlers: HashMap<TypeKey, MdastFn>,
+    line_starts: Vec<usize>,
+    // utf-16 offset for each byte offset in the source
+    utf16: Vec<usize>,
+}
+
+impl MdastExporter {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut utf16 = Vec::with_capacity(source.len() + 1);
+        let mut offset = 0;
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((pos, ch)) = chars.next() {
+            utf16.resize(pos + ch.len_utf8(), offset);
+            offset += ch.len_utf16();
+            let crlf = ch == '\r' && matches!(chars.peek(), Some((_, '\n')));
+            if (ch == '\n' || ch == '\r') && !crlf {
+                line_starts.push(pos + 1);
+            }
+        }
+        utf16.push(offset);
+
+        Self { handlers: HashMap::new(), line_starts, utf16 }
+    }
+
+    /// Convert nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace conversion of built-in ones). Position is added
+    /// to the returned object automatically.
+    pub fn set_handler<T: NodeValue>(&mut self, f: MdastFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Convert a single node.
+    pub fn export(&mut self, node: &Node) -> Value {
+        let mut value = match self.handlers.get(&node.node_type).copied() {
+            Some(f) => f(node, self),
+            None => self.convert(node),
+        };
+
+        if let (Value::Object(object), Some(position)) = (&mut value, self.position(node)) {
+            object.entry("position").or_insert(position);
+        }
+        value
+    }
+
+    /// Convert a list of nodes, merging adjacent text nodes.
+    pub fn children(&mut self, nodes: &[Node]) -> Vec<Value> {
+        let mut result : Vec<Value> = Vec::with_capacity(nodes.len());
+
+        for node in nodes.iter() {
+            let value = self.export(node);
+            if let Some(prev) = result.last_mut() {
+                if is_text(prev) && is_text(&value) {
+                    let text = value["value"].as_str().unwrap_or_default();
+                    let merged = format!("{}{}", prev["value"].as_str().unwrap_or_default(), text);
+                    prev["value"] = Value::String(merged);
+                    if let (Some(end), Some(Value::Object(position))) = (value.get("position"), prev.get_mut("position")) {
+                        position.insert("end".to_owned(), end["end"].clone());
+                    }
+                    continue;
+                }
+            }
+            result.push(value);
+        }
+
+        result
+    }
+
+    /// Position of a node in the source in mdast format, if it is known.
+    pub fn position(&self, node: &Node) -> Option<Value> {
+        let (start, end) = node.srcmap?.get_byte_offsets();
+        Some(json!({ "start": self.point(start), "end": self.point(end) }))
+    }
+
+    fn point(&self, byte_offset: usize) -> Value {
+        let byte_offset = byte_offset.min(self.utf16.len() - 1);
+        let line = self.line_starts.partition_point(|start| *start <= byte_offset);
+        let line_start = self.line_starts[line - 1];
+        json!({
+            "line": line,
+            "column": self.utf16[byte_offset] - self.utf16[line_start] + 1,
+            "offset": self.utf16[byte_offset],
+        })
+    }
+
+    fn parent(&mut self, kind: &str, node: &Node) -> Value {
+        json!({ "type": kind, "children": self.children(&node.children) })
+    }
+
+    fn convert(&mut self, node: &Node) -> Value {
+        if let Some(data) = node.cast::<Text>() {
+            json!({ "type": "text", "value": data.content })
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            json!({ "type": "text", "value": data.content })
+        } else if node.is::<Softbreak>() {
+            json!({ "type": "text", "value": "\n" })
+        } else if node.is::<Hardbreak>() {
+            json!({ "type": "break" })
+        } else if node.is::<Root>() {
+            self.parent("root", node)
+        } else if node.is::<Paragraph>() {
+            self.parent("paragraph", node)
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            json!({ "type": "heading", "depth": data.level, "children": self.children(&node.children) })
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            json!({ "type": "heading", "depth": data.level, "children": self.children(&node.children) })
+        } else if node.is::<ThematicBreak>() {
+            json!({ "type": "thematicBreak" })
+        } else if node.is::<Blockquote>() {
+            self.parent("blockquote", node)
+        } else if node.is::<BulletList>() {
+            self.list(node, None)
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            self.list(node, Some(data.start))
+        } else if node.is::<ListItem>() {
+            // only reached when exporting a list item by itself
+            self.list_item(node, false)
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            json!({ "type": "code", "lang": null, "meta": null, "value": strip_newline(&data.content) })
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            // same as remark, info is split at first whitespace, then both parts are unescaped
+            let info = data.info.trim();
+            let (lang, meta) = match info.split_once(char::is_whitespace) {
+                Some((lang, meta)) => (Some(unescape_all(lang)), Some(unescape_all(meta.trim_start()))),
+                None if info.is_empty() => (None, None),
+                None => (Some(unescape_all(info)), None),
+            };
+            json!({ "type": "code", "lang": lang, "meta": meta, "value": strip_newline(&data.content) })
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            json!({ "type": "html", "value": strip_newline(&data.content) })
+        } else if let Some(data) = node.cast::<HtmlInline>() {
+            json!({ "type": "html", "value": data.content })
+        } else if node.is::<Em>() {
+            self.parent("emphasis", node)
+        } else if node.is::<Strong>() {
+            self.parent("strong", node)
+        } else if node.is::<Strikethrough>() {
+            self.parent("delete", node)
+        } else if node.is::<CodeInline>() {
+            json!({ "type": "inlineCode", "value": text_content(node) })
+        } else if let Some(data) = node.cast::<Link>() {
+            json!({ "type": "link", "url": data.url, "title": data.title, "children": self.children(&node.children) })
+        } else if let Some(data) = node.cast::<Autolink>() {
+            json!({ "type": "link", "url": data.url, "title": null, "children": self.children(&node.children) })
+        } else if let Some(url) = linkified_url(node) {
+            json!({ "type": "link", "url": url, "title": null, "children": self.children(&node.children) })
+        } else if let Some(data) = node.cast::<Image>() {
+            json!({ "type": "image", "url": data.url, "title": data.title, "alt": text_content(node) })
+        } else if let Some(data) = node.cast::<Table>() {
+            let align = data.alignments.iter().map(|align| match align {
+                ColumnAlignment::None => Value::Null,
+                ColumnAlignment::Left => "left".into(),
+                ColumnAlignment::Right => "right".into(),
+                ColumnAlignment::Center => "center".into(),
+            }).collect::<Vec<_>>();
+            // mdast has no head and body sections, rows are direct children
+            let rows = node.children.iter()
+                .flat_map(|section| section.children.iter())
+                .map(|row| self.export(row))
+                .collect::<Vec<_>>();
+            json!({ "type": "table", "align": align, "children": rows })
+        } else if node.is::<TableRow>() {
+            self.parent("tableRow", node)
+        } else if node.is::<TableCell>() {
+            self.parent("tableCell", node)
+        } else {
+            let name = node.name().rsplit("::").next().unwrap_or_default();
+            let mut chars = name.chars();
+            let kind = match chars.next() {
+                Some(first) => first.to_lowercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            };
+            let mut object = Map::new();
+            object.insert("type".to_owned(), kind.into());
+            if !node.children.is_empty() {
+                object.insert("children".to_owned(), self.children(&node.children).into());
+            }
+            Value::Object(object)
+        }
+    }
+
+    fn list(&mut self, node: &Node, start: Option<u32>) -> Value {
+        // markdown-it only keeps paragraphs in loose lists
+        let spread = node.children.iter().any(
+            |item| item.children.iter().any(|child| child.is::<Paragraph>())
+        );
+        let items = node.children.iter().map(|item| {
+            let mut value = match self.handlers.get(&item.node_type).copied() {
+                Some(f) => f(item, self),
+                None if item.is::<ListItem>() => self.list_item(item, spread),
+                None => self.convert(item),
+            };
+            if let (Value::Object(object), Some(position)) = (&mut value, self.position(item)) {
+                object.entry("position").or_insert(position);
+            }
+            value
+        }).collect::<Vec<_>>();
+
+        json!({
+            "type": "list",
+            "ordered": start.is_some(),
+            "start": start,
+            "spread": spread,
+            "children": items,
+        })
+    }
+
+    fn list_item(&mut self, node: &Node, spread: bool) -> Value {
+        // items in tight lists contain inline nodes directly,
+        // mdast always wraps them in paragraphs
+        let mut children = Vec::new();
+        let mut inline_start = None;
+
+        for (idx, child) in node.children.iter().enumerate() {
+            if is_block(child) {
+                if let Some(start) = inline_start.take() {
+                    children.push(self.paragraph(&node.children[start..idx]));
+                }
+                children.push(self.export(child));
+            } else if inline_start.is_none() {
+                inline_start = Some(idx);
+            }
+        }
+        if let Some(start) = inline_start {
+            children.push(self.paragraph(&node.children[start..]));
+        }
+
+        json!({ "type": "listItem", "spread": spread, "checked": null, "children": children })
+    }
+
+    fn paragraph(&mut self, nodes: &[Node]) -> Value {
+        let mut value = json!({ "type": "paragraph", "children": self.children(nodes) });
+        let start = nodes.first().and_then(|node| node.srcmap);
+        let end = nodes.last().and_then(|node| node.srcmap);
+        if let (Some(start), Some(end)) = (start, end) {
+            value["position"] = json!({
+                "start": self.point(start.get_byte_offsets().0),
+                "end": self.point(end.get_byte_offsets().1),
+            });
+        }
+        value
+    }
+}
+
+fn is_text(value: &Value) -> bool {
+    value["type"] == "text"
+}
+
+fn strip_newline(content: &str) -> &str {
+    content.strip_suffix('\n').unwrap_or(content)
+}
@@ -0,0 +1,68 @@
+//! Renderers for output formats other than HTML.
+//!
+//! HTML output is produced by nodes themselves (see [NodeValue::render](crate::NodeValue::render)),
+//! but it doesn't work well for formats that have very different structure. So renderers
+//! in this module walk the AST and output known node types themselves. Unknown nodes
+//! have their children rendered, and you can add custom handlers for your own node types.
+pub mod ansi;
+pub mod events;
+pub mod gemtext;
+pub mod latex;
+pub mod markdown;
+#[cfg(feature = "json")]
+pub mod mdast;
+pub mod plain;
+pub mod roff;
+pub mod tokens;
+
+use std::borrow::Cow;
+use crate::Node;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::extra::tables::Table;
+use crate::plugins::html::html_block::HtmlBlock;
+
+// url of a node produced by linkify plugin (if that feature is enabled)
+#[cfg(feature = "linkify")]
+pub(crate) fn linkified_url(node: &Node) -> Option<&str> {
+    node.cast::<crate::plugins::extra::linkify::Linkified>().map(|data| data.url.as_str())
+}
+
+#[cfg(not(feature = "linkify"))]
+pub(crate) fn linkified_url(_: &Node) -> Option<&str> {
+    None
+}
+
+// concatenate text of all descendants (without allocating for a single text node)
+pub(crate) fn text_content(node: &Node) -> Cow<'_, str> {
+    if let [child] = &node.children[..] {
+        if let Some(data) = child.cast::<Text>() {
+            return Cow::Borrowed(&data.content);
+        }
+    }
+
+    let mut result = String::new();
+    node.walk(|node, _| {
+        if let Some(data) = node.cast::<Text>() {
+            result.push_str(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            result.push_str(&data.content);
+        }
+    });
+    Cow::Owned(result)
+}
+
+// built-in block nodes (used to find inline content of tight list items)
+pub(crate) fn is_block(node: &Node) -> bool {
+    node.is::<Paragraph>() || node.is::<ATXHeading>() || node.is::<SetextHeader>() ||
+    node.is::<ThematicBreak>() || node.is::<CodeBlock>() || node.is::<CodeFence>() ||
+    node.is::<HtmlBlock>() || node.is::<Blockquote>() || node.is::<BulletList>() ||
+    node.is::<OrderedList>() || node.is::<Table>()
+}
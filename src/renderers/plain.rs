@@ -0,0 +1,361 @@
+//! Plain text renderer.
+//!
+//! Markup is removed, but document structure is kept readable: paragraphs
+//! are separated with empty lines, lists get bullets or numbers, quotes are
+//! prefixed with `>`, code blocks are indented and tables are aligned in columns.
+//! Useful for text parts of emails, previews and search indexing.
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("# Hello\n\nSome *text* with [a link](https://example.org)\n\n- one\n- two &amp; three");
+//! assert_eq!(
+//!     markdown_it::renderers::plain::render(&ast),
+//!     "Hello\n=====\n\nSome text with a link\n\n- one\n- two & three\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::mem;
+use unicode_width::UnicodeWidthStr;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+
+/// Custom handler for a node type, see [PlainTextRenderer::set_handler].
+pub type PlainTextFn = fn (node: &Node, fmt: &mut PlainTextRenderer);
+
+/// Options for [PlainTextRenderer].
+#[derive(Debug, Clone)]
+pub struct PlainTextOptions {
+    /// Add link urls after link text, like this: `text [https://example.org]`.
+    pub link_urls: bool,
+    /// Marker for bullet list items.
+    pub bullet: &'static str,
+}
+
+impl Default for PlainTextOptions {
+    fn default() -> Self {
+        Self {
+            link_urls: false,
+            bullet: "-",
+        }
+    }
+}
+
+/// Render node to plain text with default options.
+pub fn render(node: &Node) -> String {
+    PlainTextRenderer::new(PlainTextOptions::default()).render(node)
+}
+
+#[derive(Debug)]
+struct Prefix {
+    first: String,
+    rest: String,
+    first_used: bool,
+}
+
+#[derive(Debug)]
+/// Plain text renderer, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::Node;
+/// use markdown_it::plugins::cmark::inline::emphasis::Strong;
+/// use markdown_it::renderers::plain::{PlainTextOptions, PlainTextRenderer};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("**Note:** see [docs](https://example.org/docs)");
+///
+/// let mut fmt = PlainTextRenderer::new(PlainTextOptions { link_urls: true, ..Default::default() });
+/// fmt.set_handler::<Strong>(|node, fmt| {
+///     let text = fmt.render_to_string(&node.children);
+///     fmt.text(&text.to_uppercase());
+/// });
+/// assert_eq!(fmt.render(&ast), "NOTE: see docs [https://example.org/docs]\n");
+/// ```
+pub struct PlainTextRenderer {
+    options: PlainTextOptions,
+    handlers: HashMap<TypeKey, PlainTextFn>,
+    result: String,
+    prefixes: Vec<Prefix>,
+    // nothing was written on the current line yet
+    line_start: bool,
+    // nothing was written since the start of the current container
+    fresh: bool,
+    // blocks are separated by line breaks instead of empty lines
+    tight: bool,
+}
+
+impl PlainTextRenderer {
+    pub fn new(options: PlainTextOptions) -> Self {
+        Self {
+            options,
+            handlers: HashMap::new(),
+            result: String::new(),
+            prefixes: Vec::new(),
+            line_start: true,
+            fresh: true,
+            tight: false,
+        }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &PlainTextOptions {
+        &self.options
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: PlainTextFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting text.
+    pub fn render(mut self, node: &Node) -> String {
+        self.node(node);
+
+        let mut result = String::with_capacity(self.result.len());
+        for line in self.result.lines() {
+            result.push_str(line.trim_end());
+            result.push('\n');
+        }
+
+        let len = result.trim_end_matches('\n').len();
+        if len == 0 { return String::new(); }
+        result.truncate(len + 1);
+        result
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() || node.is::<Hardbreak>() {
+            self.text("\n");
+        } else if node.is::<HtmlInline>() || node.is::<HtmlBlock>() {
+            // raw html is dropped
+        } else if let Some(data) = node.cast::<Link>() {
+            self.contents(&node.children);
+            if self.options.link_urls && !data.url.is_empty() {
+                self.text(&format!(" [{}]", data.url));
+            }
+        } else if node.is::<Paragraph>() {
+            self.block();
+            self.contents(&node.children);
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level);
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level);
+        } else if node.is::<ThematicBreak>() {
+            self.block();
+            self.text("* * *");
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.code(&data.content);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.code(&data.content);
+        } else if node.is::<Blockquote>() {
+            self.block();
+            let tight = mem::replace(&mut self.tight, false);
+            self.with_prefix("> ", "> ", |fmt| fmt.contents(&node.children));
+            self.tight = tight;
+        } else if node.is::<BulletList>() {
+            let bullet = self.options.bullet;
+            self.list(node, |_| bullet.to_owned());
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            let start = data.start as usize;
+            self.list(node, |idx| format!("{}.", start + idx));
+        } else if let Some(data) = node.cast::<Table>() {
+            self.table(node, &data.alignments);
+        } else {
+            self.contents(&node.children);
+        }
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes.iter() {
+            self.node(node);
+        }
+    }
+
+    /// Write text, each line gets prefixed according to the current nesting (quotes, lists).
+    pub fn text(&mut self, text: &str) {
+        for (idx, line) in text.split('\n').enumerate() {
+            if idx > 0 { self.newline(); }
+            if !line.is_empty() {
+                self.write_prefix();
+                self.result.push_str(line);
+                self.fresh = false;
+            }
+        }
+    }
+
+    /// Start a new block, it is separated from previous content with an empty line
+    /// (or with a line break inside of tight lists).
+    pub fn block(&mut self) {
+        if self.fresh { return; }
+        if !self.line_start { self.newline(); }
+        if !self.tight { self.newline(); }
+    }
+
+    /// Render nodes into a separate string, ignoring current nesting,
+    /// useful to post-process inline content.
+    pub fn render_to_string(&mut self, nodes: &[Node]) -> String {
+        let result = mem::take(&mut self.result);
+        let prefixes = mem::take(&mut self.prefixes);
+        let line_start = mem::replace(&mut self.line_start, true);
+        let fresh = mem::replace(&mut self.fresh, true);
+
+        self.contents(nodes);
+
+        self.line_start = line_start;
+        self.fresh = fresh;
+        self.prefixes = prefixes;
+        mem::replace(&mut self.result, result)
+    }
+
+    /// Execute `f` with all lines prefixed with a given string (`first` for the first line,
+    /// `rest` for the following ones), this is how quotes and list items are rendered.
+    pub fn with_prefix(&mut self, first: &str, rest: &str, f: impl FnOnce(&mut Self)) {
+        self.prefixes.push(Prefix { first: first.to_owned(), rest: rest.to_owned(), first_used: false });
+        self.fresh = true;
+
+        f(self);
+
+        // make sure that empty list items still have a bullet
+        if !self.prefixes.last().unwrap().first_used {
+            self.write_prefix();
+            self.fresh = false;
+        }
+        self.prefixes.pop();
+    }
+
+    fn write_prefix(&mut self) {
+        if !self.line_start { return; }
+        self.line_start = false;
+
+        for prefix in self.prefixes.iter_mut() {
+            if prefix.first_used {
+                self.result.push_str(&prefix.rest);
+            } else {
+                self.result.push_str(&prefix.first);
+                prefix.first_used = true;
+            }
+        }
+    }
+
+    fn newline(&mut self) {
+        // empty lines get prefixes too (trailing whitespace is trimmed later)
+        self.write_prefix();
+        self.result.push('\n');
+        self.line_start = true;
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) {
+        self.block();
+        let text = self.render_to_string(&node.children);
+        self.text(&text);
+
+        if level <= 2 {
+            let width = text.lines().map(|line| line.width()).max().unwrap_or_default();
+            let underline = if level == 1 { "=" } else { "-" };
+            self.text("\n");
+            self.text(&underline.repeat(width.max(3)));
+        }
+    }
+
+    fn code(&mut self, content: &str) {
+        self.block();
+        let content = content.strip_suffix('\n').unwrap_or(content);
+        self.with_prefix("    ", "    ", |fmt| fmt.text(content));
+    }
+
+    fn list(&mut self, node: &Node, marker: impl Fn(usize) -> String) {
+        self.block();
+
+        // paragraphs are only kept in loose lists
+        let tight = !node.children.iter().any(
+            |item| item.children.iter().any(|child| child.is::<Paragraph>())
+        );
+        let old_tight = mem::replace(&mut self.tight, tight);
+
+        for (idx, item) in node.children.iter().enumerate() {
+            if idx > 0 { self.block(); }
+            let marker = marker(idx);
+            let rest = " ".repeat(marker.width() + 1);
+            self.with_prefix(&format!("{marker} "), &rest, |fmt| fmt.contents(&item.children));
+        }
+
+        self.tight = old_tight;
+    }
+
+    fn table(&mut self, node: &Node, alignments: &[ColumnAlignment]) {
+        self.block();
+
+        let mut rows = Vec::new();
+        let mut head_rows = 0;
+
+        for section in node.children.iter() {
+            for row in section.children.iter() {
+                let cells = row.children.iter().map(|cell| {
+                    self.render_to_string(&cell.children).replace('\n', " ")
+                }).collect::<Vec<_>>();
+                rows.push(cells);
+            }
+            if section.is::<TableHead>() { head_rows = rows.len(); }
+        }
+
+        self.text(&table_lines(&rows, head_rows, alignments).join("\n"));
+    }
+}
+
+// lay out table cells in aligned columns, with a separator line after the header
+pub(crate) fn table_lines(rows: &[Vec<String>], head_rows: usize, alignments: &[ColumnAlignment]) -> Vec<String> {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or_default();
+    let mut widths = vec![0; columns];
+    for row in rows.iter() {
+        for (idx, cell) in row.iter().enumerate() {
+            widths[idx] = widths[idx].max(cell.width());
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (row_idx, row) in rows.iter().enumerate() {
+        let mut line = Vec::with_capacity(columns);
+        for (idx, width) in widths.iter().enumerate() {
+            let cell = row.get(idx).map(|s| s.as_str()).unwrap_or_default();
+            let pad = width - cell.width();
+            let (left, right) = match alignments.get(idx).copied().unwrap_or_default() {
+                ColumnAlignment::Right => (pad, 0),
+                ColumnAlignment::Center => (pad / 2, pad - pad / 2),
+                ColumnAlignment::Left | ColumnAlignment::None => (0, pad),
+            };
+            line.push(format!("{}{}{}", " ".repeat(left), cell, " ".repeat(right)));
+        }
+        lines.push(line.join(" | "));
+
+        if row_idx + 1 == head_rows {
+            lines.push(widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join("-+-"));
+        }
+    }
+
+    lines
+}
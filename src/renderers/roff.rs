@@ -0,0 +1,429 @@
+//! Man page renderer, produces roff using `man(7)` macros.
+//!
+//! Headings become `.SH` and `.SS`, paragraphs become `.PP`, list items
+//! become `.IP` (or `.TP`, see below), code blocks are wrapped in `.nf`/`.fi`,
+//! tables are written for `tbl` preprocessor. Emphasis is rendered as italic,
+//! strong emphasis and code spans as bold.
+//!
+//! List items which start with a line containing only a code span or strong emphasis
+//! are rendered as tagged paragraphs, which is the usual way of documenting options:
+//!
+//! ```rust
+//! use markdown_it::renderers::roff::{RoffOptions, RoffRenderer};
+//!
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let ast = md.parse("# OPTIONS\n\n- `-v`, `--verbose`\n  Print more output.");
+//! let fmt = RoffRenderer::new(RoffOptions { title: Some("foo".into()), ..Default::default() });
+//! assert_eq!(
+//!     fmt.render(&ast),
+//!     ".TH \"FOO\" \"1\"\n.SH \"OPTIONS\"\n.TP\n\\fB\\-v\\fP, \\fB\\-\\-verbose\\fP\nPrint more output.\n"
+//! );
+//! ```
+use std::collections::HashMap;
+use std::mem;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+
+/// Custom handler for a node type, see [RoffRenderer::set_handler].
+pub type RoffFn = fn (node: &Node, fmt: &mut RoffRenderer);
+
+/// Options for [RoffRenderer].
+#[derive(Debug, Clone)]
+pub struct RoffOptions {
+    /// Page title, `.TH` line is only added if it's set.
+    pub title: Option<String>,
+    /// Manual section number.
+    pub section: String,
+    /// Date for the page footer.
+    pub date: Option<String>,
+}
+
+impl Default for RoffOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            section: "1".to_owned(),
+            date: None,
+        }
+    }
+}
+
+/// Render node to roff with default options.
+pub fn render(node: &Node) -> String {
+    RoffRenderer::new(RoffOptions::default()).render(node)
+}
+
+/// Escape backslashes and dashes in text (dashes are escaped
+/// so command line options in the output can be copied).
+pub fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => result.push_str("\\e"),
+            '-' => result.push_str("\\-"),
+            ch => result.push(ch),
+        }
+    }
+    result
+}
+
+// macro arguments are split by spaces, so quote them
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", escape(arg).replace('"', "\\(dq"))
+}
+
+#[derive(Debug)]
+/// Roff renderer, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::block::hr::ThematicBreak;
+/// use markdown_it::renderers::roff::{RoffOptions, RoffRenderer};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let ast = md.parse("a\n\n---\n\nb");
+///
+/// let mut fmt = RoffRenderer::new(RoffOptions::default());
+/// fmt.set_handler::<ThematicBreak>(|_, fmt| fmt.request(".bp"));
+/// assert_eq!(fmt.render(&ast), ".PP\na\n.bp\n.PP\nb\n");
+/// ```
+pub struct RoffRenderer {
+    options: RoffOptions,
+    handlers: HashMap<TypeKey, RoffFn>,
+    result: String,
+    // active fonts: (bold, italic)
+    fonts: Vec<(bool, bool)>,
+    // paragraphs are rendered as indented ones (inside of list items)
+    indented: bool,
+    // nothing was written since the start of the current list item
+    fresh: bool,
+}
+
+impl RoffRenderer {
+    pub fn new(options: RoffOptions) -> Self {
+        Self {
+            options,
+            handlers: HashMap::new(),
+            result: String::new(),
+            fonts: Vec::new(),
+            indented: false,
+            fresh: false,
+        }
+    }
+
+    /// Options this renderer was created with.
+    pub fn options(&self) -> &RoffOptions {
+        &self.options
+    }
+
+    /// Render nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace rendering of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: RoffFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Render node, return resulting roff document.
+    pub fn render(mut self, node: &Node) -> String {
+        if let Some(title) = &self.options.title {
+            let mut th = format!(".TH {} {}", quote(&title.to_uppercase()), quote(&self.options.section));
+            if let Some(date) = &self.options.date {
+                th.push(' ');
+                th.push_str(&quote(date));
+            }
+            self.request(&th);
+        }
+
+        self.node(node);
+        self.end_line();
+        self.result
+    }
+
+    /// Render a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() {
+            self.text("\n");
+        } else if node.is::<Hardbreak>() {
+            self.request(".br");
+        } else if node.is::<Em>() {
+            self.font(false, true, |fmt| fmt.contents(&node.children));
+        } else if node.is::<Strong>() || node.is::<CodeInline>() {
+            self.font(true, false, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<Link>() {
+            self.contents(&node.children);
+            if !data.url.is_empty() {
+                self.text(" <");
+                self.font(false, true, |fmt| fmt.text(&data.url));
+                self.text(">");
+            }
+        } else if node.is::<HtmlInline>() || node.is::<HtmlBlock>() {
+            // raw html is dropped
+        } else if node.is::<Paragraph>() {
+            self.paragraph();
+            self.contents(&node.children);
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            self.heading(node, data.level);
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            self.heading(node, data.level);
+        } else if node.is::<ThematicBreak>() {
+            self.paragraph();
+            self.request(".ce");
+            self.text("* * *");
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            self.code(&data.content);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            self.code(&data.content);
+        } else if node.is::<Blockquote>() {
+            self.request(".RS 4");
+            let indented = mem::replace(&mut self.indented, false);
+            self.contents(&node.children);
+            self.indented = indented;
+            self.request(".RE");
+        } else if node.is::<BulletList>() {
+            self.list(node, |_| "\\(bu".to_owned(), 2);
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            let start = data.start as usize;
+            let last = start + node.children.len().saturating_sub(1);
+            self.list(node, |idx| format!("{}.", start + idx), last.to_string().len() as u32 + 2);
+        } else if let Some(data) = node.cast::<Table>() {
+            self.table(node, &data.alignments);
+        } else {
+            self.contents(&node.children);
+        }
+    }
+
+    /// Render a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes.iter() {
+            self.node(node);
+        }
+    }
+
+    /// Write text, escaping special characters.
+    pub fn text(&mut self, text: &str) {
+        for (idx, line) in text.split('\n').enumerate() {
+            if idx > 0 { self.end_line(); }
+            let mut line = line;
+            if self.at_line_start() {
+                // leading spaces cause a line break in roff
+                line = line.trim_start();
+                // and leading dots and quotes are treated as control lines
+                if line.starts_with(['.', '\'']) {
+                    self.result.push_str("\\&");
+                }
+            }
+            self.result.push_str(&escape(line));
+        }
+    }
+
+    /// Write a request or a macro call on its own line (no escaping is done).
+    pub fn request(&mut self, line: &str) {
+        self.end_line();
+        self.result.push_str(line);
+        self.result.push('\n');
+        self.fresh = false;
+    }
+
+    /// Start a new paragraph, `.PP` or `.IP` depending on nesting.
+    pub fn paragraph(&mut self) {
+        if self.fresh {
+            self.fresh = false;
+        } else if self.indented {
+            self.request(".IP");
+        } else {
+            self.request(".PP");
+        }
+    }
+
+    /// Render inline nodes into a separate string.
+    pub fn render_inline(&mut self, nodes: &[Node]) -> String {
+        self.render_inline_in_font(nodes, Vec::new())
+    }
+
+    // render inline nodes into a separate string, `fonts` are active at the start
+    fn render_inline_in_font(&mut self, nodes: &[Node], fonts: Vec<(bool, bool)>) -> String {
+        let result = mem::take(&mut self.result);
+        let fonts = mem::replace(&mut self.fonts, fonts);
+        self.contents(nodes);
+        self.fonts = fonts;
+        mem::replace(&mut self.result, result)
+    }
+
+    /// Execute `f` with bold and/or italic font (in addition to the current one).
+    pub fn font(&mut self, bold: bool, italic: bool, f: impl FnOnce(&mut Self)) {
+        let (old_bold, old_italic) = self.fonts.last().copied().unwrap_or_default();
+        let font = (old_bold || bold, old_italic || italic);
+
+        self.fonts.push(font);
+        self.result.push_str(font_escape(font));
+        let start = self.result.len();
+        f(self);
+        self.fonts.pop();
+
+        // `\fP` restores the font used before (e.g. bold one in `.SH` lines), but it only
+        // remembers one font, so it can't be used if font was changed inside
+        if self.result[start..].contains("\\f") {
+            self.result.push_str(font_escape((old_bold, old_italic)));
+        } else {
+            self.result.push_str("\\fP");
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.result.is_empty() || self.result.ends_with('\n')
+    }
+
+    fn end_line(&mut self) {
+        if !self.at_line_start() {
+            self.result.push('\n');
+        }
+    }
+
+    fn heading(&mut self, node: &Node, level: u8) {
+        // headings are bold, so nested fonts are restored to bold
+        let text = self.render_inline_in_font(&node.children, vec![(true, false)]).replace('\n', " ");
+        // text is already escaped, so it's quoted here instead of using quote()
+        let arg = format!("\"{}\"", text.replace('"', "\\(dq"));
+        match level {
+            1 => self.request(&format!(".SH {arg}")),
+            2 => self.request(&format!(".SS {arg}")),
+            _ => {
+                self.request(".PP");
+                self.result.push_str(&format!("\\fB{text}\\fP\n"));
+            }
+        }
+    }
+
+    fn code(&mut self, content: &str) {
+        self.paragraph();
+        self.request(".RS 4");
+        self.request(".nf");
+        // leading whitespace is kept in no-fill mode, so text() isn't used here
+        for line in content.strip_suffix('\n').unwrap_or(content).split('\n') {
+            if line.starts_with(['.', '\'']) {
+                self.result.push_str("\\&");
+            }
+            self.result.push_str(&escape(&line.replace('\t', "    ")));
+            self.result.push('\n');
+        }
+        self.request(".fi");
+        self.request(".RE");
+    }
+
+    fn list(&mut self, node: &Node, marker: impl Fn(usize) -> String, indent: u32) {
+        let nested = self.indented;
+        if nested { self.request(".RS"); }
+        let old_indented = mem::replace(&mut self.indented, true);
+
+        for (idx, item) in node.children.iter().enumerate() {
+            match split_tag(&item.children) {
+                Some((tag, inline, blocks)) => {
+                    self.request(".TP");
+                    self.contents(tag);
+                    self.end_line();
+                    self.contents(inline);
+                    self.contents(blocks);
+                }
+                None => {
+                    self.request(&format!(".IP {} {}", marker(idx), indent));
+                    self.fresh = true;
+                    self.contents(&item.children);
+                }
+            }
+            self.fresh = false;
+        }
+
+        self.indented = old_indented;
+        if nested { self.request(".RE"); }
+    }
+
+    fn table(&mut self, node: &Node, alignments: &[ColumnAlignment]) {
+        let mut head = Vec::new();
+        let mut body = Vec::new();
+
+        for section in node.children.iter() {
+            for row in section.children.iter() {
+                let cells = row.children.iter().map(|cell| {
+                    self.render_inline(&cell.children).replace(['\n', '\t'], " ")
+                }).collect::<Vec<_>>().join("\t");
+
+                if section.is::<TableHead>() { head.push(cells) } else { body.push(cells) }
+            }
+        }
+
+        let format = |bold: bool| alignments.iter().map(|align| {
+            let column = match align {
+                ColumnAlignment::Center => "c",
+                ColumnAlignment::Right => "r",
+                ColumnAlignment::Left | ColumnAlignment::None => "l",
+            };
+            if bold { format!("{column}b") } else { column.to_owned() }
+        }).collect::<Vec<_>>().join(" ");
+
+        self.paragraph();
+        self.request(".TS");
+        self.request("box;");
+        let mut formats = vec![format(true); head.len()];
+        if !body.is_empty() { formats.push(format(false)); }
+        let formats = formats.join("\n");
+        self.request(&format!("{formats}."));
+        for row in head.into_iter().chain(body) {
+            self.request(&row);
+        }
+        self.request(".TE");
+    }
+}
+
+fn font_escape((bold, italic): (bool, bool)) -> &'static str {
+    match (bold, italic) {
+        (false, false) => "\\fR",
+        (true, false) => "\\fB",
+        (false, true) => "\\fI",
+        (true, true) => "\\f(BI",
+    }
+}
+
+// split list item into a tag (first line, consisting of code and strong spans),
+// the rest of the first paragraph, and the rest of the blocks
+fn split_tag(children: &[Node]) -> Option<(&[Node], &[Node], &[Node])> {
+    let (inline, blocks) = match children.first() {
+        Some(node) if node.is::<Paragraph>() => (&node.children[..], &children[1..]),
+        // tight list items don't have paragraphs
+        _ => (children, &[][..]),
+    };
+
+    let pos = inline.iter().position(|node| node.is::<Softbreak>())?;
+    let tag = &inline[..pos];
+    let is_term = |node: &Node| node.is::<CodeInline>() || node.is::<Strong>();
+    let is_tag = tag.iter().any(is_term) && tag.iter().all(|node| match node.cast::<Text>() {
+        // allow separators like "`-v`, `--verbose`"
+        Some(text) => text.content.trim().chars().all(|ch| ch == ',' || ch == '|'),
+        None => is_term(node),
+    });
+
+    if !is_tag { return None; }
+    Some((tag, &inline[pos + 1..], blocks))
+}
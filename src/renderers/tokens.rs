@@ -0,0 +1,611 @@
+//! Export to markdown-it.js token stream.
+//!
+//! Flattens the AST into a list of [Token] objects with the same shape as `Token`
+//! in [markdown-it.js](https://github.com/markdown-it/markdown-it), so output of this
+//! crate can be compared to the output of the original library. Container nodes
+//! produce `*_open` and `*_close` tokens, inline content of paragraphs, headings
+//! and table cells is wrapped in `inline` tokens with `children`, and paragraphs
+//! of tight lists are reported as hidden, same as in JS.
+//!
+//! Like `text_join` rule in JS, escaped characters and entities are reported as text
+//! and merged with adjacent text tokens. Line ranges in `map` are derived from node
+//! source maps. Unknown nodes get their type from the Rust type name (`MyNode`
+//! becomes `my_node`), use [TokenExporter::set_handler] to convert them properly.
+//!
+//! With `json` feature, tokens can be converted to JSON with `to_json`.
+//!
+//! ```rust
+//! let md = &mut markdown_it::MarkdownIt::new();
+//! markdown_it::plugins::cmark::add(md);
+//!
+//! let source = "## Hi";
+//! let tokens = markdown_it::renderers::tokens::to_tokens(&md.parse(source), source);
+//! let types = tokens.iter().map(|token| token.kind.as_str()).collect::<Vec<_>>();
+//! assert_eq!(types, ["heading_open", "inline", "heading_close"]);
+//! assert_eq!(tokens[0].tag, "h2");
+//! assert_eq!(tokens[0].markup, "##");
+//! assert_eq!(tokens[0].map, Some([0, 1]));
+//! assert_eq!(tokens[1].content, "Hi");
+//! ```
+use std::collections::HashMap;
+use std::mem;
+use crate::{Node, NodeValue};
+use crate::common::TypeKey;
+use crate::common::sourcemap::SourcePos;
+use crate::parser::core::Root;
+use crate::parser::inline::{Text, TextSpecial};
+use crate::plugins::cmark::block::blockquote::Blockquote;
+use crate::plugins::cmark::block::code::CodeBlock;
+use crate::plugins::cmark::block::fence::CodeFence;
+use crate::plugins::cmark::block::heading::ATXHeading;
+use crate::plugins::cmark::block::hr::ThematicBreak;
+use crate::plugins::cmark::block::lheading::SetextHeader;
+use crate::plugins::cmark::block::list::{BulletList, ListItem, OrderedList};
+use crate::plugins::cmark::block::paragraph::Paragraph;
+use crate::plugins::cmark::inline::autolink::Autolink;
+use crate::plugins::cmark::inline::backticks::CodeInline;
+use crate::plugins::cmark::inline::emphasis::{Em, Strong};
+use crate::plugins::cmark::inline::image::Image;
+use crate::plugins::cmark::inline::link::Link;
+use crate::plugins::cmark::inline::newline::{Hardbreak, Softbreak};
+use crate::plugins::extra::strikethrough::Strikethrough;
+use crate::plugins::extra::tables::{ColumnAlignment, Table, TableHead};
+use crate::plugins::html::html_block::HtmlBlock;
+use crate::plugins::html::html_inline::HtmlInline;
+use super::{is_block, linkified_url, text_content};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+/// A single token, same as `Token` in markdown-it.js.
+pub struct Token {
+    /// Type of the token, e.g. `paragraph_open` (it's `type` in JS).
+    pub kind: String,
+    /// Html tag name, e.g. `p`.
+    pub tag: String,
+    /// Html attributes.
+    pub attrs: Vec<(String, String)>,
+    /// Source lines `[start, end)` (zero-based), block tokens only.
+    pub map: Option<[usize; 2]>,
+    /// 1 for opening tags, -1 for closing tags, 0 for everything else.
+    pub nesting: i8,
+    /// Nesting level, same as in JS.
+    pub level: u32,
+    /// Inline tokens, for `inline` tokens only.
+    pub children: Option<Vec<Token>>,
+    /// Text content for text, code, html and inline tokens.
+    pub content: String,
+    /// Markup characters, e.g. `*` for emphasis or fence backticks.
+    pub markup: String,
+    /// Fence info string, list item number, or `auto` for autolinks.
+    pub info: String,
+    /// True for block tokens, false for inline ones.
+    pub block: bool,
+    /// True for paragraphs in tight lists.
+    pub hidden: bool,
+}
+
+impl Token {
+    pub fn new(kind: impl Into<String>, tag: impl Into<String>, nesting: i8) -> Self {
+        Self { kind: kind.into(), tag: tag.into(), nesting, ..Default::default() }
+    }
+
+    #[cfg(feature = "json")]
+    /// Convert token to JSON, with the same keys as in JS.
+    pub fn to_json(&self) -> serde_json::Value {
+        let attrs = if self.attrs.is_empty() {
+            serde_json::Value::Null
+        } else {
+            self.attrs.iter().map(|(name, value)| serde_json::json!([name, value])).collect()
+        };
+        let children = self.children.as_ref().map(|children| to_json(children));
+
+        serde_json::json!({
+            "type": self.kind,
+            "tag": self.tag,
+            "attrs": attrs,
+            "map": self.map,
+            "nesting": self.nesting,
+            "level": self.level,
+            "children": children,
+            "content": self.content,
+            "markup": self.markup,
+            "info": self.info,
+            "meta": null,
+            "block": self.block,
+            "hidden": self.hidden,
+        })
+    }
+}
+
+#[cfg(feature = "json")]
+/// Convert list of tokens to JSON array, same as `JSON.stringify(tokens)` in JS.
+pub fn to_json(tokens: &[Token]) -> serde_json::Value {
+    tokens.iter().map(Token::to_json).collect()
+}
+
+/// Custom handler for a node type, see [TokenExporter::set_handler].
+pub type TokenFn = fn (node: &Node, fmt: &mut TokenExporter);
+
+/// Convert node to tokens, `source` is the markdown it was parsed from (used for line maps).
+pub fn to_tokens(node: &Node, source: &str) -> Vec<Token> {
+    TokenExporter::new(source).export(node)
+}
+
+#[derive(Debug)]
+/// Converts nodes to tokens, see [module documentation](self).
+///
+/// ```rust
+/// use markdown_it::plugins::cmark::block::hr::ThematicBreak;
+/// use markdown_it::renderers::tokens::{Token, TokenExporter};
+///
+/// let md = &mut markdown_it::MarkdownIt::new();
+/// markdown_it::plugins::cmark::add(md);
+/// let source = "***";
+/// let ast = md.parse(source);
+///
+/// let mut fmt = TokenExporter::new(source);
+/// fmt.set_handler::<ThematicBreak>(|node, fmt| {
+///     let mut token = Token::new("page_break", "hr", 0);
+///     token.map = fmt.map(node);
+///     fmt.push(token);
+/// });
+/// let tokens = fmt.export(&ast);
+/// assert_eq!(tokens[0].kind, "page_break");
+/// assert_eq!(tokens[0].map, Some([0, 1]));
+/// ```
+pub struct TokenExporter {
+    handlers: HashMap<TypeKey, TokenFn>,
+    source: String,
+    line_starts: Vec<usize>,
+    tokens: Vec<Token>,
+    level: u32,
+    block: bool,
+    prefixes: Vec<LinePrefix>,
+}
+
+#[derive(Debug, Clone, Copy)]
+// what block parser strips from lines of enclosing containers
+enum LinePrefix {
+    // blockquote marker, optional on lazy continuation lines
+    Quote,
+    // list item content indentation, absolute column
+    Indent(usize),
+}
+
+impl TokenExporter {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut chars = source.char_indices().peekable();
+
+        while let Some((pos, ch)) = chars.next() {
+            let crlf = ch == '\r' && matches!(chars.peek(), Some((_, '\n')));
+            if (ch == '\n' || ch == '\r') && !crlf {
+                line_starts.push(pos + 1);
+            }
+        }
+
+        Self {
+            handlers: HashMap::new(),
+            source: source.to_owned(),
+            line_starts,
+            tokens: Vec::new(),
+            level: 0,
+            block: true,
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Convert nodes of type `T` with a custom function (it's used for custom
+    /// nodes, and can also replace conversion of built-in ones).
+    pub fn set_handler<T: NodeValue>(&mut self, f: TokenFn) {
+        self.handlers.insert(TypeKey::of::<T>(), f);
+    }
+
+    /// Convert node, return resulting tokens.
+    pub fn export(&mut self, node: &Node) -> Vec<Token> {
+        self.level = 0;
+        self.block = true;
+        self.prefixes.clear();
+        self.node(node);
+        mem::take(&mut self.tokens)
+    }
+
+    /// Convert a single node, using custom handler if there is one.
+    pub fn node(&mut self, node: &Node) {
+        if let Some(f) = self.handlers.get(&node.node_type).copied() {
+            f(node, self);
+        } else if let Some(data) = node.cast::<Text>() {
+            self.text(&data.content);
+        } else if let Some(data) = node.cast::<TextSpecial>() {
+            self.text(&data.content);
+        } else if node.is::<Softbreak>() {
+            self.push(Token::new("softbreak", "br", 0));
+        } else if node.is::<Hardbreak>() {
+            self.push(Token::new("hardbreak", "br", 0));
+        } else if node.is::<Root>() {
+            self.contents(&node.children);
+        } else if node.is::<Paragraph>() {
+            self.container(Token::new("paragraph", "p", 0), node, |fmt| fmt.inline(&node.children));
+        } else if let Some(data) = node.cast::<ATXHeading>() {
+            let mut token = Token::new("heading", format!("h{}", data.level), 0);
+            token.markup = "#".repeat(data.level as usize);
+            self.container(token, node, |fmt| fmt.inline(&node.children));
+        } else if let Some(data) = node.cast::<SetextHeader>() {
+            let mut token = Token::new("heading", format!("h{}", data.level), 0);
+            token.markup = data.marker.to_string();
+            self.container(token, node, |fmt| fmt.inline(&node.children));
+        } else if let Some(data) = node.cast::<ThematicBreak>() {
+            let mut token = Token::new("hr", "hr", 0);
+            token.markup = data.marker.to_string().repeat(data.marker_len);
+            self.leaf(token, node);
+        } else if node.is::<Blockquote>() {
+            let mut token = Token::new("blockquote", "blockquote", 0);
+            token.markup = ">".to_owned();
+            self.container(token, node, |fmt| fmt.prefixed(LinePrefix::Quote, |fmt| fmt.contents(&node.children)));
+        } else if let Some(data) = node.cast::<BulletList>() {
+            let mut token = Token::new("bullet_list", "ul", 0);
+            token.markup = data.marker.to_string();
+            self.container(token, node, |fmt| fmt.list_items(node, None, data.marker));
+        } else if let Some(data) = node.cast::<OrderedList>() {
+            let mut token = Token::new("ordered_list", "ol", 0);
+            token.markup = data.marker.to_string();
+            if data.start != 1 {
+                token.attrs.push(("start".to_owned(), data.start.to_string()));
+            }
+            self.container(token, node, |fmt| fmt.list_items(node, Some(data.start), data.marker));
+        } else if node.is::<ListItem>() {
+            // only reached when exporting a list item by itself
+            self.container(Token::new("list_item", "li", 0), node, |fmt| fmt.list_item_contents(node));
+        } else if let Some(data) = node.cast::<CodeBlock>() {
+            let mut token = Token::new("code_block", "code", 0);
+            token.content = data.content.clone();
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<CodeFence>() {
+            let mut token = Token::new("fence", "code", 0);
+            token.content = data.content.clone();
+            token.info = data.info.clone();
+            token.markup = data.marker.to_string().repeat(data.marker_len);
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<HtmlBlock>() {
+            let mut token = Token::new("html_block", "", 0);
+            token.content = data.content.clone();
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<HtmlInline>() {
+            let mut token = Token::new("html_inline", "", 0);
+            token.content = data.content.clone();
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<Em>() {
+            let mut token = Token::new("em", "em", 0);
+            token.markup = data.marker.to_string();
+            self.container(token, node, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<Strong>() {
+            let mut token = Token::new("strong", "strong", 0);
+            token.markup = data.marker.to_string().repeat(2);
+            self.container(token, node, |fmt| fmt.contents(&node.children));
+        } else if node.is::<Strikethrough>() {
+            let mut token = Token::new("s", "s", 0);
+            token.markup = "~~".to_owned();
+            self.container(token, node, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<CodeInline>() {
+            let mut token = Token::new("code_inline", "code", 0);
+            token.content = text_content(node).into_owned();
+            token.markup = data.marker.to_string().repeat(data.marker_len);
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<Link>() {
+            let mut token = Token::new("link", "a", 0);
+            token.attrs.push(("href".to_owned(), data.url.clone()));
+            if let Some(title) = &data.title {
+                token.attrs.push(("title".to_owned(), title.clone()));
+            }
+            self.container(token, node, |fmt| fmt.contents(&node.children));
+        } else if let Some(data) = node.cast::<Autolink>() {
+            self.autolink(node, &data.url, "autolink");
+        } else if let Some(url) = linkified_url(node) {
+            self.autolink(node, url, "linkify");
+        } else if let Some(data) = node.cast::<Image>() {
+            let mut token = Token::new("image", "img", 0);
+            token.attrs.push(("src".to_owned(), data.url.clone()));
+            token.attrs.push(("alt".to_owned(), String::new()));
+            if let Some(title) = &data.title {
+                token.attrs.push(("title".to_owned(), title.clone()));
+            }
+            token.content = text_content(node).into_owned();
+            token.children = Some(self.nested(|fmt| fmt.contents(&node.children)));
+            self.leaf(token, node);
+        } else if let Some(data) = node.cast::<Table>() {
+            self.container(Token::new("table", "table", 0), node, |fmt| {
+                for section in node.children.iter() {
+                    let head = section.is::<TableHead>();
+                    let token = if head { Token::new("thead", "thead", 0) } else { Token::new("tbody", "tbody", 0) };
+                    fmt.container(token, section, |fmt| {
+                        for row in section.children.iter() {
+                            fmt.container(Token::new("tr", "tr", 0), row, |fmt| {
+                                for (idx, cell) in row.children.iter().enumerate() {
+                                    fmt.table_cell(cell, head, data.alignments.get(idx));
+                                }
+                            });
+                        }
+                    });
+                }
+            });
+        } else {
+            let name = node.name().rsplit("::").next().unwrap_or_default();
+            let mut kind = String::new();
+            for (idx, ch) in name.chars().enumerate() {
+                if ch.is_uppercase() && idx > 0 { kind.push('_'); }
+                kind.extend(ch.to_lowercase());
+            }
+            if node.children.is_empty() {
+                self.leaf(Token::new(kind, "", 0), node);
+            } else {
+                self.container(Token::new(kind, "", 0), node, |fmt| fmt.contents(&node.children));
+            }
+        }
+    }
+
+    /// Convert a list of nodes.
+    pub fn contents(&mut self, nodes: &[Node]) {
+        for node in nodes.iter() {
+            self.node(node);
+        }
+    }
+
+    /// Add a token, its `level` and `block` fields are set according to the current position.
+    pub fn push(&mut self, mut token: Token) {
+        if token.nesting < 0 { self.level = self.level.saturating_sub(1); }
+        token.level = self.level;
+        token.block = self.block;
+        if token.nesting > 0 { self.level += 1; }
+        self.tokens.push(token);
+    }
+
+    /// Add `inline` token containing given nodes (they are converted to its children).
+    pub fn inline(&mut self, nodes: &[Node]) {
+        let mut token = Token::new("inline", "", 0);
+        let (start, end) = self.byte_range(nodes);
+        token.content = if end > start { self.line_contents(start, end) } else { String::new() };
+        token.map = self.lines(start, end);
+        token.children = Some(self.nested(|fmt| fmt.contents(nodes)));
+        self.push(token);
+    }
+
+    /// Line range `[start, end)` of a node in the source, if it is known.
+    pub fn map(&self, node: &Node) -> Option<[usize; 2]> {
+        let (start, end) = self.offsets(node.srcmap?);
+        self.lines(start, end)
+    }
+
+    // add opening token, contents and closing token,
+    // `token` is a template for both with `_open`/`_close` suffix added to its type
+    fn container(&mut self, token: Token, node: &Node, f: impl FnOnce(&mut Self)) {
+        let mut open = token.clone();
+        open.kind.push_str("_open");
+        open.nesting = 1;
+        open.map = if self.block { self.map(node) } else { None };
+        open.attrs.extend(node.attrs.iter().map(|(name, value)| ((*name).to_owned(), value.clone())));
+        self.push(open);
+
+        f(self);
+
+        let mut close = token;
+        close.kind.push_str("_close");
+        close.nesting = -1;
+        close.attrs = Vec::new();
+        close.info = String::new();
+        self.push(close);
+    }
+
+    fn leaf(&mut self, mut token: Token, node: &Node) {
+        if self.block { token.map = self.map(node); }
+        token.attrs.extend(node.attrs.iter().map(|(name, value)| ((*name).to_owned(), value.clone())));
+        self.push(token);
+    }
+
+    fn text(&mut self, content: &str) {
+        if let Some(last) = self.tokens.last_mut() {
+            if last.kind == "text" && !self.block {
+                last.content.push_str(content);
+                return;
+            }
+        }
+        let mut token = Token::new("text", "", 0);
+        token.content = content.to_owned();
+        self.push(token);
+    }
+
+    fn autolink(&mut self, node: &Node, url: &str, markup: &str) {
+        let mut token = Token::new("link", "a", 0);
+        token.attrs.push(("href".to_owned(), url.to_owned()));
+        token.markup = markup.to_owned();
+        token.info = "auto".to_owned();
+        self.container(token, node, |fmt| fmt.contents(&node.children));
+    }
+
+    fn list_items(&mut self, node: &Node, start: Option<u32>, marker: char) {
+        for (idx, item) in node.children.iter().enumerate() {
+            if !item.is::<ListItem>() || self.handlers.contains_key(&item.node_type) {
+                self.node(item);
+                continue;
+            }
+            let mut token = Token::new("list_item", "li", 0);
+            token.markup = marker.to_string();
+            if let Some(start) = start {
+                token.info = (start as usize + idx).to_string();
+            }
+            self.container(token, item, |fmt| fmt.list_item_contents(item));
+        }
+    }
+
+    // items in tight lists contain inline nodes directly,
+    // JS wraps them in hidden paragraphs
+    fn list_item_contents(&mut self, node: &Node) {
+        let indent = LinePrefix::Indent(self.item_indent(node));
+        self.prefixed(indent, |fmt| {
+            let mut inline_start = None;
+
+            for (idx, child) in node.children.iter().enumerate() {
+                if is_block(child) {
+                    if let Some(start) = inline_start.take() {
+                        fmt.hidden_paragraph(&node.children[start..idx]);
+                    }
+                    fmt.node(child);
+                } else if inline_start.is_none() {
+                    inline_start = Some(idx);
+                }
+            }
+            if let Some(start) = inline_start {
+                fmt.hidden_paragraph(&node.children[start..]);
+            }
+        });
+    }
+
+    // column where list item content starts: after the marker and up to 4 spaces,
+    // or right after the marker and one space if content is indented further or starts on the next line
+    fn item_indent(&self, node: &Node) -> usize {
+        let Some(srcmap) = node.srcmap else { return 0 };
+        let start = self.offsets(srcmap).0;
+        let line_end = self.line_starts.get(self.line_of(start) + 1).copied().unwrap_or(self.source.len());
+        let marker = self.source[start..line_end].find(char::is_whitespace).map_or(line_end, |len| start + len);
+        let marker_end = self.column(marker);
+
+        let content = node.children.iter().find_map(|child| child.srcmap).map(|srcmap| self.offsets(srcmap).0);
+        match content {
+            Some(content) if content < line_end && self.column(content) <= marker_end + 4 => self.column(content),
+            _ => marker_end + 1,
+        }
+    }
+
+    // run `f` with an extra line prefix of enclosing container
+    fn prefixed(&mut self, prefix: LinePrefix, f: impl FnOnce(&mut Self)) {
+        self.prefixes.push(prefix);
+        f(self);
+        self.prefixes.pop();
+    }
+
+    // source text from `start` to `end` with container prefixes removed
+    // from continuation lines, same as inline content is collected by block parser
+    fn line_contents(&self, start: usize, end: usize) -> String {
+        let mut result = String::new();
+        let mut pos = start;
+
+        loop {
+            let next = self.line_starts.get(self.line_of(pos) + 1).copied().filter(|next| *next < end);
+            let line = &self.source[pos..next.unwrap_or(end)];
+            result.push_str(line.trim_end_matches(['\n', '\r']));
+            let Some(next) = next else { break };
+            result.push('\n');
+            pos = self.strip_prefixes(next);
+        }
+        result
+    }
+
+    // skip container markers and indentation at the start of the line
+    fn strip_prefixes(&self, line_start: usize) -> usize {
+        let bytes = self.source.as_bytes();
+        let mut pos = line_start;
+        let mut col = 0;
+
+        let skip_space = |pos: &mut usize, col: &mut usize, limit: usize| {
+            while *col < limit {
+                match bytes.get(*pos) {
+                    Some(b' ') => *col += 1,
+                    Some(b'\t') => *col += 4 - *col % 4,
+                    _ => break,
+                }
+                *pos += 1;
+            }
+        };
+
+        for prefix in self.prefixes.iter() {
+            match *prefix {
+                LinePrefix::Quote => {
+                    let (mut quote_pos, mut quote_col) = (pos, col);
+                    skip_space(&mut quote_pos, &mut quote_col, col + 3);
+                    if bytes.get(quote_pos) == Some(&b'>') {
+                        pos = quote_pos + 1;
+                        col = quote_col + 1;
+                        if matches!(bytes.get(pos), Some(b' ' | b'\t')) {
+                            pos += 1;
+                            col += 1;
+                        }
+                    }
+                }
+                LinePrefix::Indent(column) => skip_space(&mut pos, &mut col, column),
+            }
+        }
+        pos
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    // visual column of byte offset, with tabs expanded to 4 spaces
+    fn column(&self, offset: usize) -> usize {
+        let line_start = self.line_starts[self.line_of(offset)];
+        self.source[line_start..offset].chars().fold(0, |col, ch| if ch == '\t' { col + 4 - col % 4 } else { col + 1 })
+    }
+
+    fn hidden_paragraph(&mut self, nodes: &[Node]) {
+        let (start, end) = self.byte_range(nodes);
+        let mut open = Token::new("paragraph_open", "p", 1);
+        open.map = self.lines(start, end);
+        open.hidden = true;
+        self.push(open);
+        self.inline(nodes);
+        let mut close = Token::new("paragraph_close", "p", -1);
+        close.hidden = true;
+        self.push(close);
+    }
+
+    fn table_cell(&mut self, node: &Node, head: bool, alignment: Option<&ColumnAlignment>) {
+        let mut token = if head { Token::new("th", "th", 0) } else { Token::new("td", "td", 0) };
+        let align = match alignment {
+            Some(ColumnAlignment::Left) => Some("left"),
+            Some(ColumnAlignment::Right) => Some("right"),
+            Some(ColumnAlignment::Center) => Some("center"),
+            Some(ColumnAlignment::None) | None => None,
+        };
+        if let Some(align) = align {
+            token.attrs.push(("style".to_owned(), format!("text-align:{align}")));
+        }
+        self.container(token, node, |fmt| fmt.inline(&node.children));
+    }
+
+    // collect tokens produced by `f` into a separate list (for inline children)
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) -> Vec<Token> {
+        let tokens = mem::take(&mut self.tokens);
+        let level = mem::replace(&mut self.level, 0);
+        let block = mem::replace(&mut self.block, false);
+        f(self);
+        self.block = block;
+        self.level = level;
+        mem::replace(&mut self.tokens, tokens)
+    }
+
+    // byte offsets from the start of the first node to the end of the last one
+    fn byte_range(&self, nodes: &[Node]) -> (usize, usize) {
+        let start = nodes.iter().find_map(|node| node.srcmap);
+        let end = nodes.iter().rev().find_map(|node| node.srcmap);
+        match (start, end) {
+            (Some(start), Some(end)) => (self.offsets(start).0, self.offsets(end).1),
+            _ => (0, 0),
+        }
+    }
+
+    // byte offsets of a source map, clamped to the source and char boundaries
+    // (source maps of some nodes can point past the end of the source)
+    fn offsets(&self, srcmap: SourcePos) -> (usize, usize) {
+        let clamp = |mut offset: usize| {
+            offset = offset.min(self.source.len());
+            while !self.source.is_char_boundary(offset) { offset -= 1; }
+            offset
+        };
+        let (start, end) = srcmap.get_byte_offsets();
+        (clamp(start), clamp(end))
+    }
+
+    fn lines(&self, start: usize, end: usize) -> Option<[usize; 2]> {
+        if end <= start { return None; }
+        Some([self.line_of(start), self.line_of(end - 1) + 1])
+    }
+}
@@ -44,6 +44,19 @@ fn no_block_parser() {
     assert_eq!(result, "hello <em>world</em>");
 }*/
 
+// parser shared by tests of renderers other than html
+fn parse(input: &str) -> markdown_it::Node {
+    static MD : Lazy<markdown_it::MarkdownIt> = Lazy::new(|| {
+        let mut md = markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(&mut md);
+        markdown_it::plugins::html::add(&mut md);
+        markdown_it::plugins::extra::tables::add(&mut md);
+        markdown_it::plugins::extra::strikethrough::add(&mut md);
+        md
+    });
+    MD.parse(input)
+}
+
 fn run(input: &str, output: &str) {
     let output = if output.is_empty() { "".to_owned() } else { output.to_owned() + "\n" };
     let md = &mut markdown_it::MarkdownIt::new();
@@ -85,6 +98,17 @@ r#"<blockquote>
 </blockquote>"#);
     }
 
+    #[test]
+    fn regression_softbreak_in_link_label() {
+        // space before unclosed bracket is kept
+        run("a [b\nc]", "<p>a [b\nc]</p>");
+    }
+
+    #[test]
+    fn regression_image_alt_escapes() {
+        run("![a\\*b &amp; c](x)", "<p><img src=\"x\" alt=\"a*b &amp; c\"></p>");
+    }
+
     #[test]
     fn null_char_replacement() {
         run("&#0;", "<p>\u{FFFD}</p>");
@@ -118,3 +142,2245 @@ mod examples {
         main();
     }
 }
+
+mod renderer {
+    use markdown_it::parser::extset::RenderExtSet;
+    use markdown_it::parser::renderer::{render_node, AttrOrder, HTMLRenderer, RenderOptions, RenderOverrides};
+    use markdown_it::plugins::cmark::block::heading::ATXHeading;
+    use markdown_it::plugins::cmark::inline::link::Link;
+    use markdown_it::plugins::cmark::inline::emphasis::Em;
+    use markdown_it::{Node, Renderer};
+
+    fn run(input: &str, output: &str, options: RenderOptions) {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let result = md.parse(input).render_with(&options);
+        assert_eq!(result, output);
+    }
+
+    #[test]
+    fn xhtml() {
+        let options = RenderOptions { xhtml: true, ..Default::default() };
+        run("---", "<hr />\n", options);
+    }
+
+    #[test]
+    fn breaks() {
+        let options = RenderOptions { breaks: true, ..Default::default() };
+        run("a\nb  \nc", "<p>a<br>\nb<br>\nc</p>\n", options);
+        run("a\nb", "<p>a\nb</p>\n", RenderOptions::default());
+    }
+
+    #[test]
+    fn newline() {
+        let options = RenderOptions { newline: "\r\n", ..Default::default() };
+        run("a\nb\n\n    c\n    d", "<p>a\r\nb</p>\r\n<pre><code>c\r\nd\r\n</code></pre>\r\n", options);
+    }
+
+    #[test]
+    fn indent() {
+        let options = RenderOptions { indent: 2, ..Default::default() };
+        run(
+            "- a\n\n  > b\n  >\n  > ```\n  > x\n  >   y\n  > ```\n- c",
+            "<ul>\n  <li>\n    <p>a</p>\n    <blockquote>\n      <p>b</p>\n      \
+                <pre><code>x\n  y\n</code></pre>\n    </blockquote>\n  </li>\n  <li>\n    <p>c</p>\n  </li>\n</ul>\n",
+            options
+        );
+    }
+
+    #[test]
+    fn attr_order() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let mut ast = md.parse("*a*");
+        let em = &mut ast.children[0].children[0];
+        em.attrs.push(("id", "x".into()));
+        em.attrs.push(("class", "y".into()));
+        em.attrs.push(("data-z", "z".into()));
+
+        assert_eq!(ast.render(), "<p><em id=\"x\" class=\"y\" data-z=\"z\">a</em></p>\n");
+        let options = RenderOptions { attr_order: AttrOrder::Sorted, ..Default::default() };
+        assert_eq!(ast.render_with(&options), "<p><em class=\"y\" data-z=\"z\" id=\"x\">a</em></p>\n");
+    }
+
+    // renders emphasis as italic, forwards everything else to html renderer
+    struct ItalicRenderer(HTMLRenderer<'static>);
+
+    impl Renderer for ItalicRenderer {
+        fn open(&mut self, tag: &str, attrs: &[(&str, String)]) {
+            self.0.open(if tag == "em" { "i" } else { tag }, attrs);
+        }
+        fn close(&mut self, tag: &str) {
+            self.0.close(if tag == "em" { "i" } else { tag });
+        }
+        fn self_close(&mut self, tag: &str, attrs: &[(&str, String)]) { self.0.self_close(tag, attrs); }
+        fn contents(&mut self, nodes: &[Node]) {
+            for node in nodes {
+                render_node(node, self);
+            }
+        }
+        fn cr(&mut self) { self.0.cr(); }
+        fn softbreak(&mut self) { self.0.softbreak(); }
+        fn text(&mut self, text: &str) { self.0.text(text); }
+        fn text_raw(&mut self, text: &str) { self.0.text_raw(text); }
+        fn ext(&mut self) -> &mut RenderExtSet { self.0.ext() }
+    }
+
+    // records size of each write
+    #[derive(Default)]
+    struct ChunkWriter(Vec<u8>, Vec<usize>);
+
+    impl std::io::Write for ChunkWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.extend_from_slice(buf);
+            self.1.push(buf.len());
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    struct FailingWriter;
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn streaming() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let input = "# hello\0world\n\n- *a*\n- b\n\n".repeat(2000);
+        let ast = md.parse(&input);
+        let expected = ast.render();
+
+        let mut writer = ChunkWriter::default();
+        ast.render_to(&mut writer).unwrap();
+        assert_eq!(String::from_utf8(writer.0).unwrap(), expected);
+        assert!(writer.1.len() > 10);
+        assert!(writer.1.iter().all(|len| *len < 10000));
+
+        let mut string = String::new();
+        ast.render_to_fmt(&mut string).unwrap();
+        assert_eq!(string, expected);
+
+        let mut output = Vec::new();
+        let options = RenderOptions { newline: "\r\n", indent: 2, ..Default::default() };
+        let mut fmt = HTMLRenderer::with_writer(options.clone(), &mut output);
+        fmt.render(&ast);
+        fmt.finish().unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), ast.render_with(&options));
+
+        let options = RenderOptions { xhtml: true, breaks: true, ..Default::default() };
+        let mut output = Vec::new();
+        ast.render_to_with(&mut output, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), ast.render_with(&options));
+        let mut string = String::new();
+        ast.render_to_fmt_with(&mut string, &options).unwrap();
+        assert_eq!(string, ast.render_with(&options));
+
+        let err = ast.render_to(&mut FailingWriter).unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+    }
+
+    #[test]
+    fn render_overrides() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        md.set_render_override::<ATXHeading>(|node, fmt| {
+            let level = node.cast::<ATXHeading>().unwrap().level;
+            fmt.open(&format!("h{}", level + 1), &[]);
+            fmt.contents(&node.children);
+            fmt.close(&format!("h{}", level + 1));
+            fmt.cr();
+        });
+        md.set_render_override::<Em>(|node, fmt| {
+            fmt.text("_");
+            node.node_value.render(node, fmt);
+            fmt.text("_");
+        });
+
+        let ast = md.parse("# *a*\n\n*b*");
+        assert!(ast.children[0].is::<ATXHeading>());
+        assert_eq!(md.render(&ast), "<h2>_<em>a</em>_</h2>\n<p>_<em>b</em>_</p>\n");
+        assert_eq!(ast.render(), "<h1><em>a</em></h1>\n<p><em>b</em></p>\n");
+
+        // overrides apply to any node, not only to the root
+        assert_eq!(md.render(&ast.children[1]), "<p>_<em>b</em>_</p>\n");
+        let options = RenderOptions { xhtml: true, ..md.render_options() };
+        let mut output = Vec::new();
+        ast.children[0].render_to_with(&mut output, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "<h2>_<em>a</em>_</h2>\n");
+
+        // overrides passed to renderer directly
+        let mut overrides = RenderOverrides::default();
+        overrides.set::<Em>(|node, fmt| fmt.contents(&node.children));
+        let mut fmt = HTMLRenderer::new(RenderOptions { overrides, ..Default::default() });
+        fmt.render(&ast);
+        assert_eq!(String::from(fmt), "<h1>a</h1>\n<p>b</p>\n");
+
+        md.remove_render_override::<ATXHeading>();
+        md.remove_render_override::<Link>();
+        assert_eq!(md.render(&md.parse("# *a*")), "<h1>_<em>a</em>_</h1>\n");
+    }
+
+    #[test]
+    fn wrapped_renderer() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let ast = md.parse("> x\n\n*a*\nb");
+        assert!(ast.children[1].children[0].is::<Em>());
+
+        let mut fmt = ItalicRenderer(HTMLRenderer::new(RenderOptions { breaks: true, ..Default::default() }));
+        fmt.contents(std::slice::from_ref(&ast));
+        let result : String = fmt.0.into();
+        assert_eq!(result, "<blockquote>\n<p>x</p>\n</blockquote>\n<p><i>a</i><br>\nb</p>\n");
+    }
+}
+
+mod url_rewrite {
+    use markdown_it::plugins::cmark::inline::image::Image;
+    use markdown_it::plugins::cmark::inline::link::Link;
+    use markdown_it::plugins::extra::url_rewrite::{self, BaseUrl, MarkdownToHtml, RewriteFn, UrlContext};
+
+    fn run(input: &str, output: &str, f: fn (&mut markdown_it::MarkdownIt)) {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        f(md);
+        let result = md.parse(input).render();
+        assert_eq!(result.trim(), output);
+    }
+
+    #[test]
+    fn markdown_to_html() {
+        let f = |md: &mut markdown_it::MarkdownIt| url_rewrite::add(md, MarkdownToHtml);
+        run("[x](foo.md#bar)", r#"<p><a href="foo.html#bar">x</a></p>"#, f);
+        run("[x](foo.md?a=b)", r#"<p><a href="foo.html?a=b">x</a></p>"#, f);
+        run("[x](foo.mdx)", r#"<p><a href="foo.mdx">x</a></p>"#, f);
+        run("[x](https://example.org/README.md)", r#"<p><a href="https://example.org/README.md">x</a></p>"#, f);
+    }
+
+    #[test]
+    fn base_url() {
+        let f = |md: &mut markdown_it::MarkdownIt| url_rewrite::add(md, BaseUrl::new("https://example.org/docs"));
+        run("[x](foo/bar)", r#"<p><a href="https://example.org/docs/foo/bar">x</a></p>"#, f);
+        run("[x](/foo)", r#"<p><a href="https://example.org/docs/foo">x</a></p>"#, f);
+        run("[x](#foo)", r##"<p><a href="#foo">x</a></p>"##, f);
+        run("[x]()", r#"<p><a href="">x</a></p>"#, f);
+        run("[x](//cdn/foo)", r#"<p><a href="//cdn/foo">x</a></p>"#, f);
+        run("<mailto:foo@bar>", r#"<p><a href="mailto:foo@bar">mailto:foo@bar</a></p>"#, f);
+    }
+
+    #[test]
+    fn base_url_only_images() {
+        let f = |md: &mut markdown_it::MarkdownIt| url_rewrite::add(md, BaseUrl::new("https://cdn/").only::<Image>());
+        run("[x](a.png) ![y](b.png)", r#"<p><a href="a.png">x</a> <img src="https://cdn/b.png" alt="y"></p>"#, f);
+    }
+
+    #[test]
+    fn references() {
+        let f = |md: &mut markdown_it::MarkdownIt| url_rewrite::add(md, MarkdownToHtml);
+        run("[x]\n\n[x]: foo.md", r#"<p><a href="foo.html">x</a></p>"#, f);
+    }
+
+    #[test]
+    fn context() {
+        fn rewrite(url: &str, ctx: &UrlContext) -> Option<String> {
+            assert!(ctx.srcmap.is_some());
+            if ctx.is::<Link>() { Some(format!("{url}?link")) } else { None }
+        }
+        let f = |md: &mut markdown_it::MarkdownIt| url_rewrite::add(md, RewriteFn(rewrite));
+        run("[x](a) ![y](b)", r#"<p><a href="a?link">x</a> <img src="b" alt="y"></p>"#, f);
+    }
+
+    #[test]
+    fn chained() {
+        let f = |md: &mut markdown_it::MarkdownIt| {
+            url_rewrite::add(md, MarkdownToHtml);
+            url_rewrite::add(md, BaseUrl::new("/docs/"));
+        };
+        run("[x](a.md)", r#"<p><a href="/docs/a.html">x</a></p>"#, f);
+    }
+
+    #[test]
+    #[cfg(feature = "linkify")]
+    fn linkified() {
+        let f = |md: &mut markdown_it::MarkdownIt| {
+            markdown_it::plugins::extra::linkify::add(md);
+            url_rewrite::add(md, RewriteFn(|url, _| Some(format!("{url}?ref=x"))));
+        };
+        run("see https://example.org/a", r#"<p>see <a href="https://example.org/a?ref=x">https://example.org/a</a></p>"#, f);
+
+        // rewriter added before linkify plugin still applies
+        let f = |md: &mut markdown_it::MarkdownIt| {
+            url_rewrite::add(md, RewriteFn(|url, _| Some(format!("{url}?ref=x"))));
+            markdown_it::plugins::extra::linkify::add(md);
+        };
+        run("see https://example.org/a", r#"<p>see <a href="https://example.org/a?ref=x">https://example.org/a</a></p>"#, f);
+    }
+}
+
+mod fence_info {
+    use markdown_it::plugins::cmark::block::fence::FenceInfo;
+
+    #[test]
+    fn lang_only() {
+        let info = FenceInfo::parse("rust");
+        assert_eq!(info.lang.as_deref(), Some("rust"));
+        assert_eq!(info, FenceInfo { lang: Some("rust".into()), ..Default::default() });
+        assert_eq!(FenceInfo::parse("  "), FenceInfo::default());
+    }
+
+    #[test]
+    fn attributes() {
+        let info = FenceInfo::parse("js {.a .b #main data-x=1 data-y=\"two words\" hidden}");
+        assert_eq!(info.lang.as_deref(), Some("js"));
+        assert_eq!(info.classes, vec!["a", "b"]);
+        assert_eq!(info.id.as_deref(), Some("main"));
+        assert_eq!(info.attrs, vec![
+            ("data-x".into(), "1".into()),
+            ("data-y".into(), "two words".into()),
+            ("hidden".into(), String::new()),
+        ]);
+    }
+
+    #[test]
+    fn line_ranges() {
+        let info = FenceInfo::parse("js{1,x}");
+        assert_eq!(info.highlight_lines, vec![]);
+        assert_eq!(info.attrs, vec![("1,x".into(), String::new())]);
+
+        let info = FenceInfo::parse("js{1, 3-5,9-7}");
+        assert_eq!(info.highlight_lines, vec![1..=1, 3..=5]);
+        assert!(info.is_highlighted(4));
+        assert!(!info.is_highlighted(2));
+    }
+
+    #[test]
+    fn title_and_meta() {
+        let info = FenceInfo::parse("rust title=\"src/main rs\"  showLineNumbers   foo=bar");
+        assert_eq!(info.title.as_deref(), Some("src/main rs"));
+        assert_eq!(info.meta, "showLineNumbers foo=bar");
+        assert!(info.has_flag("showLineNumbers"));
+        assert!(!info.has_flag("show"));
+    }
+
+    #[test]
+    fn no_lang() {
+        let info = FenceInfo::parse("{.foo} title=x");
+        assert_eq!(info.lang, None);
+        assert_eq!(info.classes, vec!["foo"]);
+        assert_eq!(info.title.as_deref(), Some("x"));
+
+        let info = FenceInfo::parse("title=x");
+        assert_eq!(info.lang, None);
+        assert_eq!(info.title.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn unclosed_braces() {
+        let info = FenceInfo::parse("rust {1,2");
+        assert_eq!(info.highlight_lines, vec![]);
+        assert_eq!(info.meta, "{1,2");
+    }
+
+    #[test]
+    fn escapes() {
+        let info = FenceInfo::parse("c\\+\\+ title=&quot;a b&quot;");
+        assert_eq!(info.lang.as_deref(), Some("c++"));
+        assert_eq!(info.title.as_deref(), Some("a b"));
+    }
+
+    #[test]
+    fn render_uses_lang() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let html = md.parse("```rust{1} title=\"x\"\nfoo\n```").render();
+        assert_eq!(html.trim(), "<pre><code class=\"language-rust{1}\">foo\n</code></pre>");
+
+        // line wrappers are opt-in
+        markdown_it::plugins::cmark::block::fence::set_line_wrappers(md, true);
+        let html = md.parse("```rust{1} title=\"x\"\nfoo\n```").render();
+        assert_eq!(html.trim(), "<pre><code class=\"language-rust\"><span class=\"line highlighted\">foo</span>\n</code></pre>");
+
+        // fences that aren't wrapped keep the first word of info string
+        let html = md.parse("```{r}\nfoo\n```").render();
+        assert_eq!(html.trim(), "<pre><code class=\"language-{r}\">foo\n</code></pre>");
+    }
+
+    #[test]
+    fn line_wrappers_disabled() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        let html = md.parse("```diff {2} showLineNumbers\n-a\n+b\n```").render();
+        assert_eq!(html.trim(), "<pre><code class=\"language-diff\">-a\n+b\n</code></pre>");
+    }
+}
+
+mod highlight {
+    use markdown_it::plugins::cmark::block::fence;
+    use markdown_it::plugins::extra::highlight::{self, Highlighter};
+
+    #[derive(Debug)]
+    struct Brackets;
+
+    impl Highlighter for Brackets {
+        fn highlight(&self, lang: Option<&str>, code: &str) -> Option<String> {
+            Some(format!("[{}]{}", lang?, code))
+        }
+    }
+
+    fn run(input: &str, output: &str) {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        fence::set_line_wrappers(md, true);
+        highlight::set_highlighter(md, Brackets);
+        let result = md.parse(input).render();
+        assert_eq!(result.trim(), output);
+    }
+
+    #[test]
+    fn custom_highlighter() {
+        run("```rust\nfoo\n```", "<pre><code class=\"language-rust\">[rust]foo\n</code></pre>");
+        run("```\n<foo>\n```", "<pre><code>&lt;foo&gt;\n</code></pre>");
+        run("    <foo>", "<pre><code>&lt;foo&gt;\n</code></pre>");
+    }
+
+    #[test]
+    fn remove_highlighter() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        highlight::set_highlighter(md, Brackets);
+        highlight::remove_highlighter(md);
+        let result = md.parse("```rust\nfoo\n```").render();
+        assert_eq!(result.trim(), "<pre><code class=\"language-rust\">foo\n</code></pre>");
+    }
+
+    #[derive(Debug)]
+    struct LineBrackets;
+
+    impl Highlighter for LineBrackets {
+        fn highlight(&self, _: Option<&str>, _: &str) -> Option<String> {
+            None
+        }
+
+        fn highlight_lines(&self, lang: Option<&str>, code: &str) -> Option<Vec<String>> {
+            Some(code.lines().map(|line| format!("[{}]{}", lang.unwrap_or_default(), line)).collect())
+        }
+    }
+
+    #[test]
+    fn line_wrappers() {
+        run("```rust {2}\nfoo\n<bar>\n```", "<pre><code class=\"language-rust\">\
+            <span class=\"line\">foo</span>\n\
+            <span class=\"line highlighted\">&lt;bar&gt;</span>\n\
+            </code></pre>");
+
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        fence::set_line_wrappers(md, true);
+        highlight::set_highlighter(md, LineBrackets);
+        let result = md.parse("```rust showLineNumbers\nfoo\n\n```\n```rust\nfoo\n```").render();
+        assert_eq!(result.trim(), "<pre><code class=\"language-rust\">\
+            <span class=\"line\"><span class=\"line-number\">1</span>[rust]foo</span>\n\
+            <span class=\"line\"><span class=\"line-number\">2</span>[rust]</span>\n\
+            </code></pre>\n<pre><code class=\"language-rust\">foo\n</code></pre>");
+    }
+
+    #[test]
+    fn diff() {
+        run("```diff {2}\n-a\n+<b>\n c\n@@ x\n```", "<pre><code class=\"language-diff\">\
+            <span class=\"line removed\">-a</span>\n\
+            <span class=\"line added highlighted\">+&lt;b&gt;</span>\n\
+            <span class=\"line context\"> c</span>\n\
+            <span class=\"line header\">@@ x</span>\n\
+            </code></pre>");
+
+        // file headers are only recognized outside of hunks
+        run("```diff\n--- a\n+++ b\n@@ -1 +1 @@\n--- x\n+++ y\n```", "<pre><code class=\"language-diff\">\
+            <span class=\"line header\">--- a</span>\n\
+            <span class=\"line header\">+++ b</span>\n\
+            <span class=\"line header\">@@ -1 +1 @@</span>\n\
+            <span class=\"line removed\">--- x</span>\n\
+            <span class=\"line added\">+++ y</span>\n\
+            </code></pre>");
+
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        fence::set_line_wrappers(md, true);
+        highlight::set_highlighter(md, LineBrackets);
+        let result = md.parse("```diff-rust\n--- a.rs\n-a\n+<b>\n\n```\n```diff\n-a\n```").render();
+        assert_eq!(result.trim(), "<pre><code class=\"language-diff-rust\">\
+            <span class=\"line header\">--- a.rs</span>\n\
+            <span class=\"line removed\">-[rust]a</span>\n\
+            <span class=\"line added\">+[rust]<b></span>\n\
+            <span class=\"line context\">[rust]</span>\n\
+            </code></pre>\n<pre><code class=\"language-diff\">\
+            <span class=\"line removed\">[diff]-a</span>\n\
+            </code></pre>");
+
+        // without line wrappers diff is highlighted as a whole, markers included
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        highlight::set_highlighter(md, Brackets);
+        let result = md.parse("```diff-rust\n-a\n```").render();
+        assert_eq!(result.trim(), "<pre><code class=\"language-diff-rust\">[diff-rust]-a\n</code></pre>");
+    }
+
+    #[test]
+    #[cfg(feature = "syntect")]
+    fn syntect_keeps_code_fence() {
+        use markdown_it::plugins::cmark::block::fence::CodeFence;
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::extra::syntect::add(md);
+        let ast = md.parse("```rust\nfn main() {}\n```");
+        assert_eq!(ast.children[0].cast::<CodeFence>().unwrap().info, "rust");
+        let html = ast.render();
+        assert!(html.starts_with("<pre style=\"background-color:#ffffff;\"><code class=\"language-rust\"><span style=\""));
+        assert!(html.contains(">main</span>"));
+    }
+}
+
+#[cfg(feature = "syntect")]
+mod syntect {
+    use markdown_it::plugins::cmark::block::fence;
+    use markdown_it::plugins::extra::syntect;
+    use std::sync::Arc;
+
+    fn render(input: &str, f: fn (&mut markdown_it::MarkdownIt)) -> String {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        f(md);
+        md.parse(input).render()
+    }
+
+    #[test]
+    fn default_sets_are_shared() {
+        assert!(Arc::ptr_eq(&syntect::default_syntax_set(), &syntect::default_syntax_set()));
+        assert!(Arc::ptr_eq(&syntect::default_theme_set(), &syntect::default_theme_set()));
+    }
+
+    #[test]
+    fn custom_syntax_set() {
+        let html = render("```rust\nfn main() {}\n```", |md| {
+            let mut builder = ::syntect::parsing::SyntaxSetBuilder::new();
+            builder.add_plain_text_syntax();
+            syntect::set_syntax_set(md, Arc::new(builder.build()));
+            syntect::add(md);
+        });
+        assert!(!html.contains(">main</span>"));
+    }
+
+    #[test]
+    fn set_theme_before_and_after_add() {
+        let dark = "<pre style=\"background-color:#2b303b;\">";
+        let html = render("```\nfoo\n```", |md| {
+            syntect::set_theme(md, "base16-ocean.dark").unwrap();
+            syntect::add(md);
+        });
+        assert!(html.starts_with(dark));
+        let html = render("```\nfoo\n```", |md| {
+            syntect::add(md);
+            syntect::set_theme(md, "base16-ocean.dark").unwrap();
+        });
+        assert!(html.starts_with(dark));
+    }
+
+    #[test]
+    fn class_style() {
+        let html = render("```rust\nfn main() {}\n```", |md| {
+            syntect::add(md);
+            syntect::set_class_style(md, ::syntect::html::ClassStyle::SpacedPrefixed { prefix: "hl-" });
+        });
+        assert!(html.starts_with(r#"<pre class="hl-code"><code class="language-rust"><span class="hl-source hl-rust">"#));
+        assert!(!html.contains("style="));
+    }
+
+    #[test]
+    fn stylesheet() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        syntect::set_class_style(md, ::syntect::html::ClassStyle::SpacedPrefixed { prefix: "hl-" });
+        let css = syntect::stylesheet(md, "InspiredGitHub", None).unwrap();
+        assert!(css.contains(".hl-code {"));
+        assert!(!css.contains("@media"));
+        let css = syntect::stylesheet(md, "InspiredGitHub", Some("base16-ocean.dark")).unwrap();
+        assert!(css.contains("@media (prefers-color-scheme: dark) {\n  /*"));
+        assert!(syntect::stylesheet(md, "InspiredGitHub", Some("unknown")).is_none());
+        assert!(syntect::stylesheet(md, "unknown", None).is_none());
+    }
+
+    #[test]
+    fn line_wrappers() {
+        let input = "```rust {2}\n/* a\nb */ fn x() {}\n```";
+
+        let html = render(input, |md| {
+            syntect::add(md);
+            fence::set_line_wrappers(md, true);
+        });
+        assert!(html.starts_with("<pre style=\"background-color:#ffffff;\"><code class=\"language-rust\"><span class=\"line\"><span style=\""));
+        assert!(html.contains("</span></span>\n<span class=\"line highlighted\"><span style=\""));
+        assert!(!html.contains("\n</span>"));
+
+        let html = render(input, |md| {
+            syntect::add(md);
+            fence::set_line_wrappers(md, true);
+            syntect::set_class_style(md, ::syntect::html::ClassStyle::Spaced);
+        });
+        assert!(html.starts_with("<pre class=\"code\"><code class=\"language-rust\">\
+            <span class=\"line\"><span class=\"source rust\"><span class=\"comment block rust\">\
+            <span class=\"punctuation definition comment rust\">/*</span> a</span></span></span>\n\
+            <span class=\"line highlighted\"><span class=\"source rust\"><span class=\"comment block rust\">b \
+            <span class=\"punctuation definition comment rust\">*/</span></span> "));
+        for line in html.lines().skip(1) {
+            assert_eq!(line.matches("<span").count(), line.matches("</span>").count());
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let html = render("```diff-rust\n-fn a() {}\n+fn b() {}\n```", |md| {
+            syntect::add(md);
+            fence::set_line_wrappers(md, true);
+            syntect::set_class_style(md, ::syntect::html::ClassStyle::Spaced);
+        });
+        assert!(html.starts_with("<pre class=\"code\"><code class=\"language-diff-rust\">\
+            <span class=\"line removed\">-<span class=\"source rust\"><span class=\"meta function rust\">"));
+        assert!(html.contains("<span class=\"line added\">+<span class=\"source rust\">"));
+    }
+
+    #[test]
+    fn unknown_theme() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        let err = syntect::set_theme(md, "unknown").unwrap_err();
+        assert_eq!(err.to_string(), r#"unknown theme: "unknown""#);
+    }
+
+    #[test]
+    fn theme_from_registered_highlighter() {
+        use ::syntect::highlighting::ThemeSet;
+        use markdown_it::plugins::extra::highlight;
+
+        // theme set is only known to the highlighter, not to plugin settings
+        let toy = ThemeSet::get_theme("tests/fixtures/syntect/toy.tmTheme").unwrap();
+        let theme_set = ThemeSet { themes: [("toy".to_owned(), toy)].into_iter().collect() };
+        let highlighter = syntect::SyntectHighlighter::default().with_theme_set(Arc::new(theme_set));
+
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        highlight::set_highlighter(md, highlighter);
+        syntect::set_theme(md, "toy").unwrap();
+        assert!(syntect::set_theme(md, "InspiredGitHub").is_err());
+
+        let html = md.parse("```\nfrob\n```").render();
+        assert!(html.starts_with("<pre style=\"background-color:#123456;\">"));
+    }
+
+    #[test]
+    fn load_from_folder() {
+        let html = render("```toy\nfrob\n```", |md| {
+            syntect::add(md);
+            syntect::load_syntaxes_from_folder(md, "tests/fixtures/syntect").unwrap();
+            syntect::load_themes_from_folder(md, "tests/fixtures/syntect").unwrap();
+            syntect::set_theme(md, "toy").unwrap();
+        });
+        assert_eq!(html, "<pre style=\"background-color:#123456;\"><code class=\"language-toy\">\
+            <span style=\"color:#ff0000;\">frob\n</span></code></pre>\n");
+
+        // default syntaxes and themes are still there
+        let html = render("```rust\nfn main() {}\n```", |md| {
+            syntect::load_syntaxes_from_folder(md, "tests/fixtures/syntect").unwrap();
+            syntect::load_themes_from_folder(md, "tests/fixtures/syntect").unwrap();
+            syntect::set_theme(md, "base16-ocean.dark").unwrap();
+            syntect::add(md);
+        });
+        assert!(html.contains(">main</span>"));
+    }
+
+    #[test]
+    fn load_from_missing_folder() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        assert!(syntect::load_syntaxes_from_folder(md, "tests/fixtures/nonexistent").is_err());
+        assert!(syntect::load_themes_from_folder(md, "tests/fixtures/nonexistent").is_err());
+    }
+}
+
+#[cfg(feature = "tree-sitter")]
+mod tree_sitter {
+    use markdown_it::plugins::cmark::block::fence;
+    use markdown_it::plugins::extra::tree_sitter;
+
+    fn render(input: &str, f: fn (&mut markdown_it::MarkdownIt)) -> String {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        f(md);
+        md.parse(input).render()
+    }
+
+    #[test]
+    fn bundled_languages() {
+        let html = render("```ts\nlet x: number = 1;\n```", tree_sitter::add);
+        assert!(html.contains("<span class=\"keyword\">let</span>"));
+        assert!(html.contains("<span class=\"type builtin\">number</span>"));
+
+        let html = render("```py\nprint('<x>')\n```", tree_sitter::add);
+        assert!(html.contains("<span class=\"function builtin\">print</span>"));
+        assert!(html.contains("<span class=\"string\">&#39;&lt;x&gt;&#39;</span>"));
+    }
+
+    #[test]
+    fn unknown_language() {
+        let html = render("```cobol\n<x>\n```\n```\n<y>\n```", tree_sitter::add);
+        assert_eq!(html, "<pre><code class=\"language-cobol\">&lt;x&gt;\n</code></pre>\n<pre><code>&lt;y&gt;\n</code></pre>\n");
+    }
+
+    #[test]
+    fn line_wrappers() {
+        let html = render("```js {2}\nlet x = `a\nb`;\n```", |md| {
+            tree_sitter::add(md);
+            fence::set_line_wrappers(md, true);
+        });
+        assert_eq!(html, "<pre><code class=\"language-js\">\
+            <span class=\"line\"><span class=\"keyword\">let</span> <span class=\"variable\">x</span> \
+            <span class=\"operator\">=</span> <span class=\"string\">`a</span></span>\n\
+            <span class=\"line highlighted\"><span class=\"string\">b`</span>\
+            <span class=\"punctuation delimiter\">;</span></span>\n\
+            </code></pre>\n");
+    }
+
+    #[test]
+    fn add_language() {
+        let html = render("```myrust\nfn x() {}\n```", |md| {
+            tree_sitter::add_language(md, &["myrust"], ::tree_sitter_rust::language(), "\"fn\" @keyword", "", "").unwrap();
+            tree_sitter::add(md);
+        });
+        assert_eq!(html, "<pre><code class=\"language-myrust\"><span class=\"keyword\">fn</span> x() {}\n</code></pre>\n");
+
+        let html = render("```myrust\nfn x() {}\n```", |md| {
+            tree_sitter::add(md);
+            tree_sitter::add_language(md, &["myrust"], ::tree_sitter_rust::language(), "\"fn\" @keyword", "", "").unwrap();
+        });
+        assert!(html.contains("<span class=\"keyword\">fn</span>"));
+    }
+
+    #[test]
+    fn invalid_query() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        let result = tree_sitter::add_language(md, &["x"], ::tree_sitter_rust::language(), "(nonexistent_node) @keyword", "", "");
+        assert!(result.is_err());
+    }
+}
+
+mod plain_text {
+    use super::parse;
+    use markdown_it::renderers::plain::{self, PlainTextOptions, PlainTextRenderer};
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(plain::render(&parse(input)), output);
+    }
+
+    #[test]
+    fn empty() {
+        run("", "");
+    }
+
+    #[test]
+    fn paragraphs_and_headings() {
+        run("Title\n-----\n\nfoo *bar*\nbaz  \nquux\n\n### Sub `code`\n\n***",
+            "Title\n-----\n\nfoo bar\nbaz\nquux\n\nSub code\n\n* * *\n");
+    }
+
+    #[test]
+    fn links() {
+        let input = "[text](https://example.org) <https://auto.org> ![alt *text*](img.png)";
+        run(input, "text https://auto.org alt text\n");
+
+        let fmt = PlainTextRenderer::new(PlainTextOptions { link_urls: true, ..Default::default() });
+        assert_eq!(fmt.render(&parse(input)), "text [https://example.org] https://auto.org alt text\n");
+    }
+
+    #[test]
+    fn tight_lists() {
+        run("- a\n- b\n  1. c\n  2. d\n- e", "- a\n- b\n  1. c\n  2. d\n- e\n");
+    }
+
+    #[test]
+    fn loose_lists() {
+        run("9. a\n\n   b\n10. c\n\n-\n", "9. a\n\n   b\n\n10. c\n\n-\n");
+    }
+
+    #[test]
+    fn custom_bullet() {
+        let fmt = PlainTextRenderer::new(PlainTextOptions { bullet: "*", ..Default::default() });
+        assert_eq!(fmt.render(&parse("- a\n- b")), "* a\n* b\n");
+    }
+
+    #[test]
+    fn blockquotes() {
+        run("> a\n>\n> > b\n\n- > c\n  > d", "> a\n>\n> > b\n\n- > c\n  > d\n");
+    }
+
+    #[test]
+    fn code_blocks() {
+        run("text\n\n```rust\nfn main() {\n\n}\n```\n\n    indented", "text\n\n    fn main() {\n\n    }\n\n    indented\n");
+    }
+
+    #[test]
+    fn html_is_dropped() {
+        run("<div>\nblock\n</div>\n\ninline <b>html</b>", "inline html\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | long header | c |\n|:-|:-:|--:|\n| 1 | x | 333 |\n| 22 |",
+            "a  | long header |   c\n---+-------------+----\n1  |      x      | 333\n22 |             |\n");
+    }
+
+    #[test]
+    fn wide_characters() {
+        run("| 日本語 | b |\n|--|--|\n| 😀 | c |", "日本語 | b\n-------+--\n😀     | c\n");
+        run("日本\n==", "日本\n====\n");
+    }
+
+    #[test]
+    fn custom_handler() {
+        use markdown_it::plugins::cmark::block::code::CodeBlock;
+        let mut fmt = PlainTextRenderer::new(PlainTextOptions::default());
+        fmt.set_handler::<CodeBlock>(|_, fmt| {
+            fmt.block();
+            fmt.text("[code omitted]");
+        });
+        assert_eq!(fmt.render(&parse("a\n\n    code\n\nb")), "a\n\n[code omitted]\n\nb\n");
+    }
+}
+
+mod ansi {
+    use super::parse;
+    use markdown_it::renderers::ansi::{self, AnsiOptions, AnsiRenderer};
+
+    fn run(input: &str, width: usize, output: &str) {
+        let fmt = AnsiRenderer::new(AnsiOptions { width, ..Default::default() });
+        assert_eq!(fmt.render(&parse(input)), output);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(ansi::render(&parse("")), "");
+    }
+
+    #[test]
+    fn nested_styles_are_restored() {
+        run("**a *b* c** ~~d~~ `e`", 0,
+            "\x1b[1ma \x1b[3mb\x1b[0m\x1b[1m c\x1b[0m \x1b[9md\x1b[0m \x1b[33me\x1b[0m\n");
+    }
+
+    #[test]
+    fn styles_are_reset_at_line_end() {
+        run("*one two three*", 10, "\x1b[3mone two\x1b[0m\n\x1b[3mthree\x1b[0m\n");
+    }
+
+    #[test]
+    fn no_wrapping() {
+        run("one two\nthree", 0, "one two\nthree\n");
+        run("one two\nthree", 20, "one two three\n");
+        run("one  \ntwo", 20, "one\ntwo\n");
+    }
+
+    #[test]
+    fn wide_characters() {
+        run("日本語 日本語 日本", 14, "日本語 日本語\n日本\n");
+        run("| 日本語 | b |\n|---|---|\n| 😀 | c |", 80,
+            "\x1b[2m┌────────┬───┐\x1b[0m\n\
+             \x1b[2m│\x1b[0m \x1b[1m日本語\x1b[0m \x1b[2m│\x1b[0m \x1b[1mb\x1b[0m \x1b[2m│\x1b[0m\n\
+             \x1b[2m├────────┼───┤\x1b[0m\n\
+             \x1b[2m│\x1b[0m 😀     \x1b[2m│\x1b[0m c \x1b[2m│\x1b[0m\n\
+             \x1b[2m└────────┴───┘\x1b[0m\n");
+    }
+
+    #[test]
+    fn headings() {
+        run("# a\n## b\n### c", 80, "\x1b[1;4;35ma\x1b[0m\n\n\x1b[1;35mb\x1b[0m\n\n\x1b[1;36mc\x1b[0m\n");
+    }
+
+    #[test]
+    fn links() {
+        run("[a](http://x) <http://y>", 80,
+            "\x1b[4;34ma\x1b[0m\x1b[2m (http://x)\x1b[0m \x1b[4;34mhttp://y\x1b[0m\n");
+
+        let fmt = AnsiRenderer::new(AnsiOptions { link_urls: false, ..Default::default() });
+        assert_eq!(fmt.render(&parse("[a](http://x)")), "\x1b[4;34ma\x1b[0m\n");
+    }
+
+    #[test]
+    fn lists_and_quotes_wrap_with_prefix() {
+        run("- aaa bbb ccc ddd eee fff ggg hhh iii jjj kkk\n  > lll mmm nnn ooo ppp qqq rrr sss", 24,
+            "• aaa bbb ccc ddd eee\n  fff ggg hhh iii jjj\n  kkk\n  \x1b[2m│\x1b[0m lll mmm nnn ooo ppp\n  \x1b[2m│\x1b[0m qqq rrr sss\n");
+        run("1. a\n\n   b\n2. c", 80, "1. a\n\n   b\n\n2. c\n");
+    }
+
+    #[test]
+    fn code_blocks() {
+        run("```\nfn x() {}\n```\n\n    indented", 80,
+            "\x1b[2m┌───────────\x1b[0m\n\x1b[2m│\x1b[0m fn x() {}\x1b[0m\n\x1b[2m└───────────\x1b[0m\n\n\
+             \x1b[2m┌──────────\x1b[0m\n\x1b[2m│\x1b[0m indented\x1b[0m\n\x1b[2m└──────────\x1b[0m\n");
+    }
+
+    #[cfg(feature = "syntect")]
+    #[test]
+    fn code_blocks_highlighted() {
+        let text = ansi::render(&parse("```rust\nfn x() {}\n```"));
+        assert!(text.starts_with("\x1b[2m┌─ rust ─"));
+        assert!(text.contains("\x1b[38;2;"));
+
+        let fmt = AnsiRenderer::new(AnsiOptions { theme: None, ..Default::default() });
+        assert!(!fmt.render(&parse("```rust\nfn x() {}\n```")).contains("\x1b[38;2;"));
+
+        let text = ansi::render(&parse("```rust\nlet x = \"\x1b]0;title\x07\x1b[2J\";\n```"));
+        assert!(text.contains("]0;title"));
+        assert!(!text.contains("\x1b]") && !text.contains('\x07') && !text.contains("\x1b[2J"));
+    }
+
+    #[cfg(feature = "syntect")]
+    #[test]
+    fn code_blocks_custom_syntax() {
+        use markdown_it::plugins::extra::syntect;
+
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        syntect::load_syntaxes_from_folder(md, "tests/fixtures/syntect").unwrap();
+        syntect::load_themes_from_folder(md, "tests/fixtures/syntect").unwrap();
+        let ast = md.parse("```toy\nfrob\n```");
+
+        // toy syntax isn't in the default set
+        assert!(!ansi::render(&ast).contains("\x1b[38;2;"));
+
+        let fmt = AnsiRenderer::new(AnsiOptions {
+            theme: Some("toy".to_owned()),
+            syntax_set: Some(syntect::syntax_set(md)),
+            theme_set: Some(syntect::theme_set(md)),
+            ..Default::default()
+        });
+        assert!(fmt.render(&ast).contains("\x1b[38;2;255;0;0mfrob"));
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | b |\n|---|--:|\n| *x* | 10 |", 80,
+            "\x1b[2m┌───┬────┐\x1b[0m\n\
+             \x1b[2m│\x1b[0m \x1b[1ma\x1b[0m \x1b[2m│\x1b[0m  \x1b[1mb\x1b[0m \x1b[2m│\x1b[0m\n\
+             \x1b[2m├───┼────┤\x1b[0m\n\
+             \x1b[2m│\x1b[0m \x1b[3mx\x1b[0m \x1b[2m│\x1b[0m 10 \x1b[2m│\x1b[0m\n\
+             \x1b[2m└───┴────┘\x1b[0m\n");
+    }
+
+    #[test]
+    fn control_characters_are_removed() {
+        run("a\x1b[31mb", 80, "a[31mb\n");
+        run("```\x1b[2J\na\x1b]0;b\x07\n```", 80,
+            "\x1b[2m┌─ [2J ─\x1b[0m\n\x1b[2m│\x1b[0m a]0;b\x1b[0m\n\x1b[2m└───────\x1b[0m\n");
+    }
+}
+
+mod latex {
+    use super::parse;
+    use markdown_it::renderers::latex::{self, LatexError, LatexOptions, LatexRenderer};
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(latex::render(&parse(input)).unwrap(), output);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(latex::escape(r"\{}$&%#_~^<>|"),
+            r"\textbackslash{}\{\}\$\&\%\#\_\textasciitilde{}\textasciicircum{}\textless{}\textgreater{}\textbar{}");
+        run("a_b `$x$` ~~c~~ **d**", "a\\_b \\texttt{\\$x\\$} \\sout{c} \\textbf{d}\n");
+    }
+
+    #[test]
+    fn headings() {
+        run("# a\n## b\n### c\n#### d\n###### e",
+            "\\section{a}\n\n\\subsection{b}\n\n\\subsubsection{c}\n\n\\paragraph{d}\n\n\\subparagraph{e}\n");
+
+        let fmt = LatexRenderer::new(LatexOptions {
+            sections: ["chapter", "section", "subsection", "subsubsection", "paragraph", "subparagraph"],
+            ..Default::default()
+        });
+        assert_eq!(fmt.render(&parse("# a")).unwrap(), "\\chapter{a}\n");
+    }
+
+    #[test]
+    fn links_and_images() {
+        run("[a](http://x/#y%20) <http://z> ![alt](img.png)",
+            "\\href{http://x/\\#y\\%20}{a} \\url{http://z} \\includegraphics{img.png}\n");
+    }
+
+    #[test]
+    fn breaks() {
+        run("a\nb  \nc", "a\nb\\\\{}\nc\n");
+        run("a\\\n[b]", "a\\\\{}\n[b]\n");
+    }
+
+    #[test]
+    fn lists() {
+        run("- a\n- b\n  1. c\n  2. d",
+            "\\begin{itemize}\n\\item{} a\n\\item{} b\n\\begin{enumerate}\n\\item{} c\n\\item{} d\n\\end{enumerate}\n\\end{itemize}\n");
+        run("3. a\n\n   b\n4. c",
+            "\\begin{enumerate}\n\\item[3.] a\n\nb\n\n\\item[4.] c\n\\end{enumerate}\n");
+        run("- [x] done", "\\begin{itemize}\n\\item{} [x] done\n\\end{itemize}\n");
+    }
+
+    #[test]
+    fn blockquote_and_hr() {
+        run("> a\n>\n> b\n\n***",
+            "\\begin{quote}\na\n\nb\n\\end{quote}\n\n\\begin{center}\\rule{0.5\\linewidth}{0.4pt}\\end{center}\n");
+    }
+
+    #[test]
+    fn code() {
+        run("```rust\nlet x = \"$\";\n```", "\\begin{verbatim}\nlet x = \"$\";\n\\end{verbatim}\n");
+
+        let fmt = LatexRenderer::new(LatexOptions { listings: true, ..Default::default() });
+        assert_eq!(fmt.render(&parse("```rust\nx\n```\n\n    y")).unwrap(),
+            "\\begin{lstlisting}[language=rust]\nx\n\\end{lstlisting}\n\n\\begin{lstlisting}\ny\n\\end{lstlisting}\n");
+        let fmt = LatexRenderer::new(LatexOptions { listings: true, ..Default::default() });
+        assert_eq!(fmt.render(&parse("```c,escapechar=!\nx\n```")).unwrap(),
+            "\\begin{lstlisting}\nx\n\\end{lstlisting}\n");
+    }
+
+    #[test]
+    fn code_with_environment_end() {
+        run("```\n  a\\end {verbatim}\n\\input{/etc/passwd}\n```",
+            "\\begin{flushleft}\n\\texttt{~~a\\textbackslash{}end~\\{verbatim\\}}\\\\\n\
+             \\texttt{\\textbackslash{}input\\{/etc/passwd\\}}\n\\end{flushleft}\n");
+        run("    \\end{lstlisting}", "\\begin{verbatim}\n\\end{lstlisting}\n\\end{verbatim}\n");
+
+        let fmt = LatexRenderer::new(LatexOptions { listings: true, ..Default::default() });
+        assert_eq!(fmt.render(&parse("    \\end{lstlisting}")).unwrap(),
+            "\\begin{flushleft}\n\\texttt{\\textbackslash{}end\\{lstlisting\\}}\n\\end{flushleft}\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | b | c |\n|:-|:-:|-:|\n| 1 | *2* | 3 |",
+            "\\begin{tabular}{lcr}\n\\hline\n\\textbf{a} & \\textbf{b} & \\textbf{c} \\\\\n\\hline\n\
+             1 & \\emph{2} & 3 \\\\\n\\hline\n\\end{tabular}\n");
+    }
+
+    #[test]
+    fn html_is_commented_out() {
+        run("<div>\nx\n</div>\n\na <b>b</b>", "% <div>\n% x\n% </div>\n\na b\n");
+        run("<div>\rx\\input{y}\n</div>", "% <div>\n% x\\input{y}\n% </div>\n");
+    }
+
+    #[test]
+    fn standalone() {
+        let fmt = LatexRenderer::new(LatexOptions { standalone: true, ..Default::default() });
+        let result = fmt.render(&parse("a")).unwrap();
+        assert!(result.starts_with("\\documentclass{article}\n"));
+        assert!(result.ends_with("\\begin{document}\na\n\\end{document}\n"));
+    }
+
+    #[derive(Debug)]
+    struct Custom;
+    impl markdown_it::NodeValue for Custom {}
+
+    #[test]
+    fn unsupported_node() {
+        let mut ast = parse("a\n\nb");
+        ast.children[1].replace(Custom);
+
+        let err = latex::render(&ast).unwrap_err();
+        let LatexError::UnsupportedNode { name, srcmap } = &err;
+        assert_eq!(*name, "extras::latex::Custom");
+        assert_eq!(srcmap.unwrap().get_byte_offsets(), (3, 4));
+        assert_eq!(err.to_string(), "node extras::latex::Custom can't be rendered to LaTeX (at bytes 3..4)");
+
+        let mut fmt = LatexRenderer::new(LatexOptions::default());
+        fmt.set_handler::<Custom>(|_, fmt| {
+            fmt.block();
+            fmt.write("\\custom");
+            Ok(())
+        });
+        assert_eq!(fmt.render(&ast).unwrap(), "a\n\n\\custom\n");
+    }
+}
+
+mod roff {
+    use super::parse;
+    use markdown_it::renderers::roff::{self, RoffOptions, RoffRenderer};
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(roff::render(&parse(input)), output);
+    }
+
+    #[test]
+    fn title() {
+        let fmt = RoffRenderer::new(RoffOptions {
+            title: Some("my-tool".into()),
+            section: "8".into(),
+            date: Some("2024-01-01".into()),
+        });
+        assert_eq!(fmt.render(&parse("")), ".TH \"MY\\-TOOL\" \"8\" \"2024\\-01\\-01\"\n");
+    }
+
+    #[test]
+    fn escaping() {
+        run("a\\\\b --opt\n.dot\n'quote\n  . not a dot", ".PP\na\\eb \\-\\-opt\n\\&.dot\n\\&'quote\n\\&. not a dot\n");
+    }
+
+    #[test]
+    fn headings() {
+        run("# NAME\n\nfoo\n\n## Sub *section*\n\n### Third",
+            ".SH \"NAME\"\n.PP\nfoo\n.SS \"Sub \\f(BIsection\\fP\"\n.PP\n\\fBThird\\fP\n");
+        run("# say \"hi\"\n\n## a \"b\" c", ".SH \"say \\(dqhi\\(dq\"\n.SS \"a \\(dqb\\(dq c\"\n");
+    }
+
+    #[test]
+    fn fonts_in_headings() {
+        // fonts inside of headings are restored to bold, not to regular
+        run("# run `cmd` now\n\n## *a `b` c*",
+            ".SH \"run \\fBcmd\\fP now\"\n.SS \"\\f(BIa \\f(BIb\\fP c\\fB\"\n");
+    }
+
+    #[test]
+    fn fonts() {
+        run("*a **b** c* `d` a  \nb", ".PP\n\\fIa \\f(BIb\\fP c\\fR \\fBd\\fP a\n.br\nb\n");
+    }
+
+    #[test]
+    fn links() {
+        run("[site](http://x)", ".PP\nsite <\\fIhttp://x\\fP>\n");
+    }
+
+    #[test]
+    fn lists() {
+        run("- a\n- b\n  1. c\n- d",
+            ".IP \\(bu 2\na\n.IP \\(bu 2\nb\n.RS\n.IP 1. 3\nc\n.RE\n.IP \\(bu 2\nd\n");
+        run("9. a\n\n   b\n10. c",
+            ".IP 9. 4\na\n.IP\nb\n.IP 10. 4\nc\n");
+    }
+
+    #[test]
+    fn tagged_paragraphs() {
+        run("- **-f**, **--force**\n  Overwrite files.\n\n  More text.\n- `-q`\n  Quiet.\n- not *a tag*\n  text",
+            ".TP\n\\fB\\-f\\fP, \\fB\\-\\-force\\fP\nOverwrite files.\n.IP\nMore text.\n\
+             .TP\n\\fB\\-q\\fP\nQuiet.\n.IP \\(bu 2\nnot \\fIa tag\\fP\ntext\n");
+    }
+
+    #[test]
+    fn code_and_quotes() {
+        run("```\n.x\n  a\\b\n```\n\n> q",
+            ".PP\n.RS 4\n.nf\n\\&.x\n  a\\eb\n.fi\n.RE\n.RS 4\n.PP\nq\n.RE\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | b |\n|:-:|-:|\n| 1 | 2 |", ".PP\n.TS\nbox;\ncb rb\nc r.\na\tb\n1\t2\n.TE\n");
+    }
+
+    #[test]
+    fn html_is_dropped() {
+        run("<div>\n</div>\n\na <b>b</b>", ".PP\na b\n");
+    }
+}
+
+mod gemtext {
+    use super::parse;
+    use markdown_it::renderers::gemtext;
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(gemtext::render(&parse(input)), output);
+    }
+
+    #[test]
+    fn empty() {
+        run("", "");
+    }
+
+    #[test]
+    fn headings() {
+        run("# a\n## b\n### c\n###### d", "# a\n\n## b\n\n### c\n\n### d\n");
+    }
+
+    #[test]
+    fn paragraphs() {
+        run("a *b*\nc  \nd\n\n<b>e</b>", "a b c\nd\n\ne\n");
+    }
+
+    #[test]
+    fn line_markup_in_text() {
+        run("\\=> a\n\n\\# b\n\n\\* c\n\n\\> d  \n`` ``` `` e\n\n- \\# f", " => a\n\n # b\n\n * c\n\n > d\n ``` e\n\n* # f\n");
+        run("> \\> a", ">  > a\n");
+    }
+
+    #[test]
+    fn preformatted_toggle_in_code() {
+        run("~~~\n```\na\n ```\n~~~", "```\n ```\na\n ```\n```\n");
+    }
+
+    #[test]
+    fn links() {
+        run("[a](http://a) ![b](b.png) <http://c>\n\n[d](http://d)",
+            "a b http://c\n=> http://a a\n=> b.png b\n=> http://c\n\nd\n=> http://d d\n");
+        run("see [a  \nb](http://x)", "see a\nb\n=> http://x a b\n");
+    }
+
+    #[test]
+    fn lists() {
+        run("1. a [x](http://x)\n\n   b\n2. c\n   - d\n\n     e\n\nf",
+            "* a x b\n* c\n* d e\n=> http://x x\n\nf\n");
+    }
+
+    #[test]
+    fn blockquotes() {
+        run("> a\n>\n> - b\n> ```\n> c\n> ```", "> a\n> * b\n```\nc\n```\n");
+    }
+
+    #[test]
+    fn preformatted() {
+        run("```rust title=\"x\"\nfn x() {}\n```\n\n    indented",
+            "```rust x\nfn x() {}\n```\n\n```\nindented\n```\n");
+        run("```rust&amp;x\ny\n```", "```rust&x\ny\n```\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | [b](http://b) |\n|---|--:|\n| 10 | 2 |",
+            "```\na  | b\n---+--\n10 | 2\n```\n=> http://b b\n");
+    }
+}
+
+mod events {
+    use super::parse;
+    use markdown_it::plugins::extra::tables::ColumnAlignment;
+    use markdown_it::renderers::events::{events, CodeBlockKind, CustomNode, Event, Tag};
+
+    #[test]
+    fn inline() {
+        let ast = parse("[a](u \"t\") `b` ~~c~~ <i>\n![d](v)<http://e>  \n**f**");
+        assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Link { url: "u", title: Some("t") }),
+            Event::Text("a".into()),
+            Event::End(Tag::Link { url: "u", title: Some("t") }),
+            Event::Text(" ".into()),
+            Event::Code("b".into()),
+            Event::Text(" ".into()),
+            Event::Start(Tag::Strikethrough),
+            Event::Text("c".into()),
+            Event::End(Tag::Strikethrough),
+            Event::Text(" ".into()),
+            Event::InlineHtml("<i>"),
+            Event::SoftBreak,
+            Event::Start(Tag::Image { url: "v", title: None }),
+            Event::Text("d".into()),
+            Event::End(Tag::Image { url: "v", title: None }),
+            Event::Start(Tag::Link { url: "http://e", title: None }),
+            Event::Text("http://e".into()),
+            Event::End(Tag::Link { url: "http://e", title: None }),
+            Event::HardBreak,
+            Event::Start(Tag::Strong),
+            Event::Text("f".into()),
+            Event::End(Tag::Strong),
+            Event::End(Tag::Paragraph),
+        ]);
+    }
+
+    #[test]
+    fn blocks() {
+        let ast = parse("# a\n\n> b\n\n---\n\n```rust\nc\n```\n\n    d\n\n<div>\n");
+        assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::Heading(1)),
+            Event::Text("a".into()),
+            Event::End(Tag::Heading(1)),
+            Event::Start(Tag::BlockQuote),
+            Event::Start(Tag::Paragraph),
+            Event::Text("b".into()),
+            Event::End(Tag::Paragraph),
+            Event::End(Tag::BlockQuote),
+            Event::Rule,
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced("rust"))),
+            Event::Text("c\n".into()),
+            Event::End(Tag::CodeBlock(CodeBlockKind::Fenced("rust"))),
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)),
+            Event::Text("d\n".into()),
+            Event::End(Tag::CodeBlock(CodeBlockKind::Indented)),
+            Event::Html("<div>\n"),
+        ]);
+    }
+
+    #[test]
+    fn lists() {
+        let ast = parse("3. a\n4. b\n   - c");
+        assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::List(Some(3))),
+            Event::Start(Tag::Item),
+            Event::Text("a".into()),
+            Event::End(Tag::Item),
+            Event::Start(Tag::Item),
+            Event::Text("b".into()),
+            Event::Start(Tag::List(None)),
+            Event::Start(Tag::Item),
+            Event::Text("c".into()),
+            Event::End(Tag::Item),
+            Event::End(Tag::List(None)),
+            Event::End(Tag::Item),
+            Event::End(Tag::List(Some(3))),
+        ]);
+    }
+
+    #[test]
+    fn tables() {
+        let ast = parse("| a | b |\n|:-|-:|\n| c | d |");
+        let alignments = &[ColumnAlignment::Left, ColumnAlignment::Right][..];
+        assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::Table(alignments)),
+            Event::Start(Tag::TableHead),
+            Event::Start(Tag::TableCell),
+            Event::Text("a".into()),
+            Event::End(Tag::TableCell),
+            Event::Start(Tag::TableCell),
+            Event::Text("b".into()),
+            Event::End(Tag::TableCell),
+            Event::End(Tag::TableHead),
+            Event::Start(Tag::TableRow),
+            Event::Start(Tag::TableCell),
+            Event::Text("c".into()),
+            Event::End(Tag::TableCell),
+            Event::Start(Tag::TableCell),
+            Event::Text("d".into()),
+            Event::End(Tag::TableCell),
+            Event::End(Tag::TableRow),
+            Event::End(Tag::Table(alignments)),
+        ]);
+    }
+
+    #[derive(Debug)]
+    struct Custom;
+    impl markdown_it::NodeValue for Custom {}
+
+    #[test]
+    fn custom_nodes() {
+        let mut ast = parse("*a*");
+        ast.children[0].children[0].replace(Custom);
+        let custom = &ast.children[0].children[0];
+
+        assert_eq!(events(&ast).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::Paragraph),
+            Event::Start(Tag::Custom(CustomNode(custom))),
+            Event::Text("a".into()),
+            Event::End(Tag::Custom(CustomNode(custom))),
+            Event::End(Tag::Paragraph),
+        ]);
+        assert_eq!(format!("{:?}", CustomNode(custom)), "CustomNode(\"extras::events::Custom\")");
+    }
+
+    #[test]
+    fn subtree() {
+        let ast = parse("a\n\n*b*");
+        assert_eq!(events(&ast.children[1].children[0]).collect::<Vec<_>>(), vec![
+            Event::Start(Tag::Emphasis),
+            Event::Text("b".into()),
+            Event::End(Tag::Emphasis),
+        ]);
+    }
+}
+
+#[cfg(feature = "json")]
+mod mdast {
+    use super::parse;
+    use markdown_it::renderers::mdast::{to_mdast, MdastExporter};
+    use serde_json::{json, Value};
+
+    // drop positions to keep expected values short
+    fn strip(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.remove("position");
+                object.values_mut().for_each(strip);
+            }
+            Value::Array(array) => array.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+
+    fn run(input: &str, output: Value) {
+        let mut result = to_mdast(&parse(input), input);
+        strip(&mut result);
+        assert_eq!(result["children"], output);
+    }
+
+    #[test]
+    fn inline() {
+        run("*a* **b** ~~c~~ `d`<i>\ne  \n[f](u \"t\") ![g](v)", json!([{
+            "type": "paragraph",
+            "children": [
+                { "type": "emphasis", "children": [{ "type": "text", "value": "a" }] },
+                { "type": "text", "value": " " },
+                { "type": "strong", "children": [{ "type": "text", "value": "b" }] },
+                { "type": "text", "value": " " },
+                { "type": "delete", "children": [{ "type": "text", "value": "c" }] },
+                { "type": "text", "value": " " },
+                { "type": "inlineCode", "value": "d" },
+                { "type": "html", "value": "<i>" },
+                { "type": "text", "value": "\ne" },
+                { "type": "break" },
+                { "type": "link", "url": "u", "title": "t", "children": [{ "type": "text", "value": "f" }] },
+                { "type": "text", "value": " " },
+                { "type": "image", "url": "v", "title": null, "alt": "g" },
+            ],
+        }]));
+    }
+
+    #[test]
+    fn blocks() {
+        run("a\n===\n\n> b\n\n***\n\n```js {1} x\nc\n```\n\n    d\n\n<div>\n", json!([
+            { "type": "heading", "depth": 1, "children": [{ "type": "text", "value": "a" }] },
+            { "type": "blockquote", "children": [
+                { "type": "paragraph", "children": [{ "type": "text", "value": "b" }] },
+            ] },
+            { "type": "thematicBreak" },
+            { "type": "code", "lang": "js", "meta": "{1} x", "value": "c" },
+            { "type": "code", "lang": null, "meta": null, "value": "d" },
+            { "type": "html", "value": "<div>" },
+        ]));
+    }
+
+    #[test]
+    fn code_info_unescaped() {
+        run("```c\\+\\+ a&amp;b \\{x}\n```\n\n~~~&lt;a&gt;\n~~~", json!([
+            { "type": "code", "lang": "c++", "meta": "a&b {x}", "value": "" },
+            { "type": "code", "lang": "<a>", "meta": null, "value": "" },
+        ]));
+    }
+
+    #[test]
+    fn lists() {
+        run("2. a\n3. b\n   - c\n\n     d", json!([{
+            "type": "list", "ordered": true, "start": 2, "spread": false,
+            "children": [
+                { "type": "listItem", "spread": false, "checked": null, "children": [
+                    { "type": "paragraph", "children": [{ "type": "text", "value": "a" }] },
+                ] },
+                { "type": "listItem", "spread": false, "checked": null, "children": [
+                    { "type": "paragraph", "children": [{ "type": "text", "value": "b" }] },
+                    { "type": "list", "ordered": false, "start": null, "spread": true, "children": [
+                        { "type": "listItem", "spread": true, "checked": null, "children": [
+                            { "type": "paragraph", "children": [{ "type": "text", "value": "c" }] },
+                            { "type": "paragraph", "children": [{ "type": "text", "value": "d" }] },
+                        ] },
+                    ] },
+                ] },
+            ],
+        }]));
+    }
+
+    #[test]
+    fn tables() {
+        run("| a | b | c |\n|:-|-:|--|\n| d |", json!([{
+            "type": "table",
+            "align": ["left", "right", null],
+            "children": [
+                { "type": "tableRow", "children": [
+                    { "type": "tableCell", "children": [{ "type": "text", "value": "a" }] },
+                    { "type": "tableCell", "children": [{ "type": "text", "value": "b" }] },
+                    { "type": "tableCell", "children": [{ "type": "text", "value": "c" }] },
+                ] },
+                { "type": "tableRow", "children": [
+                    { "type": "tableCell", "children": [{ "type": "text", "value": "d" }] },
+                    { "type": "tableCell", "children": [] },
+                    { "type": "tableCell", "children": [] },
+                ] },
+            ],
+        }]));
+    }
+
+    #[test]
+    fn positions() {
+        let source = "é\r\n- 😀 *b*";
+        let result = to_mdast(&parse(source), source);
+        let item = &result["children"][1]["children"][0];
+        assert_eq!(item["position"], json!({
+            "start": { "line": 2, "column": 1, "offset": 3 },
+            "end": { "line": 2, "column": 9, "offset": 11 },
+        }));
+        assert_eq!(item["children"][0]["children"][1]["position"], json!({
+            "start": { "line": 2, "column": 6, "offset": 8 },
+            "end": { "line": 2, "column": 9, "offset": 11 },
+        }));
+    }
+
+    #[derive(Debug)]
+    struct Custom;
+    impl markdown_it::NodeValue for Custom {}
+
+    #[test]
+    fn custom_nodes() {
+        let mut ast = parse("*a*");
+        ast.children[0].children[0].replace(Custom);
+
+        let mut result = MdastExporter::new("*a*").export(&ast);
+        strip(&mut result);
+        assert_eq!(result["children"][0]["children"], json!([
+            { "type": "custom", "children": [{ "type": "text", "value": "a" }] },
+        ]));
+
+        let mut fmt = MdastExporter::new("*a*");
+        fmt.set_handler::<Custom>(|node, fmt| json!({ "type": "mark", "children": fmt.children(&node.children) }));
+        let result = fmt.export(&ast);
+        assert_eq!(result["children"][0]["children"][0]["type"], "mark");
+        assert_eq!(result["children"][0]["children"][0]["position"]["end"]["offset"], 3);
+    }
+}
+
+mod tokens {
+    use super::parse;
+    use markdown_it::renderers::tokens::{to_tokens, Token, TokenExporter};
+
+    // type, tag, nesting, level, map for each token
+    type Summary<'a> = (&'a str, &'a str, i8, u32, Option<[usize; 2]>);
+
+    fn summary(tokens: &[Token]) -> Vec<Summary<'_>> {
+        tokens.iter().map(|t| (t.kind.as_str(), t.tag.as_str(), t.nesting, t.level, t.map)).collect()
+    }
+
+    #[test]
+    fn blocks() {
+        let source = "# a\n\n> b\n> c\n\n---\n\n```js x\nd\n```\n\n    e\n\n<div>\n";
+        let tokens = to_tokens(&parse(source), source);
+        assert_eq!(summary(&tokens), vec![
+            ("heading_open", "h1", 1, 0, Some([0, 1])),
+            ("inline", "", 0, 1, Some([0, 1])),
+            ("heading_close", "h1", -1, 0, None),
+            ("blockquote_open", "blockquote", 1, 0, Some([2, 4])),
+            ("paragraph_open", "p", 1, 1, Some([2, 4])),
+            ("inline", "", 0, 2, Some([2, 4])),
+            ("paragraph_close", "p", -1, 1, None),
+            ("blockquote_close", "blockquote", -1, 0, None),
+            ("hr", "hr", 0, 0, Some([5, 6])),
+            ("fence", "code", 0, 0, Some([7, 10])),
+            ("code_block", "code", 0, 0, Some([11, 12])),
+            ("html_block", "", 0, 0, Some([13, 14])),
+        ]);
+        assert_eq!(tokens[0].markup, "#");
+        assert_eq!(tokens[1].content, "a");
+        assert_eq!(tokens[8].markup, "---");
+        assert_eq!(tokens[9].info, "js x");
+        assert_eq!(tokens[9].markup, "```");
+        assert_eq!(tokens[9].content, "d\n");
+        assert_eq!(tokens[10].content, "e\n");
+        assert!(tokens.iter().all(|t| t.block));
+    }
+
+    #[test]
+    fn inline() {
+        let source = "*a* __b__ ~~c~~ ``d`` &amp;\\*<i>\n[e](u \"t\") ![f](v)<http://g>  \nh";
+        let tokens = to_tokens(&parse(source), source);
+        let children = tokens[1].children.as_ref().unwrap();
+        assert_eq!(tokens[1].content, source);
+        assert_eq!(summary(children), vec![
+            ("em_open", "em", 1, 0, None),
+            ("text", "", 0, 1, None),
+            ("em_close", "em", -1, 0, None),
+            ("text", "", 0, 0, None),
+            ("strong_open", "strong", 1, 0, None),
+            ("text", "", 0, 1, None),
+            ("strong_close", "strong", -1, 0, None),
+            ("text", "", 0, 0, None),
+            ("s_open", "s", 1, 0, None),
+            ("text", "", 0, 1, None),
+            ("s_close", "s", -1, 0, None),
+            ("text", "", 0, 0, None),
+            ("code_inline", "code", 0, 0, None),
+            ("text", "", 0, 0, None),
+            ("html_inline", "", 0, 0, None),
+            ("softbreak", "br", 0, 0, None),
+            ("link_open", "a", 1, 0, None),
+            ("text", "", 0, 1, None),
+            ("link_close", "a", -1, 0, None),
+            ("text", "", 0, 0, None),
+            ("image", "img", 0, 0, None),
+            ("link_open", "a", 1, 0, None),
+            ("text", "", 0, 1, None),
+            ("link_close", "a", -1, 0, None),
+            ("hardbreak", "br", 0, 0, None),
+            ("text", "", 0, 0, None),
+        ]);
+        assert_eq!(children[0].markup, "*");
+        assert_eq!(children[4].markup, "__");
+        assert_eq!(children[12].markup, "``");
+        assert_eq!(children[12].content, "d");
+        assert_eq!(children[13].content, " &*");
+        assert_eq!(children[16].attrs, vec![("href".into(), "u".into()), ("title".into(), "t".into())]);
+        assert_eq!(children[20].attrs, vec![("src".into(), "v".into()), ("alt".into(), "".into())]);
+        assert_eq!(children[20].content, "f");
+        assert_eq!(children[20].children.as_ref().unwrap()[0].content, "f");
+        assert_eq!((children[21].markup.as_str(), children[21].info.as_str()), ("autolink", "auto"));
+        assert!(children.iter().all(|t| !t.block));
+    }
+
+    #[test]
+    fn lists() {
+        let source = "3) a\n4) b\n\n   c\n- d";
+        let tokens = to_tokens(&parse(source), source);
+        assert_eq!(summary(&tokens), vec![
+            ("ordered_list_open", "ol", 1, 0, Some([0, 4])),
+            ("list_item_open", "li", 1, 1, Some([0, 1])),
+            ("paragraph_open", "p", 1, 2, Some([0, 1])),
+            ("inline", "", 0, 3, Some([0, 1])),
+            ("paragraph_close", "p", -1, 2, None),
+            ("list_item_close", "li", -1, 1, None),
+            ("list_item_open", "li", 1, 1, Some([1, 4])),
+            ("paragraph_open", "p", 1, 2, Some([1, 2])),
+            ("inline", "", 0, 3, Some([1, 2])),
+            ("paragraph_close", "p", -1, 2, None),
+            ("paragraph_open", "p", 1, 2, Some([3, 4])),
+            ("inline", "", 0, 3, Some([3, 4])),
+            ("paragraph_close", "p", -1, 2, None),
+            ("list_item_close", "li", -1, 1, None),
+            ("ordered_list_close", "ol", -1, 0, None),
+            ("bullet_list_open", "ul", 1, 0, Some([4, 5])),
+            ("list_item_open", "li", 1, 1, Some([4, 5])),
+            ("paragraph_open", "p", 1, 2, Some([4, 5])),
+            ("inline", "", 0, 3, Some([4, 5])),
+            ("paragraph_close", "p", -1, 2, None),
+            ("list_item_close", "li", -1, 1, None),
+            ("bullet_list_close", "ul", -1, 0, None),
+        ]);
+        assert_eq!(tokens[0].attrs, vec![("start".into(), "3".into())]);
+        assert_eq!((tokens[1].markup.as_str(), tokens[1].info.as_str()), (")", "3"));
+        assert_eq!((tokens[6].markup.as_str(), tokens[6].info.as_str()), (")", "4"));
+        assert!(!tokens[2].hidden);
+        assert!(tokens[17].hidden && tokens[19].hidden);
+    }
+
+    #[test]
+    fn inline_content_without_container_markers() {
+        let content = |source: &str| {
+            let tokens = to_tokens(&parse(source), source);
+            tokens.into_iter().find(|t| t.kind == "inline").unwrap().content
+        };
+        assert_eq!(content("> a\n> b"), "a\nb");
+        assert_eq!(content("> a\nb"), "a\nb");
+        assert_eq!(content("- x\n  y"), "x\ny");
+        assert_eq!(content("- x\n    y"), "x\n  y");
+        assert_eq!(content("1.  x\n    y"), "x\ny");
+        assert_eq!(content("- > a\n  > b"), "a\nb");
+        assert_eq!(content("> - a\n>   *b\n>   c*"), "a\n*b\nc*");
+        assert_eq!(content("a\r\n   b"), "a\n   b");
+    }
+
+    #[test]
+    fn source_map_past_the_end() {
+        let source = ">\ta%";
+        let tokens = to_tokens(&parse(source), source);
+        assert_eq!(tokens[1].map, Some([0, 1]));
+        assert_eq!(tokens[2].kind, "inline");
+    }
+
+    #[test]
+    fn tables() {
+        let source = "| a | b |\n|:-|--|\n| c | d |";
+        let tokens = to_tokens(&parse(source), source);
+        let kinds = tokens.iter().map(|t| t.kind.as_str()).collect::<Vec<_>>();
+        assert_eq!(kinds, [
+            "table_open", "thead_open", "tr_open",
+            "th_open", "inline", "th_close", "th_open", "inline", "th_close",
+            "tr_close", "thead_close", "tbody_open", "tr_open",
+            "td_open", "inline", "td_close", "td_open", "inline", "td_close",
+            "tr_close", "tbody_close", "table_close",
+        ]);
+        assert_eq!(tokens[3].attrs, vec![("style".into(), "text-align:left".into())]);
+        assert!(tokens[6].attrs.is_empty());
+        assert_eq!(tokens[14].content, "c");
+    }
+
+    #[derive(Debug)]
+    struct CustomNode;
+    impl markdown_it::NodeValue for CustomNode {}
+
+    #[test]
+    fn custom_nodes() {
+        let mut ast = parse("*a*");
+        ast.children[0].children[0].replace(CustomNode);
+
+        let tokens = to_tokens(&ast, "*a*");
+        let kinds = tokens[1].children.as_ref().unwrap().iter().map(|t| t.kind.as_str()).collect::<Vec<_>>();
+        assert_eq!(kinds, ["custom_node_open", "text", "custom_node_close"]);
+
+        let mut fmt = TokenExporter::new("*a*");
+        fmt.set_handler::<CustomNode>(|_, fmt| fmt.push(Token::new("mark", "mark", 0)));
+        let tokens = fmt.export(&ast);
+        assert_eq!(tokens[1].children.as_ref().unwrap()[0].kind, "mark");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json() {
+        use markdown_it::renderers::tokens::to_json;
+
+        let source = "`a`";
+        let tokens = to_tokens(&parse(source), source);
+        assert_eq!(to_json(&tokens)[1], serde_json::json!({
+            "type": "inline",
+            "tag": "",
+            "attrs": null,
+            "map": [0, 1],
+            "nesting": 0,
+            "level": 1,
+            "children": [{
+                "type": "code_inline",
+                "tag": "code",
+                "attrs": null,
+                "map": null,
+                "nesting": 0,
+                "level": 0,
+                "children": null,
+                "content": "a",
+                "markup": "`",
+                "info": "",
+                "meta": null,
+                "block": false,
+                "hidden": false,
+            }],
+            "content": "`a`",
+            "markup": "",
+            "info": "",
+            "meta": null,
+            "block": true,
+            "hidden": false,
+        }));
+    }
+}
+
+mod html_import {
+    use markdown_it::importers::html::parse;
+    use markdown_it::plugins::cmark::block::fence::CodeFence;
+    use markdown_it::plugins::html::html_block::HtmlBlock;
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(parse(input).render(), output);
+    }
+
+    #[test]
+    fn empty() {
+        run("", "");
+        run(" \n ", "");
+    }
+
+    #[test]
+    fn paragraphs() {
+        run("<p>a\n  <em>b</em> <i>c</i></p><p><strong>d</strong><b> e </b>\n</p>",
+            "<p>a <em>b</em> <em>c</em></p>\n<p><strong>d</strong><strong> e</strong></p>\n");
+    }
+
+    #[test]
+    fn implied_paragraphs() {
+        run("a<br>\n b<div>c<p>d<div>e</div></div><hr>f",
+            "<p>a<br>\nb</p>\n<p>c</p>\n<p>d</p>\n<p>e</p>\n<hr>\n<p>f</p>\n");
+    }
+
+    #[test]
+    fn headings() {
+        run("<h1>a</h1><H3 class=x>b <s>c</s></H3>", "<h1>a</h1>\n<h3>b <s>c</s></h3>\n");
+    }
+
+    #[test]
+    fn links_and_images() {
+        run(r#"<p><a href="/x?a=1&amp;b=2" title='t'>a</a> <a name="y">b</a> <img src="i.png" alt="c" /></p>"#,
+            "<p><a href=\"/x?a=1&amp;b=2\" title=\"t\">a</a> b <img src=\"i.png\" alt=\"c\"></p>\n");
+    }
+
+    #[test]
+    fn unsafe_urls() {
+        run(r#"<p><a href="javascript:alert(1)">a</a> <img src="data:text/html,x" alt="b"> <img src="JavaScript:x"></p>"#,
+            "<p>a b</p>\n");
+        run(r#"<p><a href="a b">c</a> <img src="data:image/png;base64,x" alt="d"></p>"#,
+            "<p><a href=\"a%20b\">c</a> <img src=\"data:image/png;base64,x\" alt=\"d\"></p>\n");
+    }
+
+    #[test]
+    fn entities() {
+        run("<p>&lt;&amp;&#65;&#x42;&#0;&unknown; &nbsp;</p>", "<p>&lt;&amp;AB\u{FFFD}&amp;unknown; \u{a0}</p>\n");
+    }
+
+    #[test]
+    fn lists() {
+        run("<ul><li>a<li>b<ol start=3><li>c</ol></ul>",
+            "<ul>\n<li>a</li>\n<li>b\n<ol start=\"3\">\n<li>c</li>\n</ol>\n</li>\n</ul>\n");
+        run("<ol>\n<li><p>a</p></li>\n<li>b</li>\n</ol>",
+            "<ol>\n<li>\n<p>a</p>\n</li>\n<li>\n<p>b</p>\n</li>\n</ol>\n");
+    }
+
+    #[test]
+    fn blockquotes() {
+        run("<blockquote>a<p>b</p></blockquote>", "<blockquote>\n<p>a</p>\n<p>b</p>\n</blockquote>\n");
+    }
+
+    #[test]
+    fn code() {
+        run("<p><code>a  &lt;b&gt;</code></p><pre><code class=\"language-rust\">\nfn x() {\n    1 &lt; 2\n}</code></pre>",
+            "<p><code>a &lt;b&gt;</code></p>\n<pre><code class=\"language-rust\">fn x() {\n    1 &lt; 2\n}\n</code></pre>\n");
+
+        let ast = parse("<pre>a<br>b</pre>");
+        let fence = ast.children[0].cast::<CodeFence>().unwrap();
+        assert_eq!(fence.info, "");
+        assert_eq!(fence.content, "a\nb\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("<table><thead><tr><th align=left>a</th><th style=\"text-align: right\">b</th></tr></thead>\
+             <tbody><tr><td><p>c</p><p>d</p><td>e<td>f</tr><tr><td>g</table>",
+            "<table>\n<thead>\n<tr>\n<th style=\"text-align:left\">a</th>\n<th style=\"text-align:right\">b</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td style=\"text-align:left\">c d</td>\n<td style=\"text-align:right\">e</td>\n</tr>\n\
+             <tr>\n<td style=\"text-align:left\">g</td>\n<td style=\"text-align:right\"></td>\n</tr>\n</tbody>\n</table>\n");
+    }
+
+    #[test]
+    fn unknown_elements() {
+        run("<p>a <u>b <em>c</em></u> <!-- d --></p><details><summary>e</summary>f</details>\n<!-- g -->",
+            "<p>a <u>b <em>c</em></u> <!-- d --></p>\n<details><summary>e</summary>f</details>\n<!-- g -->\n");
+
+        let ast = parse("<form><input></form>");
+        assert_eq!(ast.children[0].cast::<HtmlBlock>().unwrap().content, "<form><input></form>\n");
+    }
+
+    #[test]
+    fn documents() {
+        run("<!DOCTYPE html><html><head><title>x</title><style>p{}</style></head>\
+             <body><script>if (a < b) {}</script><p>a</body></html>",
+            "<p><script>if (a < b) {}</script></p>\n<p>a</p>\n");
+    }
+
+    // imported document written as markdown must render the same as the imported one
+    fn run_markdown(input: &str, output: &str) {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::html::add(md);
+        markdown_it::plugins::extra::tables::add(md);
+        markdown_it::plugins::extra::strikethrough::add(md);
+
+        let ast = parse(input);
+        let markdown = markdown_it::renderers::markdown::render(&ast);
+        assert_eq!(markdown, output);
+        assert_eq!(md.parse(&markdown).render(), ast.render());
+    }
+
+    #[test]
+    fn markdown_escaped_text() {
+        run_markdown("<p>&lt;script&gt;alert(1)&lt;/script&gt;</p>", "\\<script\\>alert(1)\\</script\\>\n");
+        run_markdown("<p>*a* &#96;b&#96; a_b_c _d_ [e](f) ~~g~~ &amp;amp;</p>",
+            "\\*a\\* \\`b\\` a_b_c \\_d\\_ \\[e\\](f) \\~\\~g\\~\\~ \\&amp;\n");
+        run_markdown("<p># a</p><p>1. b</p><p>2) c</p><p>&gt; d</p><ul><li>- e</li><li>+ f</li></ul><p>g<br>===</p>",
+            "\\# a\n\n1\\. b\n\n2\\) c\n\n\\> d\n\n- \\- e\n- \\+ f\n\ng\\\n\\===\n");
+        run_markdown("<table><tr><th>a|b</th></tr><tr><td>`c`</td></tr></table>",
+            "| a\\|b  |\n| ----- |\n| \\`c\\` |\n");
+    }
+
+    #[test]
+    fn malformed() {
+        run("<p>a</span> < b <b>c</p>d</b> <a href=\"x", "<p>a &lt; b <strong>c</strong></p>\n<p>d &lt;a href=&quot;x</p>\n");
+    }
+}
+
+mod trivia {
+    use markdown_it::parser::inline::Text;
+    use markdown_it::plugins::trivia::{to_source, Trivia};
+
+    fn parse(input: &str) -> markdown_it::Node {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::html::add(md);
+        markdown_it::plugins::extra::add(md);
+        markdown_it::plugins::trivia::add(md);
+        md.parse(input)
+    }
+
+    fn parts(node: &markdown_it::Node) -> Vec<&str> {
+        node.ext.get::<Trivia>().unwrap().parts.iter().map(|s| s.as_str()).collect()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let source = "\n\n Title\n=====  \n\n1)  a &amp; b\\*\n   c  \n\n\t- d\n\n\n\
+                      > ```  rust\n> fn x() {}\n> ````\n\n| a |  b\n|:-|-\n|c\n\n\
+                      [x]: /url  \"title\"\n\n<div>\n\n*e*__f__ ~~g~~ <i>h</i> [x] ![i](j 'k')  \r\nl\\\nm";
+        assert_eq!(to_source(&parse(source)), source);
+    }
+
+    #[test]
+    fn roundtrip_commonmark_spec() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::html::add(md);
+        markdown_it::plugins::trivia::add(md);
+
+        let fixtures = std::fs::read_to_string("tests/fixtures/commonmark/good.txt").unwrap();
+        let separator = format!("{}\n", "~".repeat(42));
+
+        for example in fixtures.split(&separator).skip(1) {
+            let (header, rest) = example.split_once('\n').unwrap();
+            let input = rest.split("\n.\n").nth(1).unwrap();
+
+            for source in [input.to_owned(), input.to_owned() + "\n"] {
+                assert_eq!(to_source(&md.parse(&source)), source, "{header}");
+            }
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        let ast = parse("\n## a ##  \n\n\n    b\n***\n");
+        assert_eq!(parts(&ast), ["\n", "\n\n\n    ", "\n", "\n"]);
+        assert_eq!(parts(&ast.children[0]), ["## ", " ##  "]);
+        assert_eq!(parts(&ast.children[1]), ["b"]);
+        assert_eq!(parts(&ast.children[2]), ["***"]);
+    }
+
+    #[test]
+    fn inline() {
+        let ast = parse("*a* \\* &amp;");
+        let paragraph = &ast.children[0];
+        assert_eq!(parts(&paragraph.children[0]), ["*", "*"]);
+        assert_eq!(parts(&paragraph.children[2]), ["\\*"]);
+        assert_eq!(parts(&paragraph.children[4]), ["&amp;"]);
+    }
+
+    #[test]
+    fn edits() {
+        let mut ast = parse("# a  b #\n\n- c\n-   *d*\n");
+        let text = &mut ast.children[1].children[1].children[0].children[0];
+        text.cast_mut::<Text>().unwrap().content = "e".to_owned();
+        text.ext.remove::<Trivia>();
+        assert_eq!(to_source(&ast), "# a  b #\n\n- c\n-   *e*\n");
+
+        ast.children[0].ext.remove::<Trivia>();
+        assert_eq!(to_source(&ast), "a  b\n\n- c\n-   *e*\n");
+    }
+}
+
+mod markdown {
+    use markdown_it::renderers::markdown::{format, MarkdownOptions, Wrap};
+
+    fn fmt(input: &str, options: MarkdownOptions) -> String {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::html::add(md);
+        markdown_it::plugins::extra::tables::add(md);
+        markdown_it::plugins::extra::strikethrough::add(md);
+
+        let result = format(md, input, options.clone()).unwrap();
+        assert_eq!(format(md, &result, options).unwrap(), result, "not idempotent");
+        result
+    }
+
+    fn run(input: &str, output: &str) {
+        assert_eq!(fmt(input, MarkdownOptions::default()), output);
+    }
+
+    fn run_wrap(input: &str, wrap: Wrap, output: &str) {
+        assert_eq!(fmt(input, MarkdownOptions { wrap, ..Default::default() }), output);
+    }
+
+    #[test]
+    fn empty() {
+        run("", "");
+    }
+
+    #[test]
+    fn headings() {
+        run("Title\n=====\n\n## Sub *title* ##\n\n###   h3", "# Title\n\n## Sub _title_\n\n### h3\n");
+        run("# a #\\#\n\nb #\n=", "# a #\\#\n\n# b \\#\n");
+        run("a\\\nb\n===", "a\\\nb\n===\n");
+    }
+
+    #[test]
+    fn code() {
+        run("    indented\n\n~~~ rust\nfenced\n~~~", "```\nindented\n```\n\n```rust\nfenced\n```\n");
+        run("````\n```\n````", "````\n```\n````\n");
+        run("~~~ a`b\n~~~", "~~~a`b\n~~~\n");
+        run("`` a`b `` ` `` `", "``a`b`` ` `` `\n");
+    }
+
+    #[test]
+    fn emphasis() {
+        run("*a* __b__ ***c***", "_a_ **b** _**c**_\n");
+        run("foo*bar* a**b**c", "foo*bar* a**b**c\n");
+        run("*a _b* c_", "_a \\_b_ c\\_\n");
+        run("*a*_b_", "_a_*b*\n");
+    }
+
+    #[test]
+    fn lists() {
+        run("* a\n* b\n\n  c\n+ d", "- a\n\n- b\n\n  c\n\n* d\n");
+        run("* a\n* b\n+ c", "- a\n- b\n\n* c\n");
+        run("3) a\n1. b", "3. a\n\n1) b\n");
+        run("- a\n  ***\n- ***", "- a\n  ***\n- ***\n");
+        run("* ---", "- ***\n");
+    }
+
+    #[test]
+    fn blockquotes() {
+        run("> a\nb\n>\n> - c", "> a\n> b\n>\n> - c\n");
+    }
+
+    #[test]
+    fn links() {
+        run("[a](</b c> 't') ![d *e*](f) <http://g.h>", "[a](</b c> \"t\") ![d _e_](f) <http://g.h>\n");
+        run("[a][b]\n\n[b]: /url", "[a](/url)\n");
+        run("[a](/&amp;copy;)", "[a](/\\&copy;)\n");
+    }
+
+    #[test]
+    fn tables() {
+        run("a|b|c\n:-|:-:|-:\nlong cell|`x\\|y`|", "| a         |   b    |   c |\n| :-------- | :----: | --: |\n| long cell | `x\\|y` |     |\n");
+    }
+
+    #[test]
+    fn html() {
+        run("<div>\n*a*\n</div>\n\nb <span\nclass=\"c\">d</span>", "<div>\n*a*\n</div>\n\nb <span\nclass=\"c\">d</span>\n");
+    }
+
+    #[test]
+    fn wrap() {
+        let input = "one two three\nfour - five 1. six";
+        run_wrap(input, Wrap::Preserve, "one two three\nfour - five 1. six\n");
+        run_wrap(input, Wrap::Never, "one two three four - five 1. six\n");
+        run_wrap(input, Wrap::Width(4), "one\ntwo\nthree\nfour\n\\-\nfive\n1\\.\nsix\n");
+        run_wrap("a `b c` [d e](f) _ _ _ g", Wrap::Width(1), "a\n`b c`\n[d\ne](f)\n\\_\n\\_\n\\_\ng\n");
+        run_wrap("aaa\\\nb c", Wrap::Width(20), "aaa\\\nb c\n");
+    }
+
+    #[test]
+    fn meaning_check() {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::extra::tables::add(md);
+
+        // empty `-` item would be taken for a table delimiter row
+        let result = format(md, "- foo\n-   \n- bar", MarkdownOptions::default());
+        assert_eq!(result.unwrap(), "* foo\n*\n* bar\n");
+    }
+
+    #[test]
+    fn escaped_text() {
+        for input in [
+            "&lt;script&gt;alert(1)&lt;/script&gt;", "\\*x\\*", "\\# x", "1\\. x", "\\`tick\\`",
+            "a_b_c", "\\[x\\](url)", "a * b _ c", "[x]", "x\n\\- y", "x\n\\=\\=\\=",
+        ] {
+            for wrap in [Wrap::Preserve, Wrap::Width(1)] {
+                fmt(input, MarkdownOptions { wrap, ..Default::default() });
+            }
+        }
+    }
+
+    #[test]
+    fn literal_text() {
+        use markdown_it::parser::inline::Text;
+
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        markdown_it::plugins::html::add(md);
+        markdown_it::plugins::extra::tables::add(md);
+        markdown_it::plugins::extra::strikethrough::add(md);
+
+        for literal in [
+            "<script>alert(1)</script>", "*x*", "# x", "1. x", "2) x", "`tick`", "a_b_c", "_a_",
+            "[x](url)", "![x](y)", "- x", "+ x", "> x", "a | b", "~~x~~", "&amp;", "a\\",
+        ] {
+            // text node replaced after parsing, so it isn't escaped in the source
+            let mut ast = md.parse("a\nb");
+            let paragraph = &mut ast.children[0];
+            paragraph.children[0].cast_mut::<Text>().unwrap().content = literal.to_owned();
+            paragraph.children[2].cast_mut::<Text>().unwrap().content = literal.to_owned();
+
+            let result = markdown_it::renderers::markdown::render(&ast);
+            assert_eq!(md.parse(&result).render(), ast.render(), "{literal:?} written as {result:?}");
+        }
+
+        let mut ast = md.parse("a\nb");
+        ast.children[0].children[2].cast_mut::<Text>().unwrap().content = "===".to_owned();
+        let result = markdown_it::renderers::markdown::render(&ast);
+        assert_eq!(result, "a\n\\===\n");
+    }
+
+    #[test]
+    fn raw_pre() {
+        let input = "<div>\n<pre>\n\n_a_\nb  c\n</pre>";
+        run_wrap(input, Wrap::Never, "<div>\n<pre>\n\n_a_\nb  c\n</pre>\n");
+        run_wrap(input, Wrap::Width(1), "<div>\n<pre>\n\n_a_\nb  c\n</pre>\n");
+    }
+
+    #[test]
+    fn delimiter_runs() {
+        run("foo******bar*********baz", "foo******bar******\\*\\*\\*baz\n");
+        run("a ***b** c*", "a _**b** c_\n");
+        run_wrap("*foo [bar* baz]", Wrap::Width(10), "_foo\n\\[bar_\nbaz\\]\n");
+    }
+}
+
+mod patch {
+    use markdown_it::patch::{self, PatchError, TextEdit};
+
+    fn parse(input: &str) -> markdown_it::Node {
+        let md = &mut markdown_it::MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        md.parse(input)
+    }
+
+    #[test]
+    fn replace_node() {
+        let source = "> a *b*\n> c\n\nd";
+        let ast = parse(source);
+        let em = &ast.children[0].children[0].children[1];
+        let edit = patch::replace_node(source, em, "**e**").unwrap();
+        assert_eq!(edit, TextEdit::new(5..6, "*e*"));
+        assert_eq!(patch::apply(source, &[edit]).unwrap(), "> a **e**\n> c\n\nd");
+
+        // only changed part is replaced
+        let edit = patch::replace_node(source, &ast.children[1], "dd").unwrap();
+        assert_eq!(edit, TextEdit::new(14..14, "d"));
+    }
+
+    #[test]
+    fn link_url() {
+        let source = "[a *b*](/x 't') ![c](</y z>) [](\n/w) <http://v> [r]\n\n[r]: /u";
+        let ast = parse(source);
+        let inline = &ast.children[0].children;
+        let set = |node, url| patch::apply(source, &[patch::set_link_url(source, node, url).unwrap()]).unwrap();
+
+        assert_eq!(set(&inline[0], "/new"), "[a *b*](/new 't') ![c](</y z>) [](\n/w) <http://v> [r]\n\n[r]: /u");
+        assert_eq!(set(&inline[2], "q(r"), "[a *b*](/x 't') ![c](<q(r>) [](\n/w) <http://v> [r]\n\n[r]: /u");
+        assert_eq!(set(&inline[4], ""), "[a *b*](/x 't') ![c](</y z>) [](\n<>) <http://v> [r]\n\n[r]: /u");
+        assert_eq!(set(&inline[6], "mailto:q@r"), "[a *b*](/x 't') ![c](</y z>) [](\n/w) <mailto:q@r> [r]\n\n[r]: /u");
+
+        assert_eq!(patch::set_link_url(source, &inline[6], "a b"), Err(PatchError::InvalidValue));
+        assert_eq!(patch::set_link_url(source, &inline[8], "/new"), Err(PatchError::NotFound));
+        assert!(matches!(patch::set_link_url(source, &ast.children[0], "/new"), Err(PatchError::UnsupportedNode { .. })));
+    }
+
+    #[test]
+    fn tasks() {
+        let source = "- [ ] a\n-  [X]\n\n> 10) [x] b\n\n* [ ]c\n+ d";
+        let ast = parse(source);
+        let toggle = |item| patch::toggle_task(source, item);
+
+        assert_eq!(toggle(&ast.children[0].children[0]), Ok(TextEdit::new(3..4, "x")));
+        assert_eq!(toggle(&ast.children[0].children[1]), Ok(TextEdit::new(12..13, " ")));
+        assert_eq!(toggle(&ast.children[1].children[0].children[0]), Ok(TextEdit::new(23..24, " ")));
+        assert_eq!(toggle(&ast.children[2].children[0]), Err(PatchError::NotFound));
+        assert_eq!(toggle(&ast.children[3].children[0]), Err(PatchError::NotFound));
+    }
+
+    #[test]
+    fn headings() {
+        let source = "#  a *b* ##\n\nc\nd\n---\n\n###";
+        let ast = parse(source);
+        let set = |node, text| patch::apply(source, &[patch::set_heading_text(source, node, text).unwrap()]).unwrap();
+
+        assert_eq!(set(&ast.children[0], "e"), "#  e ##\n\nc\nd\n---\n\n###");
+        assert_eq!(set(&ast.children[1], "e"), "#  a *b* ##\n\ne\n---\n\n###");
+        assert_eq!(set(&ast.children[2], "e"), "#  a *b* ##\n\nc\nd\n---\n\n### e");
+        assert_eq!(patch::set_heading_text(source, &ast.children[0], "e\nf"), Err(PatchError::InvalidValue));
+    }
+
+    #[test]
+    fn apply() {
+        let edits = [TextEdit::new(4..5, "E"), TextEdit::new(0..1, "A"), TextEdit::new(2..2, "-")];
+        assert_eq!(patch::apply("a b c", &edits).unwrap(), "A -b E");
+        assert_eq!(patch::apply("a b c", &[TextEdit::new(0..2, ""), TextEdit::new(1..3, "")]), Err(PatchError::InvalidEdit));
+        assert_eq!(patch::apply("a", &[TextEdit::new(0..2, "")]), Err(PatchError::InvalidEdit));
+    }
+}
+
+mod reparse {
+    use markdown_it::{MarkdownIt, Node};
+    use markdown_it::patch::{self, TextEdit};
+
+    fn dump(node: &Node) -> String {
+        let mut result = String::new();
+        node.walk(|node, depth| {
+            let map = node.srcmap.map(|map| map.get_byte_offsets());
+            result.push_str(&format!("{}{} {:?}\n", "  ".repeat(depth as usize), node.name(), map));
+        });
+        result + &node.render()
+    }
+
+    // reparse with edit and check that the result is the same as parsing from scratch
+    fn check(md: &MarkdownIt, source: &str, edit: TextEdit) -> Node {
+        let ast = md.reparse(md.parse(source), source, &edit);
+        let expected = md.parse(&patch::apply(source, &[edit]).unwrap());
+        assert_eq!(dump(&ast), dump(&expected));
+        ast
+    }
+
+    fn cmark() -> MarkdownIt {
+        let md = &mut MarkdownIt::new();
+        markdown_it::plugins::cmark::add(md);
+        std::mem::take(md)
+    }
+
+    #[test]
+    fn reuses_blocks() {
+        let md = &cmark();
+        let source = "# a\n\nb *c*\n\n> d\n\n- e\n\n```\nf\n```\n";
+        let mut ast = md.parse(source);
+        for node in ast.children.iter_mut() {
+            node.attrs.push(("data-old", "1".into()));
+        }
+
+        // block before the edit is parsed again as well
+        let ast = md.reparse(ast, source, &TextEdit::new(14..15, "**g**"));
+        assert_eq!(ast.render(), "<h1 data-old=\"1\">a</h1>\n<p>b <em>c</em></p>\n\
+            <blockquote>\n<p><strong>g</strong></p>\n</blockquote>\n<ul data-old=\"1\">\n<li>e</li>\n</ul>\n\
+            <pre><code data-old=\"1\">f\n</code></pre>\n");
+        assert_eq!(ast.children[3].children[0].srcmap.unwrap().get_byte_offsets(), (21, 25));
+
+        let expected = md.parse("# a\n\nb *c*\n\n> **g**\n\n- e\n\n```\nf\n```\n");
+        assert_eq!(dump(&ast).replace(" data-old=\"1\"", ""), dump(&expected));
+    }
+
+    #[test]
+    fn block_structure() {
+        let md = &cmark();
+        let source = "a\n\nb\n\n- c\n- d\n\ne\n\n    f\n";
+        check(md, source, TextEdit::new(2..3, ""));         // join paragraphs
+        check(md, source, TextEdit::new(4..4, "\n==="));    // setext heading
+        check(md, source, TextEdit::new(6..8, ""));         // list item becomes continuation
+        check(md, source, TextEdit::new(13..14, "  e"));    // lazy line in a list item
+        check(md, source, TextEdit::new(0..0, "```\n"));    // fence swallows the rest
+        check(md, source, TextEdit::new(17..20, ""));       // code block becomes paragraph
+        check(md, source, TextEdit::new(22..22, "\n> g"));  // append at the end
+        check(md, source, TextEdit::new(0..22, ""));        // delete everything
+        check(md, "a\r\nb\r\n\r\nc", TextEdit::new(2..2, "\n"));
+    }
+
+    #[test]
+    fn references() {
+        let md = &cmark();
+        let source = "[a] [b]\n\nc\n\n[a]: /x\n";
+        let ast = check(md, source, TextEdit::new(9..10, "[b]: /y"));
+        assert_eq!(ast.render(), "<p><a href=\"/x\">a</a> <a href=\"/y\">b</a></p>\n");
+        let ast = check(md, source, TextEdit::new(12..19, ""));
+        assert_eq!(ast.render(), "<p>[a] [b]</p>\n<p>c</p>\n");
+    }
+
+    #[test]
+    fn core_rules() {
+        let md = &mut cmark();
+        markdown_it::plugins::extra::tables::add(md);
+        markdown_it::plugins::extra::typographer::add(md);
+        #[cfg(feature = "linkify")]
+        markdown_it::plugins::extra::linkify::add(md);
+        check(md, "a -- b\n\n| c |\n|---|\n\nhttps://d.e (c)\n", TextEdit::new(1..1, "..."));
+        check(md, "a -- b\n\n| c |\n|---|\n\nhttps://d.e (c)\n", TextEdit::new(25..25, "f "));
+
+        // sourcepos needs the whole document, so it's parsed again
+        markdown_it::plugins::sourcepos::add(md);
+        let ast = check(md, "a\n\nb\n\nc\n", TextEdit::new(1..1, "\n"));
+        assert_eq!(ast.children[2].attrs, vec![("data-sourcepos", "6:1-6:1".to_owned())]);
+    }
+}
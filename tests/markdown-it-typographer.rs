@@ -1,3 +1,4 @@
+#![cfg(feature = "linkify")]
 fn run(input: &str, output: &str) {
     let output = if output.is_empty() {
         "".to_owned()
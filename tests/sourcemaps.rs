@@ -373,3 +373,22 @@ fn imglink() {
         );
     });
 }
+
+#[test]
+fn emphasis_in_container() {
+    run("> a *b*\n> c **d\n> e**  \n> f", |node, map| {
+        let inline = &node.children[0].children[0].children;
+        assert_eq!(
+            getmap(&inline[1], &map),
+            ((1, 5), (1, 7)),
+        );
+        assert_eq!(
+            getmap(&inline[4], &map),
+            ((2, 5), (3, 5)),
+        );
+        assert_eq!(
+            getmap(&inline[5], &map),
+            ((3, 6), (4, 2)),
+        );
+    });
+}